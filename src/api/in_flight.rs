@@ -0,0 +1,124 @@
+//! Global cap of the requests which an HTTP server handles at the same time.
+//! A burst of requests is refused with 503 Service Unavailable once the cap is reached, instead
+//! of piling up in memory until the finer grained limits, like the verification pool, kick in.
+
+use std::{num::NonZeroUsize, sync::Arc};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, HttpResponse,
+};
+use tokio::sync::Semaphore;
+
+/// Permits of the requests in flight, shared by all the workers of a server.
+/// It must be registered as `web::Data` for `limit_in_flight` to find it.
+#[derive(Debug, Clone, Default)]
+pub struct InFlightLimit {
+    /// One permit per request which may be in flight. Unlimited if absent.
+    permits: Option<Arc<Semaphore>>,
+}
+
+impl InFlightLimit {
+    /// Create the limit.
+    ///
+    /// # Arguments
+    ///
+    /// - `max_in_flight` - Maximum amount of requests in flight, unlimited if `None`.
+    #[must_use]
+    pub fn new(max_in_flight: Option<NonZeroUsize>) -> Self {
+        Self {
+            permits: max_in_flight.map(|max| Arc::new(Semaphore::new(max.get()))),
+        }
+    }
+}
+
+/// Middleware refusing the requests beyond the `InFlightLimit` of the app with 503.
+/// Requests pass through unlimited if the app has no `InFlightLimit`.
+/// To be registered with `actix_web::middleware::from_fn`.
+///
+/// # Errors
+///
+/// If the wrapped service fails.
+pub async fn limit_in_flight(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let permits = req
+        .app_data::<web::Data<InFlightLimit>>()
+        .and_then(|limit| limit.permits.clone());
+    // The permit is held until the response is returned.
+    let _permit = match permits.map(Semaphore::try_acquire_owned) {
+        Some(Err(_)) => {
+            return Ok(req
+                .into_response(
+                    HttpResponse::ServiceUnavailable()
+                        .body("Too many requests in flight, try again later\n"),
+                )
+                .map_into_right_body());
+        }
+        permit => permit,
+    };
+
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use actix_web::{http::StatusCode, middleware::from_fn, test, App};
+    use tokio::sync::Notify;
+
+    #[actix_web::test]
+    async fn test_in_flight_limit() {
+        let release = web::Data::new(Notify::new());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(InFlightLimit::new(NonZeroUsize::new(1))))
+                .app_data(release.clone())
+                .wrap(from_fn(limit_in_flight))
+                .route(
+                    "/slow",
+                    web::get().to(|release: web::Data<Notify>| async move {
+                        release.notified().await;
+                        HttpResponse::Ok().finish()
+                    }),
+                )
+                .route("/fast", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        // The slow request holds the only permit until the fast one is refused.
+        let (slow, fast) = tokio::join!(
+            test::call_service(&app, test::TestRequest::get().uri("/slow").to_request()),
+            async {
+                let fast =
+                    test::call_service(&app, test::TestRequest::get().uri("/fast").to_request())
+                        .await;
+                release.notify_one();
+                fast
+            }
+        );
+        assert_eq!(slow.status(), StatusCode::OK);
+        assert_eq!(fast.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // The permit is returned once the slow request is done.
+        let req = test::TestRequest::get().uri("/fast").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_unlimited() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(limit_in_flight))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+}
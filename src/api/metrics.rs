@@ -0,0 +1,146 @@
+//! Counters describing the activity of the node, exposed in the Prometheus text format.
+//! The counters are atomics, so they can be updated from any handler without locking.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The reason why a submitted vote was rejected, used to label the rejection counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// The choice on the ballot is not allowed in this election.
+    InvalidChoice,
+    /// The access token was not issued by the election authority.
+    InvalidAccessToken,
+    /// The vote signature does not match the vote.
+    InvalidSignature,
+    /// The vote was cast outside of the election period.
+    InvalidTimestamp,
+    /// The vote was submitted while the election was not open.
+    ElectionClosed,
+    /// The exact same vote had already been submitted.
+    Replayed,
+    /// Another vote with the same access token had already been accepted.
+    ReusedAccessToken,
+    /// Any other reason, like a malformed vote.
+    Other,
+}
+
+impl RejectionReason {
+    /// All of the reasons in the order in which they are exported.
+    pub const ALL: [Self; 8] = [
+        Self::InvalidChoice,
+        Self::InvalidAccessToken,
+        Self::InvalidSignature,
+        Self::InvalidTimestamp,
+        Self::ElectionClosed,
+        Self::Replayed,
+        Self::ReusedAccessToken,
+        Self::Other,
+    ];
+
+    /// The value of the `reason` label of the rejection counter.
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::InvalidChoice => "invalid_choice",
+            Self::InvalidAccessToken => "invalid_access_token",
+            Self::InvalidSignature => "invalid_signature",
+            Self::InvalidTimestamp => "invalid_timestamp",
+            Self::ElectionClosed => "election_closed",
+            Self::Replayed => "replayed",
+            Self::ReusedAccessToken => "reused_access_token",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Counters of the node.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Amount of votes which were accepted into the blockchain.
+    votes_accepted: AtomicU64,
+    /// Amount of rejected votes, indexed by the position of the reason in `RejectionReason::ALL`.
+    votes_rejected: [AtomicU64; RejectionReason::ALL.len()],
+}
+
+impl Metrics {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vote_accepted(&self) {
+        self.votes_accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn vote_rejected(&self, reason: RejectionReason) {
+        self.votes_rejected[reason as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn votes_accepted(&self) -> u64 {
+        self.votes_accepted.load(Ordering::Relaxed)
+    }
+
+    #[must_use]
+    pub fn votes_rejected(&self, reason: RejectionReason) -> u64 {
+        self.votes_rejected[reason as usize].load(Ordering::Relaxed)
+    }
+
+    /// Render the counters in the Prometheus text exposition format.
+    ///
+    /// # Arguments
+    ///
+    /// - `chain_heights` - The names of the hosted elections and the current amount of blocks in
+    ///   their blockchains.
+    ///
+    /// # Returns
+    ///
+    /// The text to be served to the metrics scraper.
+    #[must_use]
+    pub fn render(&self, chain_heights: &[(&str, usize)]) -> String {
+        // Writing to a String can't fail, so the results are ignored.
+        let mut text = String::new();
+        let _ = writeln!(
+            text,
+            "# HELP votes_accepted_total Amount of votes accepted into the blockchain."
+        );
+        let _ = writeln!(text, "# TYPE votes_accepted_total counter");
+        let _ = writeln!(text, "votes_accepted_total {}", self.votes_accepted());
+        let _ = writeln!(
+            text,
+            "# HELP votes_rejected_total Amount of rejected votes by reason."
+        );
+        let _ = writeln!(text, "# TYPE votes_rejected_total counter");
+        for reason in RejectionReason::ALL {
+            let _ = writeln!(
+                text,
+                "votes_rejected_total{{reason=\"{}\"}} {}",
+                reason.label(),
+                self.votes_rejected(reason)
+            );
+        }
+        let _ = writeln!(
+            text,
+            "# HELP chain_height Amount of blocks in the blockchain of the election."
+        );
+        let _ = writeln!(text, "# TYPE chain_height gauge");
+        for (election, chain_height) in chain_heights {
+            let _ = writeln!(
+                text,
+                "chain_height{{election=\"{}\"}} {chain_height}",
+                escape_label_value(election)
+            );
+        }
+
+        text
+    }
+}
+
+/// Escape a label value as required by the Prometheus text format.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -1,2 +1,9 @@
+pub mod in_flight;
+pub mod metrics;
+pub mod node_key;
+pub mod proof_of_work;
+pub mod receipt;
 pub mod server;
 pub mod server_cli;
+pub mod state;
+pub mod verification_pool;
@@ -0,0 +1,100 @@
+//! Key pair with which the node signs the results it announces.
+//! The key pair is kept in the data directory, so that the public key of the node stays the same
+//! across restarts and the results signed before a restart can still be tied to the node.
+
+use std::io::Write;
+use std::path::Path;
+
+use thiserror::Error;
+
+use crypto::signature::digital_sign;
+
+use crate::data_paths::create_secret_file;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Failed to access node key file: {}", .0)]
+    FileIO(#[from] std::io::Error),
+    #[error("Node key file must contain the public key and the secret key on separate lines")]
+    Malformed,
+    #[error("Invalid node key: {}", .0)]
+    InvalidKey(#[from] digital_sign::Error),
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// Load the key pair of the node from the file, generating and saving a new one if the file
+/// doesn't exist yet.
+///
+/// # Arguments
+///
+/// - `path` - The file containing the public key and the secret key, one per line.
+///
+/// # Errors
+///
+/// If the file could not be read or created, if it's malformed or if the secret key doesn't
+/// belong to the public key stored with it.
+pub fn load_or_create(path: &Path) -> Result<digital_sign::Signer> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return create(path),
+        Err(e) => return Err(e.into()),
+    };
+    let mut lines = contents.lines();
+    let (Some(public_key), Some(secret_key), None) = (lines.next(), lines.next(), lines.next())
+    else {
+        return Err(Error::Malformed);
+    };
+
+    Ok(digital_sign::Signer::from_secret_key_checked(
+        secret_key.parse()?,
+        &public_key.parse()?,
+    )?)
+}
+
+/// Generate a new key pair and save it to the file.
+fn create(path: &Path) -> Result<digital_sign::Signer> {
+    let signer = digital_sign::Signer::new()?;
+    let mut file = create_secret_file(path)?;
+    writeln!(file, "{}", signer.get_public_key())?;
+    writeln!(file, "{}", signer.get_secret_key())?;
+    file.sync_all()?;
+
+    Ok(signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use process_io::test_utils::TempDir;
+
+    #[test]
+    fn test_load_or_create() {
+        let dir = TempDir::new("node-key");
+        let path = dir.join("node-signer-keys");
+
+        let created = load_or_create(&path).unwrap();
+        let loaded = load_or_create(&path).unwrap();
+        assert_eq!(loaded.get_public_key(), created.get_public_key());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, crate::data_paths::SECRET_FILE_MODE);
+        }
+
+        // A secret key swapped for another one is refused.
+        let other = digital_sign::Signer::new().unwrap();
+        std::fs::write(
+            &path,
+            format!("{}\n{}\n", created.get_public_key(), other.get_secret_key()),
+        )
+        .unwrap();
+        assert!(matches!(
+            load_or_create(&path),
+            Err(Error::InvalidKey(digital_sign::Error::PublicKeyMismatch))
+        ));
+        std::fs::write(&path, format!("{}\n", created.get_public_key())).unwrap();
+        assert!(matches!(load_or_create(&path), Err(Error::Malformed)));
+    }
+}
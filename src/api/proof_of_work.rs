@@ -0,0 +1,217 @@
+//! Issuing and verifying the proof of work challenges, see `protocol::proof_of_work`.
+//! Each challenge can only be used once and expires after `CHALLENGE_TTL`. At most
+//! `MAX_OUTSTANDING_CHALLENGES` can be outstanding at a time, so that requesting challenges
+//! without ever solving them can't exhaust the memory of the node.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ring::rand::{SecureRandom, SystemRandom};
+use thiserror::Error;
+
+use protocol::proof_of_work::meets_difficulty;
+pub use protocol::proof_of_work::{Challenge, CHALLENGE_LEN, SOLUTION_HEADER};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Proof of work solution is missing")]
+    MissingSolution,
+    #[error("Proof of work solution is malformed")]
+    MalformedSolution,
+    #[error("Proof of work challenge is unknown or expired")]
+    UnknownChallenge,
+    #[error("Proof of work solution does not meet the difficulty")]
+    InsufficientWork,
+    #[error("Too many outstanding proof of work challenges")]
+    TooManyChallenges,
+    #[error("Failed to generate challenge")]
+    ChallengeGeneration,
+    #[error("Challenge lock poisoned")]
+    LockPoisoned,
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// How long an issued challenge can be solved for.
+pub const CHALLENGE_TTL: Duration = Duration::from_mins(1);
+
+/// How many unused and unexpired challenges can be outstanding at once.
+pub const MAX_OUTSTANDING_CHALLENGES: usize = 10_000;
+
+/// Issues challenges and verifies their solutions.
+#[derive(Debug, Default)]
+pub struct ProofOfWork {
+    /// Amount of leading zero bits required from solutions, zero disables the proof of work.
+    difficulty: u8,
+    /// The challenges which have not been used yet and the time at which they were issued.
+    issued: Mutex<HashMap<[u8; CHALLENGE_LEN], Instant>>,
+}
+
+impl ProofOfWork {
+    /// Create a new proof of work gate.
+    ///
+    /// # Arguments
+    ///
+    /// - `difficulty` - Amount of leading zero bits required from solutions. Zero disables it.
+    #[must_use]
+    pub fn new(difficulty: u8) -> Self {
+        Self {
+            difficulty,
+            issued: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.difficulty > 0
+    }
+
+    /// Issue a new challenge, which can be solved once before it expires.
+    ///
+    /// # Errors
+    ///
+    /// If the random challenge could not be generated or if `MAX_OUTSTANDING_CHALLENGES` are
+    /// already outstanding.
+    pub fn issue(&self) -> Result<Challenge> {
+        let mut challenge = [0; CHALLENGE_LEN];
+        SystemRandom::new()
+            .fill(&mut challenge)
+            .map_err(|_| Error::ChallengeGeneration)?;
+
+        let now = Instant::now();
+        let mut issued = self.issued.lock().map_err(|_| Error::LockPoisoned)?;
+        if issued.len() >= MAX_OUTSTANDING_CHALLENGES {
+            issued.retain(|_, issued_at| now.duration_since(*issued_at) < CHALLENGE_TTL);
+            if issued.len() >= MAX_OUTSTANDING_CHALLENGES {
+                return Err(Error::TooManyChallenges);
+            }
+        }
+        issued.insert(challenge, now);
+
+        Ok(Challenge {
+            challenge,
+            difficulty: self.difficulty,
+        })
+    }
+
+    /// Verify the solution submitted by a client and use up its challenge.
+    /// Any submission passes if the proof of work is disabled.
+    ///
+    /// # Arguments
+    ///
+    /// - `solution` - The value of the solution header, if it was present.
+    ///
+    /// # Errors
+    ///
+    /// If the solution is missing, malformed, for an unknown or expired challenge or if it does
+    /// not meet the difficulty.
+    pub fn verify(&self, solution: Option<&str>) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let (challenge, nonce) = solution
+            .ok_or(Error::MissingSolution)?
+            .split_once(':')
+            .ok_or(Error::MalformedSolution)?;
+        let challenge: [u8; CHALLENGE_LEN] = BASE64
+            .decode(challenge)
+            .map_err(|_| Error::MalformedSolution)?
+            .try_into()
+            .map_err(|_| Error::MalformedSolution)?;
+        let nonce: u64 = nonce.parse().map_err(|_| Error::MalformedSolution)?;
+
+        // Removing the challenge even if the solution is wrong, so that it can't be brute forced
+        // across multiple submissions.
+        let issued_at = self
+            .issued
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?
+            .remove(&challenge)
+            .ok_or(Error::UnknownChallenge)?;
+        if issued_at.elapsed() >= CHALLENGE_TTL {
+            return Err(Error::UnknownChallenge);
+        }
+        if !meets_difficulty(&challenge, nonce, self.difficulty) {
+            return Err(Error::InsufficientWork);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solution_verification() {
+        let proof_of_work = ProofOfWork::new(8);
+
+        let challenge = proof_of_work.issue().unwrap();
+        assert_eq!(challenge.difficulty, 8);
+        let solution = challenge.solve();
+        proof_of_work.verify(Some(&solution)).unwrap();
+        // Challenges can only be used once.
+        assert!(matches!(
+            proof_of_work.verify(Some(&solution)),
+            Err(Error::UnknownChallenge)
+        ));
+
+        // Finding a nonce which fails the difficulty, so that the test doesn't depend on luck.
+        let challenge = proof_of_work.issue().unwrap();
+        let nonce = (0..=u64::MAX)
+            .find(|nonce| !meets_difficulty(&challenge.challenge, *nonce, 8))
+            .unwrap();
+        let unsolved = format!("{}:{nonce}", BASE64.encode(challenge.challenge));
+        assert!(matches!(
+            proof_of_work.verify(Some(&unsolved)),
+            Err(Error::InsufficientWork)
+        ));
+
+        assert!(matches!(
+            proof_of_work.verify(None),
+            Err(Error::MissingSolution)
+        ));
+        assert!(matches!(
+            proof_of_work.verify(Some("garbage")),
+            Err(Error::MalformedSolution)
+        ));
+        assert!(matches!(
+            proof_of_work.verify(Some(&challenge.solve())),
+            Err(Error::UnknownChallenge)
+        ));
+    }
+
+    #[test]
+    fn test_outstanding_challenge_cap() {
+        let proof_of_work = ProofOfWork::new(8);
+        for _ in 0..MAX_OUTSTANDING_CHALLENGES {
+            proof_of_work.issue().unwrap();
+        }
+        assert!(matches!(
+            proof_of_work.issue(),
+            Err(Error::TooManyChallenges)
+        ));
+
+        // Expiring all but one of the challenges, which should be pruned at the next issue.
+        let mut issued = proof_of_work.issued.lock().unwrap();
+        let challenge = *issued.keys().next().unwrap();
+        let expired = Instant::now().checked_sub(CHALLENGE_TTL).unwrap();
+        issued
+            .values_mut()
+            .for_each(|issued_at| *issued_at = expired);
+        issued.insert(challenge, Instant::now());
+        drop(issued);
+        proof_of_work.issue().unwrap();
+        assert_eq!(proof_of_work.issued.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let proof_of_work = ProofOfWork::default();
+        assert!(!proof_of_work.is_enabled());
+        proof_of_work.verify(None).unwrap();
+    }
+}
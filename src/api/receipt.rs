@@ -0,0 +1,189 @@
+//! Receipts which are returned to the voters after their votes are accepted.
+//! A receipt commits to the id of the vote and the height of the block containing it with a
+//! secret known only to the node, so the voter can later check that the vote is still included
+//! in the blockchain, while the receipt itself does not reveal the choice on the ballot.
+//! The secret is kept in the data directory, so that the receipts stay verifiable across
+//! restarts of the node.
+
+use std::io::Write;
+use std::path::Path;
+
+use ring::{
+    digest,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+use thiserror::Error;
+
+use crypto::commitment::{self, Commitment, HashCommitmentScheme};
+use protocol::vote::Nonce;
+
+use crate::data_paths::create_secret_file;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Failed to generate receipt secret")]
+    SecretGeneration,
+    #[error("Failed to access receipt secret file: {}", .0)]
+    FileIO(#[from] std::io::Error),
+    #[error("Receipt secret file must contain exactly {} bytes", SECRET_LEN)]
+    MalformedSecret,
+    #[error("Receipt was not issued by this node: {}", .0)]
+    InvalidCommitment(#[from] commitment::Error),
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// Length of the secret with which the receipts are committed in bytes.
+const SECRET_LEN: usize = 32;
+
+/// Receipt of an accepted vote.
+#[serde_as]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct VoteReceipt {
+    /// The id of the vote, see `Vote::id`.
+    #[serde_as(as = "Base64")]
+    pub vote_id: Nonce,
+    /// Height of the block containing the vote.
+    pub height: usize,
+    /// Commitment to the vote id and the height.
+    pub commitment: Commitment,
+}
+
+/// Issues receipts and verifies that they were issued by this node.
+pub struct Receipts {
+    /// The secret with which the receipts are committed.
+    secret: [u8; SECRET_LEN],
+}
+
+impl Receipts {
+    /// Create a new receipt issuer with a random secret.
+    ///
+    /// # Errors
+    ///
+    /// If the random secret could not be generated.
+    pub fn new() -> Result<Self> {
+        let mut secret = [0; SECRET_LEN];
+        SystemRandom::new()
+            .fill(&mut secret)
+            .map_err(|_| Error::SecretGeneration)?;
+
+        Ok(Self { secret })
+    }
+
+    /// Load the secret of the receipt issuer from the file, generating and saving a new one if
+    /// the file doesn't exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// - `path` - The file containing the secret.
+    ///
+    /// # Errors
+    ///
+    /// If the file could not be read or created or if it doesn't contain a secret.
+    pub fn load_or_create(path: &Path) -> Result<Self> {
+        let secret = match std::fs::read(path) {
+            Ok(secret) => secret,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let receipts = Self::new()?;
+                let mut file = create_secret_file(path)?;
+                file.write_all(&receipts.secret)?;
+                file.sync_all()?;
+                return Ok(receipts);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            secret: secret.try_into().map_err(|_| Error::MalformedSecret)?,
+        })
+    }
+
+    /// Issue a receipt for a vote included in the block at the height.
+    #[must_use]
+    pub fn issue(&self, vote_id: Nonce, height: usize) -> VoteReceipt {
+        VoteReceipt {
+            vote_id,
+            height,
+            commitment: commitment_scheme().commit(&(vote_id, height), &self.secret),
+        }
+    }
+
+    /// Verify that the receipt was issued by this node and was not altered since.
+    /// Whether the vote is still included in the blockchain must be checked separately.
+    ///
+    /// # Errors
+    ///
+    /// If the commitment does not match the vote id and the height of the receipt.
+    pub fn verify(&self, receipt: &VoteReceipt) -> Result<()> {
+        commitment_scheme().verify(
+            &(receipt.vote_id, receipt.height),
+            &self.secret,
+            &receipt.commitment,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// The commitment scheme of the receipts, which hashes the secret, the vote id and the height.
+/// Created on demand, since the scheme itself is neither `Send` nor `Sync`.
+fn commitment_scheme() -> HashCommitmentScheme<(Nonce, usize), [u8; SECRET_LEN], digest::Digest> {
+    HashCommitmentScheme::new(Box::new(
+        |(vote_id, height): &(Nonce, usize), secret: &[u8; SECRET_LEN]| {
+            let mut context = digest::Context::new(&digest::SHA256);
+            context.update(secret);
+            context.update(vote_id);
+            context.update(&(*height as u64).to_le_bytes());
+            context.finish()
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use process_io::test_utils::TempDir;
+
+    #[test]
+    fn test_receipt() {
+        let receipts = Receipts::new().unwrap();
+        let receipt = receipts.issue([7; 16], 3);
+        receipts.verify(&receipt).unwrap();
+
+        let mut forged = receipt.clone();
+        forged.height = 4;
+        assert!(matches!(
+            receipts.verify(&forged),
+            Err(Error::InvalidCommitment(_))
+        ));
+        // Receipts of other nodes don't verify either.
+        assert!(Receipts::new().unwrap().verify(&receipt).is_err());
+    }
+
+    #[test]
+    fn test_load_or_create() {
+        let dir = TempDir::new("receipt-secret");
+        let path = dir.join("receipt-secret");
+
+        let receipt = Receipts::load_or_create(&path).unwrap().issue([7; 16], 3);
+        // A restarted node still verifies the receipts it issued.
+        Receipts::load_or_create(&path)
+            .unwrap()
+            .verify(&receipt)
+            .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, crate::data_paths::SECRET_FILE_MODE);
+        }
+
+        std::fs::write(&path, [0; SECRET_LEN - 1]).unwrap();
+        assert!(matches!(
+            Receipts::load_or_create(&path),
+            Err(Error::MalformedSecret)
+        ));
+    }
+}
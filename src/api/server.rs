@@ -1,12 +1,33 @@
 use std::net::SocketAddr;
 
-use actix_web::{post, routes, web, App, HttpServer, Responder};
+use actix_web::{
+    get,
+    http::{
+        header::{Accept, Quality},
+        StatusCode,
+    },
+    middleware::from_fn,
+    post, routes, web, App, HttpRequest, HttpResponse, HttpServer, Responder,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL, Engine};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
 use tracing::info;
 use tracing_actix_web::TracingLogger;
 
 use thiserror::Error;
 
-use protocol::vote::Vote;
+use protocol::{
+    candidate_id::CandidateId,
+    vote::{check_api_version, ApiVote, Nonce, Vote, API_VERSION},
+};
+
+use crate::api::in_flight::{limit_in_flight, InFlightLimit};
+use crate::api::proof_of_work::{self, SOLUTION_HEADER};
+use crate::api::receipt::{self, VoteReceipt};
+use crate::api::server_cli::ServerLimits;
+use crate::api::state::{Error as StateError, State, SyncFormat};
+use crate::api::verification_pool;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -15,32 +36,1037 @@ pub enum Error {
 }
 type Result<T> = std::result::Result<T, Error>;
 
-pub async fn run(addr: SocketAddr) -> Result<()> {
-    println!("starting HTTP server at http://localhost:8080");
+/// The maximum amount of entries that can be requested from paginated endpoints at once.
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+/// Status code of votes submitted before the election starts, which is missing from `StatusCode`.
+const TOO_EARLY: u16 = 425;
 
-    HttpServer::new(|| {
+/// Run the HTTP API of the node.
+///
+/// # Arguments
+///
+/// - `addr` - The address to listen on.
+/// - `limits` - Limits of the HTTP server.
+/// - `url_prefix` - Path under which all the endpoints are served, empty to serve them at root.
+/// - `state` - The state of the node.
+///
+/// # Errors
+///
+/// If the server could not bind to the address or failed while running.
+pub async fn run(
+    addr: SocketAddr,
+    limits: ServerLimits,
+    url_prefix: String,
+    state: State,
+) -> Result<()> {
+    println!("starting HTTP server at http://{addr}{url_prefix}");
+    let state = web::Data::new(state);
+    let in_flight_limit = web::Data::new(InFlightLimit::new(limits.max_in_flight));
+
+    let mut server = HttpServer::new(move || {
         App::new()
+            .wrap(from_fn(limit_in_flight))
             // enable logger
             .wrap(TracingLogger::default())
-            .service(greet)
-            .service(vote)
-    })
-    .bind(addr)?
-    .run()
-    .await?;
+            .app_data(state.clone())
+            .app_data(in_flight_limit.clone())
+            .service(web::scope(&url_prefix).configure(services))
+    });
+    if let Some(workers) = limits.workers {
+        server = server.workers(workers.get());
+    }
+    server.backlog(limits.backlog).bind(addr)?.run().await?;
 
     Ok(())
 }
 
+/// Register all the endpoints of the node.
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(greet)
+        .service(health)
+        .service(ready)
+        .service(node_info)
+        .service(handshake)
+        .service(config)
+        .service(candidates)
+        .service(challenge)
+        .service(vote)
+        .service(verify_receipt)
+        .service(inclusion)
+        .service(audit)
+        .service(votes)
+        .service(metrics)
+        .service(tally)
+        .service(signed_tally)
+        .service(summary)
+        .service(sync);
+}
+
 #[routes]
 #[get("/")]
 #[get("/index.html")]
 async fn greet() -> impl Responder {
-    "Hello! Please send a POST request to /vote with a JSON body, containing a public key, a vote, a timestamp, and a signature.\n"
+    "Hello! Please send a POST request to /vote/{election} with a JSON body, containing a version, a voter public key, a choice, a timestamp, an access token, a nonce and a signature.\n"
+}
+
+/// Liveness probe, which succeeds as long as the node is able to respond at all.
+#[get("/health")]
+pub async fn health() -> impl Responder {
+    HttpResponse::Ok().body("OK\n")
+}
+
+/// Readiness probe, which only succeeds once the node has finished its initialization.
+#[get("/ready")]
+pub async fn ready(state: web::Data<State>) -> impl Responder {
+    if state.is_ready() {
+        HttpResponse::Ok().body("Ready\n")
+    } else {
+        HttpResponse::ServiceUnavailable().body("Not ready\n")
+    }
+}
+
+#[get("/node-info")]
+pub async fn node_info(state: web::Data<State>) -> impl Responder {
+    HttpResponse::Ok().json(state.node_info())
+}
+
+/// Query parameters of the handshake endpoint.
+#[derive(Deserialize, Debug)]
+pub struct HandshakeQuery {
+    /// Version of the vote format produced by the client, see `ApiVote`.
+    protocol_version: u32,
+}
+
+/// Answer of the node to the handshake of a client.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Handshake {
+    /// Whether the node accepts votes in the version announced by the client.
+    pub accepted: bool,
+    /// Version of the vote format required by the node.
+    pub protocol_version: u32,
+}
+
+/// Let a client announce the version of the vote format it produces before the voter builds a
+/// vote, so that the voter isn't left with a vote the node would reject for its version.
+#[get("/handshake")]
+pub async fn handshake(query: web::Query<HandshakeQuery>) -> impl Responder {
+    let accepted = check_api_version(query.protocol_version).is_ok();
+    let handshake = Handshake {
+        accepted,
+        protocol_version: API_VERSION,
+    };
+    if accepted {
+        HttpResponse::Ok().json(handshake)
+    } else {
+        HttpResponse::BadRequest().json(handshake)
+    }
+}
+
+#[get("/config/{election}")]
+pub async fn config(election: web::Path<String>, state: web::Data<State>) -> impl Responder {
+    match state.hashed_config(&election) {
+        Ok(hashed_config) => HttpResponse::Ok().json(hashed_config),
+        Err(e) => error_response(&e),
+    }
+}
+
+/// The candidates of an election without the rest of the config, like the authority key.
+#[get("/candidates/{election}")]
+pub async fn candidates(election: web::Path<String>, state: web::Data<State>) -> impl Responder {
+    match state.candidates(&election) {
+        Ok(candidates) => HttpResponse::Ok().json(candidates),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[get("/challenge")]
+pub async fn challenge(state: web::Data<State>) -> impl Responder {
+    match state.issue_challenge() {
+        Ok(challenge) => HttpResponse::Ok().json(challenge),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[post("/vote/{election}")]
+pub async fn vote(
+    req: HttpRequest,
+    election: web::Path<String>,
+    vote: web::Json<ApiVote>,
+    state: web::Data<State>,
+) -> impl Responder {
+    info!("POST: /vote/{election} {vote:?}");
+    let solution = req
+        .headers()
+        .get(SOLUTION_HEADER)
+        .and_then(|value| value.to_str().ok());
+    if let Err(e) = state.check_proof_of_work(solution) {
+        return error_response(&e);
+    }
+    let vote = match Vote::try_from(vote.into_inner()) {
+        Ok(vote) => vote,
+        Err(e) => return error_response(&e.into()),
+    };
+    match state
+        .into_inner()
+        .submit_vote_in_pool(election.into_inner(), vote.clone())
+        .await
+    {
+        Ok(receipt) => HttpResponse::Ok().json(receipt),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[post("/verify-receipt/{election}")]
+pub async fn verify_receipt(
+    election: web::Path<String>,
+    receipt: web::Json<VoteReceipt>,
+    state: web::Data<State>,
+) -> impl Responder {
+    match state.verify_receipt(&election, &receipt) {
+        Ok(()) => HttpResponse::Ok().body("Receipt is valid\n"),
+        Err(e) => error_response(&e),
+    }
+}
+
+/// Prove that a vote is included in a block, so that the voter can verify it independently.
+/// The vote id is the id of the vote encoded as URL safe base64, with or without padding, see
+/// `Vote::id`.
+#[get("/inclusion/{election}/{vote_id}")]
+pub async fn inclusion(
+    path: web::Path<(String, String)>,
+    state: web::Data<State>,
+) -> impl Responder {
+    let (election, vote_id) = path.into_inner();
+    let Some(vote_id) = BASE64_URL
+        .decode(vote_id.trim_end_matches('='))
+        .ok()
+        .and_then(|vote_id| Nonce::try_from(vote_id).ok())
+    else {
+        return HttpResponse::BadRequest().body("Malformed vote id\n");
+    };
+    match state.inclusion_proof(&election, &vote_id) {
+        Ok(proof) => HttpResponse::Ok().json(proof),
+        Err(e) => error_response(&e),
+    }
+}
+
+/// Map state errors to the HTTP responses.
+fn error_response(e: &StateError) -> HttpResponse {
+    match e {
+        StateError::VoteRejected(_)
+        | StateError::ChoiceRejected(_)
+        | StateError::ReplayedVote
+        | StateError::AccessTokenReused
+        | StateError::Receipt(receipt::Error::InvalidCommitment(_))
+        | StateError::ReceiptVoteMissing(_) => HttpResponse::BadRequest().body(e.to_string()),
+        StateError::UnknownElection(_) | StateError::VoteNotFound(_) => {
+            HttpResponse::NotFound().body(e.to_string())
+        }
+        StateError::ProofOfWork(proof_of_work::Error::TooManyChallenges) => {
+            HttpResponse::ServiceUnavailable().body(e.to_string())
+        }
+        // The proof of work is how the node rate limits the votes, so a failed proof of work is
+        // answered the same way, which the clients retry with a new challenge instead of giving
+        // up on the vote.
+        StateError::ProofOfWork(
+            proof_of_work::Error::MissingSolution
+            | proof_of_work::Error::MalformedSolution
+            | proof_of_work::Error::UnknownChallenge
+            | proof_of_work::Error::InsufficientWork,
+        ) => HttpResponse::TooManyRequests().body(e.to_string()),
+        StateError::ElectionEnded(_) => HttpResponse::Forbidden().body(e.to_string()),
+        StateError::VerificationPool(verification_pool::Error::Saturated) => {
+            HttpResponse::ServiceUnavailable().body(e.to_string())
+        }
+        StateError::ElectionNotStarted(_) => {
+            HttpResponse::build(StatusCode::from_u16(TOO_EARLY).unwrap_or(StatusCode::FORBIDDEN))
+                .body(e.to_string())
+        }
+        _ => HttpResponse::InternalServerError().body(format!("Error: {e}")),
+    }
+}
+
+/// Query parameters for paginated endpoints.
+#[derive(Deserialize, Debug)]
+pub struct Page {
+    /// Index of the first item to return.
+    #[serde(default)]
+    from: usize,
+    /// Maximum amount of items to return, capped at `MAX_PAGE_LIMIT`.
+    limit: Option<usize>,
+}
+
+impl Page {
+    fn limit(&self) -> usize {
+        capped_limit(self.limit)
+    }
+}
+
+/// Query parameters for the votes listing endpoint.
+/// Not using `#[serde(flatten)]` with `Page`, since it breaks parsing numbers from query strings.
+#[serde_as]
+#[derive(Deserialize, Debug)]
+pub struct VotesQuery {
+    /// Index of the first matching vote to return.
+    #[serde(default)]
+    from: usize,
+    /// Maximum amount of votes to return, capped at `MAX_PAGE_LIMIT`.
+    limit: Option<usize>,
+    /// If present, only votes for this candidate are returned.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    candidate: Option<CandidateId>,
+}
+
+impl VotesQuery {
+    fn limit(&self) -> usize {
+        capped_limit(self.limit)
+    }
+}
+
+fn capped_limit(limit: Option<usize>) -> usize {
+    limit.unwrap_or(MAX_PAGE_LIMIT).min(MAX_PAGE_LIMIT)
+}
+
+#[get("/audit")]
+pub async fn audit(page: web::Query<Page>, state: web::Data<State>) -> impl Responder {
+    match state.audit_page(page.from, page.limit()) {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {e}")),
+    }
+}
+
+#[get("/votes/{election}")]
+pub async fn votes(
+    election: web::Path<String>,
+    query: web::Query<VotesQuery>,
+    state: web::Data<State>,
+) -> impl Responder {
+    match state.votes_page(
+        &election,
+        query.from,
+        query.limit(),
+        query.candidate.as_ref(),
+    ) {
+        Ok(votes) => HttpResponse::Ok().json(votes.iter().map(ApiVote::from).collect::<Vec<_>>()),
+        Err(e) => error_response(&e),
+    }
+}
+
+/// Query parameters of the sync endpoint.
+#[derive(Deserialize, Debug)]
+pub struct SyncQuery {
+    /// Height of the first block to return.
+    #[serde(default)]
+    from: usize,
+    /// Format of the returned blocks. If absent, the format is chosen by the Accept header of
+    /// the peer, see `negotiate_sync_format`.
+    format: Option<SyncFormat>,
+}
+
+/// Choose the format of the synced blocks by the Accept header of the peer: JSON if the peer
+/// prefers `application/json` to `application/octet-stream` and bincode otherwise. Media types
+/// with a zero quality are not acceptable to the peer, so they're skipped.
+fn negotiate_sync_format(accept: &Accept) -> SyncFormat {
+    let acceptable = Accept(
+        accept
+            .iter()
+            .filter(|item| item.quality > Quality::ZERO)
+            .cloned()
+            .collect(),
+    );
+    acceptable
+        .ranked()
+        .iter()
+        .find_map(|mime| match mime.essence_str() {
+            "application/json" => Some(SyncFormat::Json),
+            "application/octet-stream" => Some(SyncFormat::Bincode),
+            _ => None,
+        })
+        .unwrap_or_default()
 }
 
-#[post("/vote")]
-pub async fn vote(vote: web::Json<Vote>) -> impl Responder {
-    info!("POST: /vote {vote:?}");
-    vote
+/// Stream the blocks of an election to a peer in the binary framing of `decode_block_stream`,
+/// or as a JSON array of blocks if the peer asks for JSON, see `SyncQuery`.
+#[get("/sync/{election}")]
+pub async fn sync(
+    accept: Option<web::Header<Accept>>,
+    election: web::Path<String>,
+    query: web::Query<SyncQuery>,
+    state: web::Data<State>,
+) -> impl Responder {
+    let format = query.format.unwrap_or_else(|| {
+        accept.map_or_else(SyncFormat::default, |accept| negotiate_sync_format(&accept))
+    });
+    match state.sync_blocks(&election, query.from, format) {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .body(bytes),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[get("/tally/{election}")]
+pub async fn tally(election: web::Path<String>, state: web::Data<State>) -> impl Responder {
+    match state.tally(&election) {
+        Ok(tally) => HttpResponse::Ok().json(tally),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[get("/tally/{election}/signed")]
+pub async fn signed_tally(election: web::Path<String>, state: web::Data<State>) -> impl Responder {
+    match state.signed_tally(&election) {
+        Ok(signed_tally) => HttpResponse::Ok().json(signed_tally),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[get("/summary/{election}")]
+pub async fn summary(election: web::Path<String>, state: web::Data<State>) -> impl Responder {
+    match state.summary(&election) {
+        Ok(summary) => HttpResponse::Ok().json(summary),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[get("/metrics")]
+pub async fn metrics(state: web::Data<State>) -> impl Responder {
+    match state.metrics_text() {
+        Ok(text) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(text),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use actix_web::{http::header::Header, test};
+    use crypto::signature::blind_sign;
+    use protocol::{
+        candidate_id::CandidateId,
+        config::{ElectionConfig, HashedConfig},
+        test_utils::{make_config, make_vote_at, TEST_ELECTION},
+    };
+
+    use crate::api::proof_of_work::Challenge;
+    use crate::api::state::{tests::state_for_testing, NodeInfo};
+    use crate::api::verification_pool::VerificationPool;
+    use crate::audit::{Entry, Outcome};
+    use crate::inclusion::InclusionProof;
+    use crate::{decode_block_stream, decode_json_blocks, SignedTally, Tally};
+
+    #[actix_web::test]
+    async fn test_audit_endpoint() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let other_authority = blind_sign::BlindSigner::new().unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state_for_testing(&authority)))
+                .service(vote)
+                .service(audit),
+        )
+        .await;
+        let now = chrono::Utc::now();
+
+        let valid_vote = make_vote_at(&authority, now, CandidateId::new(1));
+        let req = test::TestRequest::post()
+            .uri(&format!("/vote/{TEST_ELECTION}"))
+            .set_json(ApiVote::from(&valid_vote))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        let invalid_vote = make_vote_at(&other_authority, now, CandidateId::new(1));
+        let req = test::TestRequest::post()
+            .uri(&format!("/vote/{TEST_ELECTION}"))
+            .set_json(ApiVote::from(&invalid_vote))
+            .to_request();
+        assert!(test::call_service(&app, req)
+            .await
+            .status()
+            .is_client_error());
+
+        let req = test::TestRequest::get().uri("/audit").to_request();
+        let entries: Vec<Entry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].outcome, Outcome::Accepted);
+        assert!(matches!(entries[1].outcome, Outcome::Rejected(_)));
+
+        let req = test::TestRequest::get()
+            .uri("/audit?from=1&limit=5")
+            .to_request();
+        let entries: Vec<Entry> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_votes_endpoint() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state_for_testing(&authority)))
+                .service(vote)
+                .service(votes),
+        )
+        .await;
+        let now = chrono::Utc::now();
+
+        for candidate in [0, 1, 1] {
+            let new_vote = make_vote_at(&authority, now, CandidateId::new(candidate));
+            let req = test::TestRequest::post()
+                .uri(&format!("/vote/{TEST_ELECTION}"))
+                .set_json(ApiVote::from(&new_vote))
+                .to_request();
+            assert!(test::call_service(&app, req).await.status().is_success());
+        }
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/votes/{TEST_ELECTION}"))
+            .to_request();
+        let listed: Vec<ApiVote> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(listed.len(), 3);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/votes/{TEST_ELECTION}?from=2&limit=5"))
+            .to_request();
+        let listed: Vec<ApiVote> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(listed.len(), 1);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/votes/{TEST_ELECTION}?candidate=1&limit=1"))
+            .to_request();
+        let listed: Vec<ApiVote> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(listed.len(), 1);
+        let listed = Vote::try_from(listed[0].clone()).unwrap();
+        assert_eq!(listed.get_choice(), &CandidateId::new(1).into());
+    }
+
+    #[actix_web::test]
+    async fn test_metrics_endpoint() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let other_authority = blind_sign::BlindSigner::new().unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state_for_testing(&authority)))
+                .service(vote)
+                .service(metrics),
+        )
+        .await;
+        let now = chrono::Utc::now();
+
+        let submitted = [
+            make_vote_at(&authority, now, CandidateId::new(0)),
+            make_vote_at(&authority, now, CandidateId::new(1)),
+            make_vote_at(&other_authority, now, CandidateId::new(1)),
+            make_vote_at(
+                &authority,
+                now - std::time::Duration::from_secs(3600),
+                CandidateId::new(1),
+            ),
+        ];
+        for new_vote in &submitted {
+            let req = test::TestRequest::post()
+                .uri(&format!("/vote/{TEST_ELECTION}"))
+                .set_json(ApiVote::from(new_vote))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        let lines: Vec<&str> = body.lines().collect();
+        assert!(lines.contains(&"votes_accepted_total 2"));
+        assert!(lines.contains(&"votes_rejected_total{reason=\"invalid_access_token\"} 1"));
+        assert!(lines.contains(&"votes_rejected_total{reason=\"invalid_timestamp\"} 1"));
+        assert!(lines.contains(&"votes_rejected_total{reason=\"invalid_signature\"} 0"));
+        assert!(lines.contains(&"chain_height{election=\"test\"} 2"));
+    }
+
+    #[actix_web::test]
+    async fn test_elections_routing() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let mut other_config = make_config(authority.get_public_key().unwrap());
+        other_config.name = "other".to_owned();
+        let state = State::new(
+            vec![
+                make_config(authority.get_public_key().unwrap()),
+                other_config,
+            ],
+            VerificationPool::default(),
+        )
+        .unwrap();
+        let state = web::Data::new(state);
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(node_info)
+                .service(config)
+                .service(vote)
+                .service(tally)
+                .service(signed_tally),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/node-info").to_request();
+        let result: NodeInfo = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(result.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(result.elections, ["other", TEST_ELECTION]);
+        assert_eq!(result.protocol_version, protocol::vote::API_VERSION);
+        assert_eq!(result.public_key, state.node_public_key());
+
+        let req = test::TestRequest::get().uri("/config/other").to_request();
+        let result: HashedConfig = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(result.config.name, "other");
+        assert_eq!(result.config_hash, result.config.hash().unwrap());
+        assert_ne!(
+            result.config_hash,
+            make_config(authority.get_public_key().unwrap())
+                .hash()
+                .unwrap()
+        );
+        let now = chrono::Utc::now();
+
+        for (election, candidate) in [(TEST_ELECTION, 0), (TEST_ELECTION, 0), ("other", 1)] {
+            let new_vote = make_vote_at(&authority, now, CandidateId::new(candidate));
+            let req = test::TestRequest::post()
+                .uri(&format!("/vote/{election}"))
+                .set_json(ApiVote::from(&new_vote))
+                .to_request();
+            assert!(test::call_service(&app, req).await.status().is_success());
+        }
+        let new_vote = make_vote_at(&authority, now, CandidateId::new(0));
+        let req = test::TestRequest::post()
+            .uri("/vote/missing")
+            .set_json(ApiVote::from(&new_vote))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            actix_web::http::StatusCode::NOT_FOUND
+        );
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/tally/{TEST_ELECTION}"))
+            .to_request();
+        let result: Tally = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            result.candidates,
+            [(CandidateId::new(0), 2)].into_iter().collect()
+        );
+        let req = test::TestRequest::get().uri("/tally/other").to_request();
+        let result: Tally = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            result.candidates,
+            [(CandidateId::new(1), 1)].into_iter().collect()
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/tally/other/signed")
+            .to_request();
+        let result: SignedTally = test::call_and_read_body_json(&app, req).await;
+        result.verify(&state.node_public_key()).unwrap();
+        assert_eq!(
+            result.tally.candidates,
+            [(CandidateId::new(1), 1)].into_iter().collect()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_readiness() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let state = web::Data::new(state_for_testing(&authority));
+        let app = test::init_service(
+            App::new()
+                .app_data(state.clone())
+                .service(health)
+                .service(ready),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/health").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        let req = test::TestRequest::get().uri("/ready").to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        state.mark_ready();
+        let req = test::TestRequest::get().uri("/ready").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_handshake() {
+        let app = test::init_service(App::new().service(handshake)).await;
+
+        let uri = format!("/handshake?protocol_version={API_VERSION}");
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let result: Handshake = test::read_body_json(resp).await;
+        assert!(result.accepted);
+
+        // An incompatible client learns the required version before building a vote.
+        let uri = format!("/handshake?protocol_version={}", API_VERSION + 1);
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let result: Handshake = test::read_body_json(resp).await;
+        assert_eq!(
+            result,
+            Handshake {
+                accepted: false,
+                protocol_version: API_VERSION,
+            }
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_candidates() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let state = web::Data::new(state_for_testing(&authority));
+        let app = test::init_service(App::new().app_data(state).service(candidates)).await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/candidates/{TEST_ELECTION}"))
+            .to_request();
+        let result: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let expected = make_config(authority.get_public_key().unwrap()).candidates;
+        assert_eq!(result, serde_json::to_value(&expected).unwrap());
+        // Only the fields of the candidates are exposed, nothing else of the config.
+        for candidate in result.as_array().unwrap() {
+            let mut fields: Vec<_> = candidate.as_object().unwrap().keys().collect();
+            fields.sort();
+            assert_eq!(fields, ["id", "name"]);
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/candidates/missing")
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_url_prefix() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let state = web::Data::new(state_for_testing(&authority));
+        let app = test::init_service(
+            App::new()
+                .app_data(state)
+                .service(web::scope("/election-node").configure(services)),
+        )
+        .await;
+
+        for uri in [
+            "/election-node/health".to_owned(),
+            format!("/election-node/config/{TEST_ELECTION}"),
+        ] {
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        }
+        for uri in ["/health".to_owned(), format!("/config/{TEST_ELECTION}")] {
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            assert_eq!(
+                test::call_service(&app, req).await.status(),
+                StatusCode::NOT_FOUND
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_election_period() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let now = chrono::Utc::now();
+        let minute = std::time::Duration::from_secs(60);
+        let upcoming = ElectionConfig {
+            name: "upcoming".to_owned(),
+            start: now + minute,
+            end: now + 2 * minute,
+            ..make_config(authority.get_public_key().unwrap())
+        };
+        let ended = ElectionConfig {
+            name: "ended".to_owned(),
+            start: now - 2 * minute,
+            end: now - minute,
+            ..make_config(authority.get_public_key().unwrap())
+        };
+        let state = State::new(
+            vec![
+                make_config(authority.get_public_key().unwrap()),
+                upcoming,
+                ended,
+            ],
+            VerificationPool::default(),
+        )
+        .unwrap();
+        let app =
+            test::init_service(App::new().app_data(web::Data::new(state)).service(vote)).await;
+
+        for (election, status) in [
+            ("upcoming", StatusCode::from_u16(TOO_EARLY).unwrap()),
+            (TEST_ELECTION, StatusCode::OK),
+            ("ended", StatusCode::FORBIDDEN),
+        ] {
+            let new_vote = make_vote_at(&authority, now, CandidateId::new(1));
+            let req = test::TestRequest::post()
+                .uri(&format!("/vote/{election}"))
+                .set_json(ApiVote::from(&new_vote))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), status);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_proof_of_work() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(
+                    state_for_testing(&authority).with_proof_of_work(8),
+                ))
+                .service(challenge)
+                .service(vote),
+        )
+        .await;
+        let now = chrono::Utc::now();
+
+        let new_vote = make_vote_at(&authority, now, CandidateId::new(1));
+        let req = test::TestRequest::post()
+            .uri(&format!("/vote/{TEST_ELECTION}"))
+            .set_json(ApiVote::from(&new_vote))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            actix_web::http::StatusCode::TOO_MANY_REQUESTS
+        );
+
+        let req = test::TestRequest::get().uri("/challenge").to_request();
+        let issued: Challenge = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(issued.difficulty, 8);
+        let solution = issued.solve();
+        let req = test::TestRequest::post()
+            .uri(&format!("/vote/{TEST_ELECTION}"))
+            .insert_header((SOLUTION_HEADER, solution.clone()))
+            .set_json(ApiVote::from(&new_vote))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        // A used or expired challenge is not a rejection of the vote, so that clients retry it.
+        let other_vote = make_vote_at(&authority, now, CandidateId::new(2));
+        let req = test::TestRequest::post()
+            .uri(&format!("/vote/{TEST_ELECTION}"))
+            .insert_header((SOLUTION_HEADER, solution))
+            .set_json(ApiVote::from(&other_vote))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            actix_web::http::StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_sync_endpoint() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state_for_testing(&authority)))
+                .service(vote)
+                .service(sync),
+        )
+        .await;
+        let now = chrono::Utc::now();
+
+        let submitted: Vec<Vote> = (0..3)
+            .map(|candidate| make_vote_at(&authority, now, CandidateId::new(candidate)))
+            .collect();
+        for new_vote in &submitted {
+            let req = test::TestRequest::post()
+                .uri(&format!("/vote/{TEST_ELECTION}"))
+                .set_json(ApiVote::from(new_vote))
+                .to_request();
+            assert!(test::call_service(&app, req).await.status().is_success());
+        }
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/sync/{TEST_ELECTION}?from=1"))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let blocks = decode_block_stream::<Vote, _>(body.as_ref())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(
+            blocks[1].values()[0].get_choice(),
+            submitted[2].get_choice()
+        );
+
+        // JSON can be asked for either in the query or in the Accept header.
+        let req = test::TestRequest::get()
+            .uri(&format!("/sync/{TEST_ELECTION}?from=1&format=json"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/json"
+        );
+        let json_blocks = decode_json_blocks::<Vote>(&test::read_body(resp).await).unwrap();
+        let req = test::TestRequest::get()
+            .uri(&format!("/sync/{TEST_ELECTION}?from=1"))
+            .insert_header(("Accept", "application/json"))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(decode_json_blocks::<Vote>(&body).unwrap().len(), 2);
+        let req = test::TestRequest::get()
+            .uri(&format!("/sync/{TEST_ELECTION}?from=1"))
+            .insert_header((
+                "Accept",
+                "text/html, application/json; charset=utf-8; q=0.9",
+            ))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/json"
+        );
+
+        // Both formats carry identical blocks.
+        assert_eq!(json_blocks.len(), blocks.len());
+        for (json_block, block) in json_blocks.iter().zip(&blocks) {
+            assert_eq!(
+                bincode::serialize(json_block).unwrap(),
+                bincode::serialize(block).unwrap()
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_negotiate_sync_format() {
+        for (accept, format) in [
+            ("application/json", SyncFormat::Json),
+            ("application/json; charset=utf-8", SyncFormat::Json),
+            ("application/octet-stream", SyncFormat::Bincode),
+            ("*/*", SyncFormat::Bincode),
+            ("text/html, application/*", SyncFormat::Bincode),
+            (
+                "application/octet-stream; q=0.5, application/json",
+                SyncFormat::Json,
+            ),
+            (
+                "application/json; q=0.5, application/octet-stream",
+                SyncFormat::Bincode,
+            ),
+            ("application/json; q=0", SyncFormat::Bincode),
+            (
+                "application/octet-stream; q=0, application/json; q=0.1",
+                SyncFormat::Json,
+            ),
+        ] {
+            let req = test::TestRequest::default()
+                .insert_header(("Accept", accept))
+                .to_http_request();
+            let accept = <Accept as Header>::parse(&req).unwrap();
+            assert_eq!(negotiate_sync_format(&accept), format, "{accept}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_receipt_endpoint() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state_for_testing(&authority)))
+                .service(vote)
+                .service(verify_receipt),
+        )
+        .await;
+        let now = chrono::Utc::now();
+
+        let mut receipts = Vec::new();
+        for candidate in 0..2 {
+            let new_vote = make_vote_at(&authority, now, CandidateId::new(candidate));
+            let req = test::TestRequest::post()
+                .uri(&format!("/vote/{TEST_ELECTION}"))
+                .set_json(ApiVote::from(&new_vote))
+                .to_request();
+            let receipt: VoteReceipt = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(receipt.vote_id, *new_vote.get_nonce());
+            receipts.push(receipt);
+        }
+        assert_eq!(receipts[1].height, 1);
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/verify-receipt/{TEST_ELECTION}"))
+            .set_json(&receipts[1])
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        // Pointing the receipt at another vote breaks the commitment.
+        let mut forged = receipts[1].clone();
+        forged.vote_id = receipts[0].vote_id;
+        let req = test::TestRequest::post()
+            .uri(&format!("/verify-receipt/{TEST_ELECTION}"))
+            .set_json(&forged)
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_inclusion_endpoint() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state_for_testing(&authority)))
+                .service(vote)
+                .service(inclusion),
+        )
+        .await;
+        let now = chrono::Utc::now();
+
+        let mut submitted = Vec::new();
+        for candidate in 0..3 {
+            let new_vote = make_vote_at(&authority, now, CandidateId::new(candidate));
+            let req = test::TestRequest::post()
+                .uri(&format!("/vote/{TEST_ELECTION}"))
+                .set_json(ApiVote::from(&new_vote))
+                .to_request();
+            assert!(test::call_service(&app, req).await.status().is_success());
+            submitted.push(new_vote);
+        }
+
+        let vote_id = BASE64_URL.encode(submitted[1].get_nonce());
+        let req = test::TestRequest::get()
+            .uri(&format!("/inclusion/{TEST_ELECTION}/{vote_id}"))
+            .to_request();
+        let proof: InclusionProof = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(proof.height, 1);
+        assert!(proof.verify(&submitted[1]).unwrap());
+        assert!(!proof.verify(&submitted[0]).unwrap());
+
+        let unknown = BASE64_URL.encode([0; 16]);
+        let req = test::TestRequest::get()
+            .uri(&format!("/inclusion/{TEST_ELECTION}/{unknown}"))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            actix_web::http::StatusCode::NOT_FOUND
+        );
+        let req = test::TestRequest::get()
+            .uri(&format!("/inclusion/{TEST_ELECTION}/garbage"))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+    }
 }
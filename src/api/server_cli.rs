@@ -3,8 +3,43 @@
 
 // TODO daemonize at least on Unix systems.
 
+use std::num::NonZeroUsize;
+
 use clap::Parser;
-use crypto::signature::blind_sign;
+use process_io::cli::DEFAULT_MAX_HISTORY_ENTRIES;
+
+use crate::api::verification_pool::DEFAULT_POOL_SIZE;
+use crate::DEFAULT_MAX_VOTES_PER_BLOCK;
+
+/// The default maximum amount of pending connections, same as the default of actix.
+pub const DEFAULT_BACKLOG: u32 = 2048;
+
+/// Limits of an HTTP server, which should be tuned to the machine running it.
+/// Shared by the node and the mock election authority.
+#[derive(clap::Args, Clone, Copy, Debug, PartialEq)]
+pub struct ServerLimits {
+    /// Amount of worker threads handling the HTTP requests.
+    /// Defaults to the amount of physical CPU cores.
+    #[clap(long = "workers")]
+    pub workers: Option<NonZeroUsize>,
+    /// Maximum amount of pending connections, after which new connections are refused.
+    #[clap(long = "backlog", default_value_t = DEFAULT_BACKLOG)]
+    pub backlog: u32,
+    /// Maximum amount of requests handled at the same time, beyond which new requests are
+    /// refused with 503 Service Unavailable. Unlimited by default.
+    #[clap(long = "max-in-flight")]
+    pub max_in_flight: Option<NonZeroUsize>,
+}
+
+/// Normalize the URL prefix, so that it's either empty or starts with, but doesn't end with, a slash.
+fn parse_url_prefix(prefix: &str) -> Result<String, String> {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() || prefix.starts_with('/') {
+        Ok(prefix.to_owned())
+    } else {
+        Err(format!("URL prefix must start with a slash: {prefix}"))
+    }
+}
 
 /// Command line arguments for the node.
 /// All the stuff required to start the node.
@@ -13,9 +48,57 @@ pub struct Args {
     /// The address the node will listen on.
     #[clap(short = 'a', long = "address", default_value = "127.0.0.1:8080")]
     pub socket_addr: std::net::SocketAddr,
-    /// The public key of the election authority used to verify that the voters are eligible.
-    #[clap(short = 'p', long = "authority-public-key")]
-    pub authority_pk: blind_sign::PublicKey,
+    /// Path to the JSON file containing the config of the election served by this node,
+    /// or to a directory of such files to host multiple elections.
+    /// Required to run the node, optional when verifying a blockchain file.
+    #[clap(short = 'c', long = "config")]
+    pub config: Option<std::path::PathBuf>,
+    /// Directory in which the node keeps its files, like the logs.
+    #[clap(short = 'd', long = "data-path", default_value = ".")]
+    pub data_path: std::path::PathBuf,
+    /// Maximum amount of votes which are verified at the same time.
+    #[clap(long = "verification-threads", default_value_t = DEFAULT_POOL_SIZE)]
+    pub verification_threads: usize,
+    /// Maximum amount of votes waiting for a free verification thread, beyond which new votes
+    /// are refused with 503 Service Unavailable. Unlimited by default.
+    #[clap(long = "max-queued-verifications")]
+    pub max_queued_verifications: Option<usize>,
+    /// Limits of the HTTP server.
+    #[clap(flatten)]
+    pub server_limits: ServerLimits,
+    /// Path under which all the endpoints are served, like `/election-node`,
+    /// for nodes sharing a gateway with other services. The endpoints are served at root by default.
+    #[clap(long = "url-prefix", default_value = "", value_parser = parse_url_prefix)]
+    pub url_prefix: String,
+    /// Maximum amount of votes in a single block, larger batches of votes are split into
+    /// multiple blocks.
+    #[clap(
+        long = "max-votes-per-block",
+        default_value_t = NonZeroUsize::new(DEFAULT_MAX_VOTES_PER_BLOCK).unwrap()
+    )]
+    pub max_votes_per_block: NonZeroUsize,
+    /// Amount of leading zero bits required from the proof of work submitted with each vote.
+    /// Zero disables the proof of work.
+    #[clap(long = "pow-difficulty", default_value_t = 0)]
+    pub pow_difficulty: u8,
+    /// Keep the blockchain files encrypted, with a key derived from the secret in the key file.
+    #[clap(long = "encrypt-at-rest", requires = "at_rest_key_file")]
+    pub encrypt_at_rest: bool,
+    /// Path to the file containing the secret used for the encryption at rest.
+    #[clap(long = "at-rest-key-file")]
+    pub at_rest_key_file: Option<std::path::PathBuf>,
+    /// Prefix every line of the log with a hash chained to the previous line, so that altering
+    /// or removing lines can be detected with the `verify-log` command.
+    #[clap(long = "hash-chain-logs")]
+    pub hash_chain_logs: bool,
+    /// File to which the results of the commands are appended instead of stdout,
+    /// so that they are kept apart from the logs.
+    #[clap(short = 'o', long = "output")]
+    pub output: Option<std::path::PathBuf>,
+    /// Maximum amount of commands kept in the command history file,
+    /// older commands are dropped when the history is loaded or saved.
+    #[clap(long = "max-history-entries", default_value_t = DEFAULT_MAX_HISTORY_ENTRIES)]
+    pub max_history_entries: usize,
     /// The command to execute. See `Cmd` for more details.
     #[clap(subcommand)]
     pub cmd: Cmd,
@@ -33,4 +116,105 @@ pub enum Cmd {
         /// The address of the existing node.
         peer_socket_addr: std::net::SocketAddr,
     },
+    /// Verify an exported blockchain file and exit.
+    /// Votes are also verified if the election config is provided.
+    #[clap(about = "Verify exported blockchain file")]
+    VerifyFile {
+        /// Path to the exported blockchain file.
+        path: String,
+    },
+    /// Run every check on an exported blockchain file against the election config and exit.
+    /// Unlike `VerifyFile`, all of the problems are reported instead of just the first one.
+    #[clap(about = "Audit exported blockchain file against the election config")]
+    AuditFile {
+        /// Path to the exported blockchain file.
+        path: String,
+    },
+    /// Count the votes of an exported blockchain file against the election config and exit.
+    #[clap(about = "Tally exported blockchain file")]
+    TallyFile {
+        /// Path to the exported blockchain file.
+        path: String,
+    },
+    /// Verify the hashes of a log written with `--hash-chain-logs` and exit.
+    #[clap(about = "Verify hash-chained log file")]
+    VerifyLog {
+        /// Path to the log file.
+        path: std::path::PathBuf,
+    },
+    /// Check that the node is wired correctly for the elections in the config and exit.
+    /// A vote of a throwaway voter and authority is verified against the rules of every
+    /// election and stored in the data directory, encrypted if encryption at rest is enabled.
+    #[clap(about = "Run self test against the election config")]
+    SelfTest {},
+    /// Drop the corrupted blocks from the end of a blockchain file and exit.
+    #[clap(about = "Repair corrupted blockchain file")]
+    RepairFile {
+        /// Path to the blockchain file.
+        path: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_limits() {
+        let args = Args::try_parse_from(["digital-voting", "genesis"]).unwrap();
+        assert_eq!(
+            args.server_limits,
+            ServerLimits {
+                workers: None,
+                backlog: DEFAULT_BACKLOG,
+                max_in_flight: None,
+            }
+        );
+
+        let args = Args::try_parse_from([
+            "digital-voting",
+            "--workers",
+            "3",
+            "--backlog",
+            "64",
+            "--max-in-flight",
+            "100",
+            "genesis",
+        ])
+        .unwrap();
+        assert_eq!(args.server_limits.workers, NonZeroUsize::new(3));
+        assert_eq!(args.server_limits.backlog, 64);
+        assert_eq!(args.server_limits.max_in_flight, NonZeroUsize::new(100));
+
+        assert!(Args::try_parse_from(["digital-voting", "--workers", "0", "genesis"]).is_err());
+    }
+
+    #[test]
+    fn test_max_votes_per_block() {
+        let parse = |args: &[&str]| {
+            Args::try_parse_from(["digital-voting"].iter().chain(args).chain(&["genesis"]))
+                .map(|args| args.max_votes_per_block.get())
+        };
+        assert_eq!(parse(&[]).unwrap(), DEFAULT_MAX_VOTES_PER_BLOCK);
+        assert_eq!(parse(&["--max-votes-per-block", "10"]).unwrap(), 10);
+        assert!(parse(&["--max-votes-per-block", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_url_prefix() {
+        let parse = |prefix| {
+            Args::try_parse_from(["digital-voting", "--url-prefix", prefix, "genesis"])
+                .map(|args| args.url_prefix)
+        };
+        assert_eq!(
+            Args::try_parse_from(["digital-voting", "genesis"])
+                .unwrap()
+                .url_prefix,
+            ""
+        );
+        assert_eq!(parse("/election-node").unwrap(), "/election-node");
+        assert_eq!(parse("/election-node/").unwrap(), "/election-node");
+        assert_eq!(parse("/").unwrap(), "");
+        assert!(parse("election-node").is_err());
+    }
 }
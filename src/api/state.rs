@@ -0,0 +1,1339 @@
+//! Shared state of the node which is accessed by all of the HTTP handlers.
+
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, RwLock,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info_span, instrument};
+
+use crypto::signature::{blind_sign, digital_sign};
+use protocol::{
+    candidate_id::CandidateId,
+    config::{
+        Candidate, ElectionConfig, ElectionContext, Error as ConfigError, HashedConfig,
+        VoteRejection,
+    },
+    timestamp::Timestamp,
+    vote::{Error as VoteError, Nonce, Vote, API_VERSION, MISSING_NONCE},
+};
+
+use crate::api::metrics::{Metrics, RejectionReason};
+use crate::api::proof_of_work::{self, Challenge, ProofOfWork};
+use crate::api::receipt::{self, Receipts, VoteReceipt};
+use crate::api::verification_pool::{self, VerificationPool};
+use crate::audit::{self, AuditLog, Outcome};
+use crate::data_paths::DataPaths;
+use crate::inclusion::InclusionProof;
+use crate::{
+    BlockLog, ElectionSummary, Error as VotingSystemError, SignedTally, Tally, VotingSystem,
+    DEFAULT_MAX_VOTES_PER_BLOCK,
+};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Vote rejected: {}", .0)]
+    VoteRejected(#[from] VoteError),
+    #[error("Vote rejected: {}", .0)]
+    ChoiceRejected(ConfigError),
+    #[error("Vote rejected: the vote had already been submitted")]
+    ReplayedVote,
+    #[error("Vote rejected: the access token had already been used")]
+    AccessTokenReused,
+    #[error("Vote rejected: the election starts at {}", .0)]
+    ElectionNotStarted(Timestamp),
+    #[error("Vote rejected: the election ended at {}", .0)]
+    ElectionEnded(Timestamp),
+    #[error("Invalid election config: {}", .0)]
+    InvalidConfig(#[from] ConfigError),
+    #[error("Invalid authority public key: {}", .0)]
+    InvalidAuthorityKey(#[from] blind_sign::Error),
+    #[error("Failed to generate node key pair: {}", .0)]
+    NodeKey(#[from] digital_sign::Error),
+    #[error(transparent)]
+    Audit(#[from] audit::Error),
+    #[error(transparent)]
+    VotingSystem(#[from] VotingSystemError),
+    #[error(transparent)]
+    VerificationPool(#[from] verification_pool::Error),
+    #[error(transparent)]
+    ProofOfWork(#[from] proof_of_work::Error),
+    #[error(transparent)]
+    Receipt(#[from] receipt::Error),
+    #[error("Vote {} is not in the blockchain", BASE64.encode(.0))]
+    VoteNotFound(Nonce),
+    #[error("The vote of the receipt is not in the block at height {}", .0)]
+    ReceiptVoteMissing(usize),
+    #[error("Unknown election: {}", .0)]
+    UnknownElection(String),
+    #[error("Election {} is configured more than once", .0)]
+    DuplicateElection(String),
+    #[error("State lock is poisoned")]
+    LockPoisoned,
+}
+type Result<T> = std::result::Result<T, Error>;
+
+impl From<VoteRejection> for Error {
+    fn from(rejection: VoteRejection) -> Self {
+        match rejection {
+            VoteRejection::NotStarted(start) => Self::ElectionNotStarted(start),
+            VoteRejection::Ended(end) => Self::ElectionEnded(end),
+            VoteRejection::Choice(e) => Self::ChoiceRejected(e),
+            VoteRejection::Vote(e) => Self::VoteRejected(e),
+            VoteRejection::InvalidConfig(e) => Self::InvalidConfig(e),
+            VoteRejection::InvalidAuthorityKey(e) => Self::InvalidAuthorityKey(e),
+        }
+    }
+}
+
+impl Error {
+    /// Categorize why a vote was rejected for the metrics.
+    fn rejection_reason(&self) -> RejectionReason {
+        match self {
+            Self::ChoiceRejected(_) => RejectionReason::InvalidChoice,
+            Self::ReplayedVote => RejectionReason::Replayed,
+            Self::AccessTokenReused => RejectionReason::ReusedAccessToken,
+            Self::ElectionNotStarted(_) | Self::ElectionEnded(_) => RejectionReason::ElectionClosed,
+            Self::VoteRejected(VoteError::AccessTokenVerification(_)) => {
+                RejectionReason::InvalidAccessToken
+            }
+            Self::VoteRejected(VoteError::SignatureVerification(_)) => {
+                RejectionReason::InvalidSignature
+            }
+            Self::VoteRejected(VoteError::InvalidTimestmap(_)) => RejectionReason::InvalidTimestamp,
+            _ => RejectionReason::Other,
+        }
+    }
+}
+
+/// Indices of the votes which passed verification, used to reject replays and double votes.
+#[derive(Debug, Default)]
+struct SeenVotes {
+    /// Nonces of the seen votes.
+    nonces: HashSet<Nonce>,
+    /// Access tokens of the seen votes.
+    access_tokens: HashSet<Vec<u8>>,
+}
+
+impl SeenVotes {
+    /// Record the vote, unless its nonce or its access token had already been seen.
+    /// Votes without a nonce are only told apart by their access tokens.
+    fn insert(&mut self, vote: &Vote) -> Result<()> {
+        let has_nonce = *vote.get_nonce() != MISSING_NONCE;
+        if has_nonce && self.nonces.contains(vote.get_nonce()) {
+            return Err(Error::ReplayedVote);
+        }
+        if self
+            .access_tokens
+            .contains(vote.get_access_token().as_slice())
+        {
+            return Err(Error::AccessTokenReused);
+        }
+        if has_nonce {
+            self.nonces.insert(*vote.get_nonce());
+        }
+        self.access_tokens.insert(vote.get_access_token().to_vec());
+
+        Ok(())
+    }
+
+    /// Forget a recorded vote, so that it can be submitted again.
+    fn remove(&mut self, vote: &Vote) {
+        if *vote.get_nonce() != MISSING_NONCE {
+            self.nonces.remove(vote.get_nonce());
+        }
+        self.access_tokens
+            .remove(vote.get_access_token().as_slice());
+    }
+}
+
+/// Format in which the blocks are sent to the peers syncing with the node.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncFormat {
+    /// Stream of framed bincode blocks, see `decode_block_stream`.
+    #[default]
+    Bincode,
+    /// JSON array of blocks, see `decode_json_blocks`, for debugging and for the tools which
+    /// can't decode bincode.
+    Json,
+}
+
+impl SyncFormat {
+    /// The content type of the blocks sent in the format.
+    #[must_use]
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Bincode => "application/octet-stream",
+            Self::Json => "application/json",
+        }
+    }
+}
+
+/// Identity of the node, so that observers know which software they are talking to.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct NodeInfo {
+    /// Version of the node software.
+    pub version: String,
+    /// Names of the elections hosted by the node, sorted alphabetically.
+    pub elections: Vec<String>,
+    /// Version of the vote format accepted by the node, see `ApiVote`.
+    pub protocol_version: u32,
+    /// The public key with which the signed tallies of the node can be verified.
+    pub public_key: digital_sign::PublicKey,
+}
+
+/// The state of a single election hosted by the node.
+pub struct ElectionState {
+    /// The rules of the election, with the verifier of the votes derived from them.
+    context: ElectionContext,
+    /// The blockchain containing all the accepted votes of this election.
+    voting_system: RwLock<VotingSystem>,
+    /// Nonces and access tokens of the votes which passed verification.
+    // TODO Gossip the seen votes to the peers once the nodes are connected to each other.
+    seen_votes: Mutex<SeenVotes>,
+    /// The log to which every appended block is written, if the blockchain is persisted.
+    block_log: Option<Mutex<BlockLog>>,
+}
+
+impl ElectionState {
+    /// Create the state for the election described by the config.
+    ///
+    /// # Errors
+    ///
+    /// If the config is invalid or contains an invalid authority public key.
+    pub fn new(config: ElectionConfig) -> Result<Self> {
+        let context = ElectionContext::new(config).map_err(|e| match e {
+            ConfigError::InvalidAuthorityKey(e) => Error::InvalidAuthorityKey(e),
+            e => Error::InvalidConfig(e),
+        })?;
+        Ok(Self {
+            context,
+            voting_system: RwLock::new(VotingSystem::new()),
+            seen_votes: Mutex::new(SeenVotes::default()),
+            block_log: None,
+        })
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &ElectionConfig {
+        self.context.config()
+    }
+
+    /// Check that the election is accepting votes at the time, see `ElectionConfig::check_open`.
+    ///
+    /// # Errors
+    ///
+    /// If the election has not started yet or has already ended.
+    pub fn check_open(&self, now: Timestamp) -> Result<()> {
+        Ok(self.config().check_open(now)?)
+    }
+
+    #[instrument(skip_all)]
+    fn verify_vote(&self, vote: &Vote, now: Timestamp) -> Result<()> {
+        // Every write to the election goes through here, so the period is enforced in one place.
+        self.context.verify_vote(vote, now)?;
+        // Checking for duplicates last, so that invalid votes can't use up the access tokens
+        // or nonces of valid ones.
+        self.seen_votes
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?
+            .insert(vote)
+    }
+
+    /// Undo `verify_vote` for a vote which could not be stored, so that the voter can submit it
+    /// again instead of having it rejected as a duplicate of a vote the node doesn't have.
+    fn forget_vote(&self, vote: &Vote) -> Result<()> {
+        self.seen_votes
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?
+            .remove(vote);
+        Ok(())
+    }
+
+    /// Rebuild the indices of seen votes from the votes in the blockchain.
+    fn rebuild_indices(&mut self) -> Result<()> {
+        let voting_system = self
+            .voting_system
+            .get_mut()
+            .map_err(|_| Error::LockPoisoned)?;
+        let mut seen_votes = SeenVotes::default();
+        for vote in voting_system.votes() {
+            // Duplicates in the chain itself are reported by verifying the chain, not here.
+            let _ = seen_votes.insert(vote);
+        }
+        *self.seen_votes.get_mut().map_err(|_| Error::LockPoisoned)? = seen_votes;
+
+        Ok(())
+    }
+
+    /// Write the blocks from the height onwards to the block log, if the blockchain is persisted.
+    /// Only the new blocks are written, so that appending doesn't slow down as the blockchain
+    /// grows.
+    ///
+    /// # Returns
+    ///
+    /// The size of the log before the blocks were written, with which they can be removed again,
+    /// see `truncate_log`.
+    fn log_blocks(&self, voting_system: &VotingSystem, height: usize) -> Result<Option<u64>> {
+        let Some(block_log) = &self.block_log else {
+            return Ok(None);
+        };
+        let mut block_log = block_log.lock().map_err(|_| Error::LockPoisoned)?;
+        let size = block_log.size();
+        voting_system.append_to_log(&mut block_log, height)?;
+        Ok(Some(size))
+    }
+
+    /// Remove the blocks written to the block log by `log_blocks`.
+    fn truncate_log(&self, size: Option<u64>) -> Result<()> {
+        if let (Some(block_log), Some(size)) = (&self.block_log, size) {
+            block_log
+                .lock()
+                .map_err(|_| Error::LockPoisoned)?
+                .truncate(size)
+                .map_err(VotingSystemError::from)?;
+        }
+        Ok(())
+    }
+
+    fn read_voting_system(&self) -> Result<std::sync::RwLockReadGuard<'_, VotingSystem>> {
+        self.voting_system.read().map_err(|_| Error::LockPoisoned)
+    }
+}
+
+/// Identify the vote in the logs by its id, see `Vote::id`.
+fn vote_id(vote: &Vote) -> String {
+    BASE64.encode(vote.id())
+}
+
+/// The state of the node.
+pub struct State {
+    /// The elections hosted by the node, keyed by their names.
+    elections: HashMap<String, ElectionState>,
+    /// Record of every vote submission and its outcome.
+    audit_log: Mutex<AuditLog>,
+    /// Counters exposed to the operators of the node.
+    metrics: Metrics,
+    /// Pool on which the votes are verified, so that the HTTP workers are not blocked.
+    verification_pool: VerificationPool,
+    /// Client puzzles which must be solved before submitting a vote, disabled by default.
+    proof_of_work: ProofOfWork,
+    /// Key pair with which the node signs the results it announces.
+    /// Generated for every run, unless a persisted one is set with `with_node_signer`.
+    node_signer: digital_sign::Signer,
+    /// Secret from which the key encrypting the saved blockchains is derived, if they are
+    /// encrypted at rest.
+    at_rest_secret: Option<Vec<u8>>,
+    /// Issuer of the receipts returned to the voters.
+    /// Generated for every run, unless a persisted one is set with `with_receipts`.
+    receipts: Receipts,
+    /// Whether the initialization of the node, like loading the blockchains, has finished.
+    ready: AtomicBool,
+    /// Maximum amount of votes in a single block of every election hosted by the node.
+    max_votes_per_block: usize,
+}
+
+impl State {
+    /// Create the node state for the elections described by the configs.
+    ///
+    /// # Arguments
+    ///
+    /// - `configs` - The configs of the elections which the node will host.
+    /// - `verification_pool` - Pool on which submitted votes are verified.
+    ///
+    /// # Errors
+    ///
+    /// If any of the configs is invalid, if two elections share the same name or if the key pair
+    /// or the receipt secret of the node could not be generated.
+    pub fn new(configs: Vec<ElectionConfig>, verification_pool: VerificationPool) -> Result<Self> {
+        let mut elections = HashMap::with_capacity(configs.len());
+        for config in configs {
+            let name = config.name.clone();
+            if elections.contains_key(&name) {
+                return Err(Error::DuplicateElection(name));
+            }
+            elections.insert(name, ElectionState::new(config)?);
+        }
+
+        Ok(Self {
+            elections,
+            audit_log: Mutex::new(AuditLog::new()),
+            metrics: Metrics::new(),
+            verification_pool,
+            proof_of_work: ProofOfWork::default(),
+            node_signer: digital_sign::Signer::new()?,
+            at_rest_secret: None,
+            receipts: Receipts::new()?,
+            ready: AtomicBool::new(false),
+            max_votes_per_block: DEFAULT_MAX_VOTES_PER_BLOCK,
+        })
+    }
+
+    /// Mark the initialization of the node as finished, so that it's reported as ready to
+    /// serve requests. Should be called once the blockchains are loaded and the indices rebuilt.
+    pub fn mark_ready(&self) {
+        self.ready.store(true, Ordering::Release);
+    }
+
+    #[must_use]
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    /// Require vote submissions to solve a proof of work challenge.
+    ///
+    /// # Arguments
+    ///
+    /// - `difficulty` - Amount of leading zero bits required from solutions. Zero disables it.
+    #[must_use]
+    pub fn with_proof_of_work(mut self, difficulty: u8) -> Self {
+        self.proof_of_work = ProofOfWork::new(difficulty);
+        self
+    }
+
+    /// Split large batches of votes into blocks of at most this many votes.
+    /// This is a setting of the node's storage rather than a rule of the election, so it's not
+    /// part of the signed election configs.
+    ///
+    /// # Arguments
+    ///
+    /// - `max_votes_per_block` - Maximum amount of votes in a single block.
+    ///
+    /// # Errors
+    ///
+    /// If any of the election locks is poisoned.
+    pub fn with_max_votes_per_block(mut self, max_votes_per_block: NonZeroUsize) -> Result<Self> {
+        self.max_votes_per_block = max_votes_per_block.get();
+        for election_state in self.elections.values_mut() {
+            election_state
+                .voting_system
+                .get_mut()
+                .map_err(|_| Error::LockPoisoned)?
+                .set_max_votes_per_block(self.max_votes_per_block);
+        }
+
+        Ok(self)
+    }
+
+    /// Sign the announced results with a key pair which outlives the node process,
+    /// see `node_key::load_or_create`.
+    ///
+    /// # Arguments
+    ///
+    /// - `node_signer` - The key pair of the node.
+    #[must_use]
+    pub fn with_node_signer(mut self, node_signer: digital_sign::Signer) -> Self {
+        self.node_signer = node_signer;
+        self
+    }
+
+    /// Issue the receipts with a persisted secret instead of one generated for every run, see
+    /// `Receipts::load_or_create`.
+    ///
+    /// # Arguments
+    ///
+    /// - `receipts` - The issuer of the receipts returned to the voters.
+    #[must_use]
+    pub fn with_receipts(mut self, receipts: Receipts) -> Self {
+        self.receipts = receipts;
+        self
+    }
+
+    /// Persist the audit log instead of keeping it in memory, see `AuditLog::open`.
+    ///
+    /// # Arguments
+    ///
+    /// - `audit_log` - The audit log to which the vote submissions are recorded.
+    #[must_use]
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Mutex::new(audit_log);
+        self
+    }
+
+    /// Keep the saved blockchains encrypted with a key derived from the secret.
+    ///
+    /// # Arguments
+    ///
+    /// - `secret` - The secret supplied by the operator of the node.
+    #[must_use]
+    pub fn with_encryption_at_rest(mut self, secret: Vec<u8>) -> Self {
+        self.at_rest_secret = Some(secret);
+        self
+    }
+
+    /// Issue a proof of work challenge for a vote submission.
+    ///
+    /// # Errors
+    ///
+    /// If the challenge could not be generated.
+    pub fn issue_challenge(&self) -> Result<Challenge> {
+        Ok(self.proof_of_work.issue()?)
+    }
+
+    /// Check the proof of work solution submitted together with a vote.
+    ///
+    /// # Arguments
+    ///
+    /// - `solution` - The value of the solution header, if it was present.
+    ///
+    /// # Errors
+    ///
+    /// If the proof of work is enabled and the solution is not valid.
+    pub fn check_proof_of_work(&self, solution: Option<&str>) -> Result<()> {
+        Ok(self.proof_of_work.verify(solution)?)
+    }
+
+    /// Get the public key with which the results announced by the node can be verified.
+    #[must_use]
+    pub fn node_public_key(&self) -> digital_sign::PublicKey {
+        self.node_signer.get_public_key()
+    }
+
+    /// Describe the node to the observers connecting to it.
+    #[must_use]
+    pub fn node_info(&self) -> NodeInfo {
+        let mut elections: Vec<_> = self.elections.keys().cloned().collect();
+        elections.sort();
+        NodeInfo {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            elections,
+            protocol_version: API_VERSION,
+            public_key: self.node_public_key(),
+        }
+    }
+
+    /// Get the state of an election by its name.
+    ///
+    /// # Errors
+    ///
+    /// If the node does not host an election with this name.
+    pub fn election(&self, election: &str) -> Result<&ElectionState> {
+        self.elections
+            .get(election)
+            .ok_or_else(|| Error::UnknownElection(election.to_owned()))
+    }
+
+    fn election_mut(&mut self, election: &str) -> Result<&mut ElectionState> {
+        self.elections
+            .get_mut(election)
+            .ok_or_else(|| Error::UnknownElection(election.to_owned()))
+    }
+
+    /// Replace the blockchain of an election with one which had been saved to a file.
+    /// The file is decrypted if the node was configured with `with_encryption_at_rest`.
+    /// `rebuild_indices` must be called afterwards, so that votes already in the loaded
+    /// blockchain can't be submitted again.
+    ///
+    /// # Arguments
+    ///
+    /// - `election` - Name of the election to which the blockchain belongs.
+    /// - `filename` - Path to the saved blockchain.
+    ///
+    /// # Errors
+    ///
+    /// If the election is unknown or if the blockchain could not be loaded.
+    pub fn load_chain(&mut self, election: &str, filename: &str) -> Result<()> {
+        let mut voting_system = VotingSystem::load(filename, self.at_rest_secret.as_deref())?;
+        voting_system.set_max_votes_per_block(self.max_votes_per_block);
+        *self
+            .election_mut(election)?
+            .voting_system
+            .get_mut()
+            .map_err(|_| Error::LockPoisoned)? = voting_system;
+
+        Ok(())
+    }
+
+    /// Keep the blockchains of all elections in the data directory, see `DataPaths::block_log`.
+    /// The blocks logged by a previous run of the node are replayed and the indices rebuilt from
+    /// them, after which every appended block is written to the log, so that the accepted votes
+    /// survive a restart of the node.
+    /// The blocks are encrypted if the node was configured with `with_encryption_at_rest`.
+    ///
+    /// # Arguments
+    ///
+    /// - `data_paths` - The data directory of the node.
+    ///
+    /// # Errors
+    ///
+    /// If any of the block logs could not be opened or replayed.
+    pub fn persist_chains(&mut self, data_paths: &DataPaths) -> Result<()> {
+        for (name, election) in &mut self.elections {
+            let (mut voting_system, block_log) = VotingSystem::open_block_log(
+                &data_paths.block_log(name),
+                self.at_rest_secret.as_deref(),
+            )?;
+            voting_system.set_max_votes_per_block(self.max_votes_per_block);
+            *election
+                .voting_system
+                .get_mut()
+                .map_err(|_| Error::LockPoisoned)? = voting_system;
+            election.block_log = Some(Mutex::new(block_log));
+        }
+
+        self.rebuild_indices()
+    }
+
+    /// Rebuild the in-memory indices of all elections from their blockchains, for example after
+    /// the blockchains were loaded on restart. Without this the node would accept votes reusing
+    /// the access tokens of votes which had been accepted before the restart.
+    /// The tallies are always counted from the blockchains, so they need no rebuilding.
+    ///
+    /// # Errors
+    ///
+    /// If any of the election locks is poisoned.
+    pub fn rebuild_indices(&mut self) -> Result<()> {
+        for election_state in self.elections.values_mut() {
+            election_state.rebuild_indices()?;
+        }
+
+        Ok(())
+    }
+
+    /// Verify a submitted vote, append it to the blockchain if it's valid and record the outcome
+    /// in the audit log.
+    /// An accepted vote is only recorded once it's in the blockchain and its block is written to
+    /// the block log, see `persist_chains`. It's removed from the blockchain and the block log
+    /// again if the audit log could not be written to, so that the audit log and the blockchain
+    /// never disagree. A vote which could not be stored can be submitted again.
+    ///
+    /// # Arguments
+    ///
+    /// - `election` - Name of the election in which the vote was cast.
+    /// - `vote` - The submitted vote.
+    ///
+    /// # Returns
+    ///
+    /// The receipt of the accepted vote.
+    ///
+    /// # Errors
+    ///
+    /// If the election is unknown or not open, if the vote is invalid, if it could not be
+    /// appended to the blockchain or if the audit log could not be written to.
+    #[instrument(skip_all, fields(election = %election, vote = %vote_id(vote), outcome))]
+    pub fn submit_vote(&self, election: &str, vote: &Vote) -> Result<VoteReceipt> {
+        let election = self.election(election)?;
+        let span = tracing::Span::current();
+        if let Err(e) = election.verify_vote(vote, chrono::Utc::now()) {
+            self.metrics.vote_rejected(e.rejection_reason());
+            self.audit(&span, vote, &Outcome::Rejected(e.to_string()))?;
+            return Err(e);
+        }
+
+        // TODO Batch accepted votes into blocks instead of creating a block for every vote.
+        let height = info_span!("append_vote").in_scope(|| {
+            let mut voting_system = election
+                .voting_system
+                .write()
+                .map_err(|_| Error::LockPoisoned)?;
+            let height = voting_system.height();
+            if let Err(e) = voting_system.add_votes(vec![vote.clone()]) {
+                election.forget_vote(vote)?;
+                let e = Error::from(e);
+                self.audit(&span, vote, &Outcome::Rejected(e.to_string()))?;
+                return Err(e);
+            }
+            // Still holding the lock, so that the popped block is the one just appended.
+            let log_size = match election.log_blocks(&voting_system, height) {
+                Ok(log_size) => log_size,
+                Err(e) => {
+                    voting_system.pop_block()?;
+                    election.forget_vote(vote)?;
+                    self.audit(&span, vote, &Outcome::Rejected(e.to_string()))?;
+                    return Err(e);
+                }
+            };
+            if let Err(e) = self.audit(&span, vote, &Outcome::Accepted) {
+                // The vote stays if it can't be removed from the block log, since it would come
+                // back from the log on restart, after the node had forgotten it and could accept
+                // it a second time.
+                if let Err(log_e) = election.truncate_log(log_size) {
+                    tracing::error!("Keeping a vote which could not be audited: {log_e}");
+                    return Err(e);
+                }
+                voting_system.pop_block()?;
+                election.forget_vote(vote)?;
+                return Err(e);
+            }
+            Ok(height)
+        })?;
+        self.metrics.vote_accepted();
+        Ok(self.receipts.issue(vote.id(), height))
+    }
+
+    /// Record the outcome of a vote submission in the audit log and in the span of the
+    /// submission.
+    fn audit(&self, span: &tracing::Span, vote: &Vote, outcome: &Outcome) -> Result<()> {
+        span.record("outcome", tracing::field::debug(outcome));
+        info_span!("audit_vote").in_scope(|| {
+            self.audit_log
+                .lock()
+                .map_err(|_| Error::LockPoisoned)?
+                .record(vote, outcome)
+                .map_err(Error::from)
+        })
+    }
+
+    /// Verify that a receipt was issued by this node and that its vote is still in the block
+    /// at the height of the receipt.
+    ///
+    /// # Errors
+    ///
+    /// If the election is unknown, if the receipt was forged or altered or if the vote is not
+    /// in the block.
+    pub fn verify_receipt(&self, election: &str, receipt: &VoteReceipt) -> Result<()> {
+        self.receipts.verify(receipt)?;
+        let voting_system = self.election(election)?.read_voting_system()?;
+        let included = voting_system
+            .block_votes(receipt.height)
+            .is_some_and(|votes| votes.iter().any(|vote| vote.id() == receipt.vote_id));
+        if !included {
+            return Err(Error::ReceiptVoteMissing(receipt.height));
+        }
+
+        Ok(())
+    }
+
+    /// Prove that the vote with the id is included in a block of the election.
+    ///
+    /// # Errors
+    ///
+    /// If the election is unknown or if the vote is not in the blockchain.
+    pub fn inclusion_proof(&self, election: &str, vote_id: &Nonce) -> Result<InclusionProof> {
+        self.election(election)?
+            .read_voting_system()?
+            .inclusion_proof(vote_id)?
+            .ok_or(Error::VoteNotFound(*vote_id))
+    }
+
+    /// Same as `submit_vote`, but the vote is verified on the verification pool,
+    /// so that the async runtime isn't blocked by the verification.
+    ///
+    /// # Errors
+    ///
+    /// If the election is unknown, if the vote is invalid, if the audit log could not be
+    /// written to or if the verification job could not be run.
+    pub async fn submit_vote_in_pool(
+        self: Arc<Self>,
+        election: String,
+        vote: Vote,
+    ) -> Result<VoteReceipt> {
+        let verification_pool = self.verification_pool.clone();
+        verification_pool
+            .run(move || self.submit_vote(&election, &vote))
+            .await?
+    }
+
+    /// Get a page of audit log entries.
+    /// The entries are read without holding the audit log lock, so that reading a page doesn't
+    /// block the vote submissions.
+    ///
+    /// # Errors
+    ///
+    /// If the audit log lock is poisoned or if the entries could not be read.
+    pub fn audit_page(&self, from: usize, limit: usize) -> Result<Vec<audit::Entry>> {
+        let entries = self
+            .audit_log
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?
+            .entries()?;
+        Ok(entries
+            .skip(from)
+            .take(limit)
+            .collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Render the metrics of the node in the Prometheus text format.
+    ///
+    /// # Errors
+    ///
+    /// If a blockchain lock is poisoned.
+    pub fn metrics_text(&self) -> Result<String> {
+        let mut chain_heights = self
+            .elections
+            .iter()
+            .map(|(name, election)| Ok((name.as_str(), election.read_voting_system()?.height())))
+            .collect::<Result<Vec<_>>>()?;
+        chain_heights.sort_unstable();
+        Ok(self.metrics.render(&chain_heights))
+    }
+
+    /// Get a page of the accepted votes of an election.
+    ///
+    /// # Arguments
+    ///
+    /// - `election` - Name of the election.
+    /// - `from` - Index of the first matching vote to return.
+    /// - `limit` - Maximum amount of votes to return.
+    /// - `candidate` - If present, only votes for this candidate are returned.
+    ///
+    /// # Errors
+    ///
+    /// If the election is unknown or if the blockchain lock is poisoned.
+    pub fn votes_page(
+        &self,
+        election: &str,
+        from: usize,
+        limit: usize,
+        candidate: Option<&CandidateId>,
+    ) -> Result<Vec<Vote>> {
+        Ok(self
+            .election(election)?
+            .read_voting_system()?
+            .votes_page(from, limit, candidate))
+    }
+
+    /// Tally the accepted votes of an election.
+    ///
+    /// # Errors
+    ///
+    /// If the election is unknown or if the blockchain lock is poisoned.
+    pub fn tally(&self, election: &str) -> Result<Tally> {
+        Ok(self
+            .election(election)?
+            .read_voting_system()?
+            .tally()
+            .clone())
+    }
+
+    /// Encode the blocks of an election from the height onwards for streaming them to a peer.
+    ///
+    /// # Arguments
+    ///
+    /// - `election` - The name of the election.
+    /// - `from` - Height of the first block to encode.
+    /// - `format` - The format requested by the peer.
+    ///
+    /// # Errors
+    ///
+    /// If the election is unknown or if the blocks could not be encoded.
+    pub fn sync_blocks(&self, election: &str, from: usize, format: SyncFormat) -> Result<Vec<u8>> {
+        let election = self.election(election)?;
+        let voting_system = election.read_voting_system()?;
+        Ok(match format {
+            SyncFormat::Bincode => voting_system.encode_blocks_from(from)?,
+            SyncFormat::Json => voting_system.encode_json_blocks_from(from)?,
+        })
+    }
+
+    /// Get the config of an election together with its hash, so that the clients can detect
+    /// whether their config differs from the one of the node.
+    ///
+    /// # Errors
+    ///
+    /// If the election is unknown or if the config could not be hashed.
+    pub fn hashed_config(&self, election: &str) -> Result<HashedConfig> {
+        Ok(HashedConfig::new(
+            self.election(election)?.config().clone(),
+        )?)
+    }
+
+    /// Get the candidates of an election, which is all the clients need to render the ballot.
+    ///
+    /// # Errors
+    ///
+    /// If the election is unknown.
+    pub fn candidates(&self, election: &str) -> Result<Vec<Candidate>> {
+        Ok(self.election(election)?.config().candidates.clone())
+    }
+
+    /// Summarize the turnout and the results of an election.
+    ///
+    /// # Errors
+    ///
+    /// If the election is unknown.
+    pub fn summary(&self, election: &str) -> Result<ElectionSummary> {
+        Ok(self.election(election)?.read_voting_system()?.summary())
+    }
+
+    /// Count the votes of an election and sign the results with the key of the node.
+    ///
+    /// # Errors
+    ///
+    /// If the election is unknown or if the votes could not be counted.
+    pub fn signed_tally(&self, election: &str) -> Result<SignedTally> {
+        Ok(self.tally(election)?.sign(&self.node_signer))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use process_io::test_utils::TempDir;
+    use protocol::{
+        choice::WriteIn,
+        test_utils::{make_access_token, make_config, make_vote_at, TEST_ELECTION},
+    };
+
+    pub(crate) fn state_for_testing(blind_signer: &blind_sign::BlindSigner) -> State {
+        State::new(
+            vec![make_config(blind_signer.get_public_key().unwrap())],
+            VerificationPool::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_audit_accepted_and_rejected() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let other_authority = blind_sign::BlindSigner::new().unwrap();
+        let state = state_for_testing(&authority);
+        let now = chrono::Utc::now();
+
+        let valid_vote = make_vote_at(&authority, now, CandidateId::new(1));
+        let invalid_vote = make_vote_at(&other_authority, now, CandidateId::new(2));
+
+        state.submit_vote(TEST_ELECTION, &valid_vote).unwrap();
+        assert!(state.submit_vote(TEST_ELECTION, &invalid_vote).is_err());
+
+        let entries = state.audit_page(0, 10).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].outcome, Outcome::Accepted);
+        assert!(matches!(entries[1].outcome, Outcome::Rejected(_)));
+
+        let audit_log = state.audit_log.lock().unwrap();
+        audit_log.validate().unwrap();
+    }
+
+    #[test]
+    fn test_resubmit_unstored_vote() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let dir = TempDir::new("unstored-vote");
+        let data_paths = DataPaths::new(dir.join("data")).unwrap();
+        let mut state = state_for_testing(&authority)
+            .with_audit_log(AuditLog::read_only_for_testing(&dir.join("audit.log")));
+        state.persist_chains(&data_paths).unwrap();
+        let logged_height = || {
+            VotingSystem::open_block_log(&data_paths.block_log(TEST_ELECTION), None)
+                .unwrap()
+                .0
+                .height()
+        };
+        let vote = make_vote_at(&authority, chrono::Utc::now(), CandidateId::new(1));
+        let height = || {
+            state
+                .election(TEST_ELECTION)
+                .unwrap()
+                .read_voting_system()
+                .unwrap()
+                .height()
+        };
+
+        // The vote is taken out of the blockchain again, since it could not be audited.
+        assert!(matches!(
+            state.submit_vote(TEST_ELECTION, &vote),
+            Err(Error::Audit(_))
+        ));
+        assert_eq!(height(), 0);
+        assert_eq!(logged_height(), 0);
+        assert!(state.tally(TEST_ELECTION).unwrap().candidates.is_empty());
+
+        // Neither its nonce nor its access token count as used.
+        *state.audit_log.lock().unwrap() = AuditLog::new();
+        state.submit_vote(TEST_ELECTION, &vote).unwrap();
+        assert_eq!(height(), 1);
+        assert_eq!(logged_height(), 1);
+    }
+
+    #[test]
+    fn test_replayed_vote() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let state = state_for_testing(&authority);
+        let now = chrono::Utc::now();
+
+        let vote = make_vote_at(&authority, now, CandidateId::new(1));
+        state.submit_vote(TEST_ELECTION, &vote).unwrap();
+        assert!(matches!(
+            state.submit_vote(TEST_ELECTION, &vote),
+            Err(Error::ReplayedVote)
+        ));
+        assert_eq!(state.metrics.votes_accepted(), 1);
+        assert_eq!(state.metrics.votes_rejected(RejectionReason::Replayed), 1);
+        assert_eq!(state.tally(TEST_ELECTION).unwrap().candidates.len(), 1);
+    }
+
+    #[test]
+    fn test_missing_nonce() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let lenient_config = ElectionConfig {
+            name: "lenient".to_owned(),
+            allow_missing_nonces: true,
+            ..make_config(authority.get_public_key().unwrap())
+        };
+        let state = State::new(
+            vec![
+                make_config(authority.get_public_key().unwrap()),
+                lenient_config,
+            ],
+            VerificationPool::default(),
+        )
+        .unwrap();
+        let now = chrono::Utc::now();
+        let vote_without_nonce = || {
+            let signer = digital_sign::Signer::new().unwrap();
+            let access_token = make_access_token(&authority, &signer);
+            Vote::new_with_nonce(
+                &signer,
+                CandidateId::new(1),
+                now,
+                &access_token,
+                MISSING_NONCE,
+            )
+            .unwrap()
+        };
+
+        let vote = vote_without_nonce();
+        assert!(matches!(
+            state.submit_vote(TEST_ELECTION, &vote),
+            Err(Error::VoteRejected(VoteError::MissingNonce))
+        ));
+
+        // Votes without a nonce don't collide with each other, but replays are still caught.
+        let other_vote = vote_without_nonce();
+        let receipt = state.submit_vote("lenient", &vote).unwrap();
+        let other_receipt = state.submit_vote("lenient", &other_vote).unwrap();
+        assert!(matches!(
+            state.submit_vote("lenient", &vote),
+            Err(Error::AccessTokenReused)
+        ));
+
+        // Their receipts and inclusion proofs refer to each of them separately.
+        assert_ne!(receipt.vote_id, MISSING_NONCE);
+        assert_ne!(receipt.vote_id, other_receipt.vote_id);
+        state.verify_receipt("lenient", &receipt).unwrap();
+        state.verify_receipt("lenient", &other_receipt).unwrap();
+        let swapped = state.receipts.issue(other_receipt.vote_id, receipt.height);
+        assert!(matches!(
+            state.verify_receipt("lenient", &swapped),
+            Err(Error::ReceiptVoteMissing(0))
+        ));
+        for (receipt, vote) in [(receipt, vote), (other_receipt, other_vote)] {
+            let proof = state.inclusion_proof("lenient", &receipt.vote_id).unwrap();
+            assert_eq!(proof.height, receipt.height);
+            assert!(proof.verify(&vote).unwrap());
+        }
+        assert!(matches!(
+            state.inclusion_proof("lenient", &MISSING_NONCE),
+            Err(Error::VoteNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_election_period() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let state = state_for_testing(&authority);
+        let election = state.election(TEST_ELECTION).unwrap();
+        let config = election.config().clone();
+        let second = std::time::Duration::from_secs(1);
+
+        assert!(matches!(
+            election.check_open(config.start - second),
+            Err(Error::ElectionNotStarted(start)) if start == config.start
+        ));
+        election.check_open(config.start).unwrap();
+        election.check_open(config.end).unwrap();
+        assert!(matches!(
+            election.check_open(config.end + second),
+            Err(Error::ElectionEnded(end)) if end == config.end
+        ));
+
+        // Votes with valid timestamps are still refused outside of the period.
+        let now = chrono::Utc::now();
+        let ended = ElectionConfig {
+            start: now - 60 * second,
+            end: now - second,
+            max_clock_skew_secs: 60,
+            signature: None,
+            ..config
+        };
+        let state = State::new(vec![ended], VerificationPool::default()).unwrap();
+        let vote = make_vote_at(&authority, now, CandidateId::new(1));
+        assert!(matches!(
+            state.submit_vote(TEST_ELECTION, &vote),
+            Err(Error::ElectionEnded(_))
+        ));
+        assert_eq!(
+            state
+                .metrics
+                .votes_rejected(RejectionReason::ElectionClosed),
+            1
+        );
+        assert!(state.tally(TEST_ELECTION).unwrap().candidates.is_empty());
+    }
+
+    #[test]
+    fn test_write_ins() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let now = chrono::Utc::now();
+        let write_in = WriteIn::new("Jane Doe").unwrap();
+
+        let state = state_for_testing(&authority);
+        let vote = make_vote_at(&authority, now, write_in.clone());
+        assert!(matches!(
+            state.submit_vote(TEST_ELECTION, &vote),
+            Err(Error::ChoiceRejected(ConfigError::WriteInsNotAllowed))
+        ));
+        let vote = make_vote_at(&authority, now, CandidateId::new(3));
+        assert!(matches!(
+            state.submit_vote(TEST_ELECTION, &vote),
+            Err(Error::ChoiceRejected(ConfigError::UnknownCandidate(_)))
+        ));
+
+        let mut config = make_config(authority.get_public_key().unwrap());
+        config.allow_write_ins = true;
+        let state = State::new(vec![config], VerificationPool::default()).unwrap();
+        let vote = make_vote_at(&authority, now, write_in);
+        state.submit_vote(TEST_ELECTION, &vote).unwrap();
+    }
+
+    #[test]
+    fn test_multiple_elections() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let now = chrono::Utc::now();
+        let mut other_config = make_config(authority.get_public_key().unwrap());
+        other_config.name = "other".to_owned();
+        let state = State::new(
+            vec![
+                make_config(authority.get_public_key().unwrap()),
+                other_config.clone(),
+            ],
+            VerificationPool::default(),
+        )
+        .unwrap();
+
+        for candidate in [0, 0, 1] {
+            let vote = make_vote_at(&authority, now, CandidateId::new(candidate));
+            state.submit_vote(TEST_ELECTION, &vote).unwrap();
+        }
+        let vote = make_vote_at(&authority, now, CandidateId::new(2));
+        state.submit_vote("other", &vote).unwrap();
+
+        assert_eq!(
+            state.votes_page(TEST_ELECTION, 0, 10, None).unwrap().len(),
+            3
+        );
+        assert_eq!(state.votes_page("other", 0, 10, None).unwrap().len(), 1);
+        let tally = state.tally(TEST_ELECTION).unwrap();
+        assert_eq!(tally.candidates.get(&CandidateId::new(0)), Some(&2));
+        assert_eq!(tally.candidates.get(&CandidateId::new(1)), Some(&1));
+        assert_eq!(tally.candidates.get(&CandidateId::new(2)), None);
+        let tally = state.tally("other").unwrap();
+        assert_eq!(tally.candidates.len(), 1);
+        assert_eq!(tally.candidates.get(&CandidateId::new(2)), Some(&1));
+
+        assert!(matches!(
+            state.submit_vote("missing", &vote),
+            Err(Error::UnknownElection(_))
+        ));
+        assert!(matches!(
+            State::new(
+                vec![other_config.clone(), other_config],
+                VerificationPool::default()
+            ),
+            Err(Error::DuplicateElection(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_receipt() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let state = state_for_testing(&authority);
+        let vote = make_vote_at(&authority, chrono::Utc::now(), CandidateId::new(1));
+        let receipt = state.submit_vote(TEST_ELECTION, &vote).unwrap();
+        assert_eq!(receipt.height, 0);
+        state.verify_receipt(TEST_ELECTION, &receipt).unwrap();
+
+        // Correctly committed, but for a vote which is not in the block.
+        let missing = state.receipts.issue([0; 16], 0);
+        assert!(matches!(
+            state.verify_receipt(TEST_ELECTION, &missing),
+            Err(Error::ReceiptVoteMissing(0))
+        ));
+        let mut forged = receipt;
+        forged.height = 1;
+        assert!(matches!(
+            state.verify_receipt(TEST_ELECTION, &forged),
+            Err(Error::Receipt(receipt::Error::InvalidCommitment(_)))
+        ));
+    }
+
+    #[test]
+    fn test_rebuild_indices() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let now = chrono::Utc::now();
+        let voter = digital_sign::Signer::new().unwrap();
+        let access_token = make_access_token(&authority, &voter);
+        let dir = TempDir::new("rebuild");
+        let chain_file = dir.file("rebuild.chain");
+
+        let state = state_for_testing(&authority);
+        let vote = Vote::new(&voter, CandidateId::new(1), now, &access_token).unwrap();
+        state.submit_vote(TEST_ELECTION, &vote).unwrap();
+        state
+            .election(TEST_ELECTION)
+            .unwrap()
+            .read_voting_system()
+            .unwrap()
+            .save_to_file(&chain_file)
+            .unwrap();
+
+        // The vote reuses the access token, but differs in the nonce.
+        let double_vote = Vote::new(&voter, CandidateId::new(2), now, &access_token).unwrap();
+        let mut state = state_for_testing(&authority);
+        state.load_chain(TEST_ELECTION, &chain_file).unwrap();
+        state.rebuild_indices().unwrap();
+        assert!(matches!(
+            state.submit_vote(TEST_ELECTION, &double_vote),
+            Err(Error::AccessTokenReused)
+        ));
+        assert!(matches!(
+            state.submit_vote(TEST_ELECTION, &vote),
+            Err(Error::ReplayedVote)
+        ));
+        assert_eq!(
+            state.tally(TEST_ELECTION).unwrap().candidates,
+            [(CandidateId::new(1), 1)].into_iter().collect()
+        );
+        state
+            .submit_vote(
+                TEST_ELECTION,
+                &make_vote_at(&authority, now, CandidateId::new(2)),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_persist_chains() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let dir = TempDir::new("persist-chains");
+        let data_paths = DataPaths::new(dir.path()).unwrap();
+
+        let block_log = data_paths.block_log(TEST_ELECTION);
+
+        let mut state = state_for_testing(&authority);
+        state.persist_chains(&data_paths).unwrap();
+        let vote = make_vote_at(&authority, chrono::Utc::now(), CandidateId::new(1));
+        state.submit_vote(TEST_ELECTION, &vote).unwrap();
+        let logged = std::fs::read(&block_log).unwrap();
+
+        // A restarted node.
+        let mut state = state_for_testing(&authority);
+        state.persist_chains(&data_paths).unwrap();
+        assert!(matches!(
+            state.submit_vote(TEST_ELECTION, &vote),
+            Err(Error::ReplayedVote)
+        ));
+        let vote = make_vote_at(&authority, chrono::Utc::now(), CandidateId::new(2));
+        state.submit_vote(TEST_ELECTION, &vote).unwrap();
+
+        // The new block is appended without rewriting the blocks before it.
+        assert!(std::fs::read(&block_log).unwrap().starts_with(&logged));
+        let (voting_system, _) = VotingSystem::open_block_log(&block_log, None).unwrap();
+        assert_eq!(voting_system.height(), 2);
+        assert_eq!(
+            voting_system.tally_votes().unwrap().candidates,
+            [(CandidateId::new(1), 1), (CandidateId::new(2), 1)]
+                .into_iter()
+                .collect()
+        );
+    }
+
+    #[test]
+    fn test_persist_encrypted_chains() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let dir = TempDir::new("persist-encrypted-chains");
+        let data_paths = DataPaths::new(dir.path()).unwrap();
+
+        let mut state = state_for_testing(&authority).with_encryption_at_rest(b"secret".to_vec());
+        state.persist_chains(&data_paths).unwrap();
+        let vote = make_vote_at(&authority, chrono::Utc::now(), CandidateId::new(1));
+        state.submit_vote(TEST_ELECTION, &vote).unwrap();
+
+        let block_log = data_paths.block_log(TEST_ELECTION);
+        assert!(VotingSystem::open_block_log(&block_log, None).is_err());
+        VotingSystem::open_block_log(&block_log, Some(b"secret")).unwrap();
+
+        let mut state = state_for_testing(&authority);
+        assert!(state.persist_chains(&data_paths).is_err());
+        let mut state = state.with_encryption_at_rest(b"secret".to_vec());
+        state.persist_chains(&data_paths).unwrap();
+        assert!(matches!(
+            state.submit_vote(TEST_ELECTION, &vote),
+            Err(Error::ReplayedVote)
+        ));
+    }
+
+    #[test]
+    fn test_load_encrypted_chain() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let dir = TempDir::new("encrypted-state");
+        let chain_file = dir.file("encrypted.chain");
+
+        let state = state_for_testing(&authority).with_encryption_at_rest(b"secret".to_vec());
+        let vote = make_vote_at(&authority, chrono::Utc::now(), CandidateId::new(1));
+        state.submit_vote(TEST_ELECTION, &vote).unwrap();
+        state
+            .election(TEST_ELECTION)
+            .unwrap()
+            .read_voting_system()
+            .unwrap()
+            .save_to_encrypted_file(&chain_file, b"secret")
+            .unwrap();
+
+        let mut state = state_for_testing(&authority);
+        assert!(state.load_chain(TEST_ELECTION, &chain_file).is_err());
+        let mut state = state.with_encryption_at_rest(b"wrong secret".to_vec());
+        assert!(state.load_chain(TEST_ELECTION, &chain_file).is_err());
+        let mut state = state.with_encryption_at_rest(b"secret".to_vec());
+        state.load_chain(TEST_ELECTION, &chain_file).unwrap();
+        assert_eq!(
+            state.tally(TEST_ELECTION).unwrap().candidates,
+            [(CandidateId::new(1), 1)].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn test_vote_spans() {
+        use tracing_subscriber::layer::{Context, SubscriberExt};
+
+        /// Layer collecting the names of the created spans.
+        struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+
+        impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanNames {
+            fn on_new_span(
+                &self,
+                attrs: &tracing::span::Attributes<'_>,
+                _id: &tracing::span::Id,
+                _ctx: Context<'_, S>,
+            ) {
+                self.0.lock().unwrap().push(attrs.metadata().name());
+            }
+        }
+
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let state = state_for_testing(&authority);
+        let now = chrono::Utc::now();
+        let span_names = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(SpanNames(span_names.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            state
+                .submit_vote(
+                    TEST_ELECTION,
+                    &make_vote_at(&authority, now, CandidateId::new(1)),
+                )
+                .unwrap();
+        });
+        assert_eq!(
+            *span_names.lock().unwrap(),
+            ["submit_vote", "verify_vote", "append_vote", "audit_vote"]
+        );
+    }
+}
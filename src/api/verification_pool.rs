@@ -0,0 +1,199 @@
+//! Pool for running CPU heavy vote verification off the async runtime.
+//! Verifying the blind signature of the access token and the digital signature of a vote would
+//! otherwise block the HTTP workers, so the jobs are moved onto blocking threads instead, while
+//! limiting how many of them can run at once and, optionally, how many of them can wait to run.
+
+use std::sync::Arc;
+
+use thiserror::Error;
+use tokio::sync::{oneshot, Semaphore, TryAcquireError};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Verification pool is closed")]
+    Closed,
+    #[error("Verification job panicked")]
+    JobPanicked,
+    #[error("Too many votes are waiting for verification, try again later")]
+    Saturated,
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// The default amount of verification jobs which can run at the same time.
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+/// Pool of blocking threads to which verification jobs are submitted.
+#[derive(Debug, Clone)]
+pub struct VerificationPool {
+    /// Permits limiting the amount of concurrently running jobs to the size of the pool.
+    permits: Arc<Semaphore>,
+    /// Permits limiting the amount of jobs which are either running or waiting to run,
+    /// so that a burst of jobs is refused instead of piling up. Unlimited if absent.
+    queue: Option<Arc<Semaphore>>,
+}
+
+impl VerificationPool {
+    /// Create a new verification pool.
+    ///
+    /// # Arguments
+    ///
+    /// - `size` - Maximum amount of jobs running at the same time. Zero is treated as one.
+    #[must_use]
+    pub fn new(size: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(size.max(1))),
+            queue: None,
+        }
+    }
+
+    /// Limit the amount of jobs waiting for a free thread of the pool.
+    /// Jobs submitted while the queue is full fail with `Error::Saturated`.
+    ///
+    /// # Arguments
+    ///
+    /// - `max_queued` - Maximum amount of jobs waiting to run, on top of the running ones.
+    #[must_use]
+    pub fn with_max_queued(mut self, max_queued: usize) -> Self {
+        let size = self.permits.available_permits();
+        self.queue = Some(Arc::new(Semaphore::new(size + max_queued)));
+        self
+    }
+
+    /// Run a job on the pool and wait for its result without blocking the async runtime.
+    ///
+    /// # Arguments
+    ///
+    /// - `job` - The verification to run.
+    ///
+    /// # Returns
+    ///
+    /// The value returned by the job.
+    ///
+    /// # Errors
+    ///
+    /// If the pool had been closed, if too many jobs are already waiting to run or if the job
+    /// panicked.
+    pub async fn run<F, R>(&self, job: F) -> Result<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let queued = self
+            .queue
+            .as_ref()
+            .map(|queue| queue.clone().try_acquire_owned())
+            .transpose()
+            .map_err(|e| match e {
+                TryAcquireError::NoPermits => Error::Saturated,
+                TryAcquireError::Closed => Error::Closed,
+            })?;
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::Closed)?;
+        let (tx, rx) = oneshot::channel();
+        tokio::task::spawn_blocking(move || {
+            // Holding the permits until the job is done, so that the pool size is respected.
+            let _permits = (permit, queued);
+            // The receiver only goes away if the request was cancelled, so the result is dropped.
+            let _ = tx.send(job());
+        });
+
+        // The sender is dropped without sending only if the job panicked.
+        rx.await.map_err(|_| Error::JobPanicked)
+    }
+}
+
+impl Default for VerificationPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_POOL_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    use tokio::task::JoinSet;
+
+    // Running on the single threaded runtime, so any job blocking it would stall the whole test.
+    #[tokio::test]
+    async fn test_concurrent_verifications() {
+        const JOBS: usize = 32;
+        const POOL_SIZE: usize = 4;
+        let pool = VerificationPool::new(POOL_SIZE);
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+
+        let mut jobs = JoinSet::new();
+        for i in 0..JOBS {
+            let pool = pool.clone();
+            let running = running.clone();
+            let max_running = max_running.clone();
+            jobs.spawn(async move {
+                pool.run(move || {
+                    let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now_running, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(10));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    i
+                })
+                .await
+                .unwrap()
+            });
+        }
+
+        // Running the jobs inline would take at least JOBS * 10ms before the timer fires.
+        let start = Instant::now();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        let mut sum = 0;
+        while let Some(result) = jobs.join_next().await {
+            sum += result.unwrap();
+        }
+        assert_eq!(sum, (0..JOBS).sum::<usize>());
+        assert!(max_running.load(Ordering::SeqCst) <= POOL_SIZE);
+    }
+
+    #[tokio::test]
+    async fn test_job_panic() {
+        let pool = VerificationPool::default();
+        assert!(matches!(
+            pool.run(|| panic!("Job panicked")).await,
+            Err(Error::JobPanicked)
+        ));
+        // The permit of the panicked job must be released.
+        assert_eq!(pool.run(|| 1).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_saturated_pool() {
+        let pool = VerificationPool::new(1).with_max_queued(1);
+        let (release_tx, release_rx) = std::sync::mpsc::channel();
+
+        // One job runs until it's released and another one waits for it, filling the queue.
+        let mut jobs = JoinSet::new();
+        let running_pool = pool.clone();
+        jobs.spawn(async move { running_pool.run(move || release_rx.recv().unwrap()).await });
+        let queued_pool = pool.clone();
+        jobs.spawn(async move { queued_pool.run(|| ()).await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        for _ in 0..3 {
+            assert!(matches!(pool.run(|| ()).await, Err(Error::Saturated)));
+        }
+
+        release_tx.send(()).unwrap();
+        while let Some(result) = jobs.join_next().await {
+            result.unwrap().unwrap();
+        }
+        // Refused jobs don't hold on to the queue.
+        assert_eq!(pool.run(|| 1).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,399 @@
+//! Append-only audit log of every vote submission the node had processed.
+//! Entries are hash-chained the same way blocks are, so an entry can't be removed or altered
+//! without breaking the chain of hashes for all the entries that follow it.
+//! The node appends the entries to a file as they are recorded, so that the log survives
+//! restarts and doesn't have to be kept in memory.
+
+use std::io::{BufRead, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use bincode::Options;
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use protocol::{timestamp::Timestamp, vote::Vote};
+
+use crate::blockchain::{Error as BlockchainError, Hash};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Audit log hash integrity error at entry {}", .0)]
+    HashIntegrity(usize),
+    #[error("Hashing error: {}", .0)]
+    Hashing(#[from] BlockchainError),
+    #[error("Binary serialization error: {}", .0)]
+    BinSerialization(#[from] bincode::Error),
+    #[error("File IO error: {}", .0)]
+    FileIO(#[from] std::io::Error),
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// The outcome of processing a single vote submission.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// The vote was verified and accepted.
+    Accepted,
+    /// The vote was rejected with the contained reason.
+    Rejected(String),
+}
+
+/// A single record in the audit log.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Time at which the node processed the submission.
+    pub timestamp: Timestamp,
+    /// Hash of the submitted vote.
+    pub vote_hash: Hash,
+    /// Whether the vote was accepted or rejected and why.
+    pub outcome: Outcome,
+    /// Hash of the previous entry in the log or zeroes for the first entry.
+    pub prev_entry_hash: Hash,
+}
+
+impl Entry {
+    fn get_hash(&self) -> Result<Hash> {
+        let bytes = bincode::serialize(&self)?;
+        Ok(digest::digest(&digest::SHA256, &bytes).try_into()?)
+    }
+}
+
+/// Iterator over the entries of an audit log, see `AuditLog::entries`.
+pub type Entries = Box<dyn Iterator<Item = Result<Entry>> + Send>;
+
+/// Where the entries of an audit log are kept.
+#[derive(Debug)]
+enum Storage {
+    /// In memory, for logs which don't have to outlive the process, like in tests.
+    Memory(Vec<Entry>),
+    /// Appended to a file, from which they are read back when requested.
+    File {
+        path: PathBuf,
+        file: std::fs::File,
+        /// Length of the file up to the end of the last complete entry.
+        file_len: u64,
+    },
+}
+
+/// The hash-chained audit log itself.
+#[derive(Debug)]
+pub struct AuditLog {
+    storage: Storage,
+    /// Amount of entries in the log.
+    len: usize,
+    /// Hash of the last entry in the log or zeroes if the log is empty.
+    last_hash: Hash,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self {
+            storage: Storage::Memory(Vec::new()),
+            len: 0,
+            last_hash: Hash::default(),
+        }
+    }
+}
+
+impl AuditLog {
+    /// Create an audit log which is only kept in memory.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the audit log persisted in the file, creating the file if it doesn't exist yet.
+    /// The hash chain of the existing entries is validated, so a log which had been tampered
+    /// with while the node was stopped is refused instead of being appended to.
+    /// An entry cut short at the end of the file, because the node stopped in the middle of
+    /// appending it, had never been recorded, so it's truncated instead.
+    ///
+    /// # Arguments
+    ///
+    /// - `path` - The file to which the entries are appended.
+    ///
+    /// # Errors
+    ///
+    /// If the file could not be opened, read or truncated, if it contains an undecodable entry
+    /// or if the hash chain of the entries is broken.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let mut file_len = file.metadata()?.len();
+        let options = entry_options(file_len);
+        let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut len = 0;
+        let mut last_hash = Hash::default();
+        let mut complete_len = 0;
+        while !reader.fill_buf()?.is_empty() {
+            let entry: Entry = match options.deserialize_from(&mut reader) {
+                Ok(entry) => entry,
+                Err(e) if is_truncated(&e) => break,
+                Err(e) => return Err(e.into()),
+            };
+            if entry.prev_entry_hash != last_hash {
+                return Err(Error::HashIntegrity(len));
+            }
+            last_hash = entry.get_hash()?;
+            len += 1;
+            complete_len = reader.stream_position()?;
+        }
+        if complete_len < file_len {
+            tracing::warn!(
+                "Truncating {} bytes of an incomplete entry at the end of the audit log",
+                file_len - complete_len
+            );
+            file.set_len(complete_len)?;
+            file_len = complete_len;
+        }
+
+        Ok(Self {
+            storage: Storage::File {
+                path: path.to_owned(),
+                file,
+                file_len,
+            },
+            len,
+            last_hash,
+        })
+    }
+
+    /// Record the outcome of processing a vote.
+    ///
+    /// # Arguments
+    ///
+    /// - `vote` - The vote which was submitted to the node.
+    /// - `outcome` - Whether the vote was accepted or rejected.
+    ///
+    /// # Errors
+    ///
+    /// If hashing the vote or the previous entry fails or if the entry could not be persisted.
+    pub fn record(&mut self, vote: &Vote, outcome: &Outcome) -> Result<()> {
+        self.record_all(std::slice::from_ref(vote), outcome)
+    }
+
+    /// Record the same outcome for a batch of votes, persisting all the entries at once.
+    /// Either all of the entries are recorded or none of them are.
+    ///
+    /// # Arguments
+    ///
+    /// - `votes` - The votes which were submitted to the node.
+    /// - `outcome` - Whether the votes were accepted or rejected.
+    ///
+    /// # Errors
+    ///
+    /// If hashing the votes or the entries fails or if the entries could not be persisted.
+    pub fn record_all(&mut self, votes: &[Vote], outcome: &Outcome) -> Result<()> {
+        let timestamp = chrono::Utc::now();
+        let mut last_hash = self.last_hash.clone();
+        let mut entries = Vec::with_capacity(votes.len());
+        for vote in votes {
+            let entry = Entry {
+                timestamp,
+                vote_hash: digest::digest(&digest::SHA256, &bincode::serialize(vote)?)
+                    .try_into()?,
+                outcome: outcome.clone(),
+                prev_entry_hash: last_hash,
+            };
+            last_hash = entry.get_hash()?;
+            entries.push(entry);
+        }
+
+        match &mut self.storage {
+            Storage::Memory(stored) => stored.extend(entries),
+            Storage::File { file, file_len, .. } => {
+                let mut bytes = Vec::new();
+                for entry in &entries {
+                    bincode::serialize_into(&mut bytes, entry)?;
+                }
+                if let Err(e) = file.write_all(&bytes).and_then(|()| file.sync_data()) {
+                    // Dropping whatever was written, so that the next entries don't follow a
+                    // partial one.
+                    let _ = file.set_len(*file_len);
+                    return Err(e.into());
+                }
+                *file_len += bytes.len() as u64;
+            }
+        }
+        self.len += votes.len();
+        self.last_hash = last_hash;
+
+        Ok(())
+    }
+
+    /// Get the entries of the log, from the oldest to the newest.
+    /// Entries recorded after this call are not included, so the returned iterator can be
+    /// consumed without holding on to the log.
+    ///
+    /// # Errors
+    ///
+    /// If the log file could not be opened.
+    pub fn entries(&self) -> Result<Entries> {
+        match &self.storage {
+            Storage::Memory(entries) => Ok(Box::new(entries.clone().into_iter().map(Ok))),
+            Storage::File { path, .. } => Ok(Box::new(read_entries(path, self.len)?)),
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Check that no entries had been altered or removed from the log.
+    ///
+    /// # Errors
+    ///
+    /// If the hash chain is broken, the error contains the index of the first offending entry.
+    pub fn validate(&self) -> Result<()> {
+        let mut prev_entry_hash = Hash::default();
+        for (index, entry) in self.entries()?.enumerate() {
+            let entry = entry?;
+            if entry.prev_entry_hash != prev_entry_hash {
+                return Err(Error::HashIntegrity(index));
+            }
+            prev_entry_hash = entry.get_hash()?;
+        }
+        Ok(())
+    }
+
+    /// Create a log persisted in a file which is opened read only, so that recording fails.
+    #[cfg(test)]
+    pub(crate) fn read_only_for_testing(path: &Path) -> Self {
+        std::fs::write(path, []).unwrap();
+        Self {
+            storage: Storage::File {
+                path: path.to_owned(),
+                file: std::fs::File::open(path).unwrap(),
+                file_len: 0,
+            },
+            len: 0,
+            last_hash: Hash::default(),
+        }
+    }
+}
+
+/// Read up to `count` entries from a log file, stopping early at the end of the file.
+fn read_entries(path: &Path, count: usize) -> Result<impl Iterator<Item = Result<Entry>>> {
+    let file = std::fs::File::open(path)?;
+    let options = entry_options(file.metadata()?.len());
+    let mut reader = std::io::BufReader::new(file);
+    let mut remaining = count;
+
+    Ok(std::iter::from_fn(move || {
+        if remaining == 0 {
+            return None;
+        }
+        remaining -= 1;
+        match reader.fill_buf() {
+            Ok([]) => None,
+            Ok(_) => Some(options.deserialize_from(&mut reader).map_err(Error::from)),
+            Err(e) => Some(Err(e.into())),
+        }
+    }))
+}
+
+/// Options for decoding the entries from a file of the length.
+/// Same as `bincode::deserialize_from`, apart from the limit, so that a corrupted length can't
+/// make the node allocate more than the size of the file.
+fn entry_options(file_len: u64) -> impl Options + Copy {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(file_len)
+}
+
+/// Check whether decoding failed because the data ended in the middle of an entry.
+fn is_truncated(e: &bincode::Error) -> bool {
+    matches!(&**e, bincode::ErrorKind::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crypto::signature::blind_sign;
+    use process_io::test_utils::TempDir;
+    use protocol::{candidate_id::CandidateId, test_utils::make_vote_at};
+
+    #[test]
+    fn test_audit_log_tampering() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let mut audit_log = AuditLog::new();
+        for candidate in 0..3 {
+            let vote = make_vote_at(&authority, chrono::Utc::now(), CandidateId::new(candidate));
+            audit_log.record(&vote, &Outcome::Accepted).unwrap();
+        }
+        audit_log.validate().unwrap();
+
+        // Silently removing an entry must break the hash chain.
+        let Storage::Memory(entries) = &mut audit_log.storage else {
+            unreachable!()
+        };
+        entries.remove(1);
+        assert!(matches!(audit_log.validate(), Err(Error::HashIntegrity(1))));
+    }
+
+    #[test]
+    fn test_persisted_audit_log() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let dir = TempDir::new("audit-log");
+        let path = dir.join("audit.log");
+        let votes: Vec<_> = (0..3)
+            .map(|candidate| {
+                make_vote_at(&authority, chrono::Utc::now(), CandidateId::new(candidate))
+            })
+            .collect();
+
+        let mut audit_log = AuditLog::open(&path).unwrap();
+        audit_log
+            .record_all(&votes[..2], &Outcome::Accepted)
+            .unwrap();
+        drop(audit_log);
+
+        // The entries survive reopening the log and new entries are chained to them.
+        let mut audit_log = AuditLog::open(&path).unwrap();
+        assert_eq!(audit_log.len(), 2);
+        audit_log
+            .record(&votes[2], &Outcome::Rejected("Invalid".to_owned()))
+            .unwrap();
+        audit_log.validate().unwrap();
+        let entries = audit_log
+            .entries()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].outcome, Outcome::Accepted);
+        assert_eq!(entries[2].prev_entry_hash, entries[1].get_hash().unwrap());
+        drop(audit_log);
+
+        // Removing the first entry from the file is detected when the log is opened.
+        let first_len = bincode::serialize(&entries[0]).unwrap().len();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[first_len..]).unwrap();
+        assert!(matches!(
+            AuditLog::open(&path),
+            Err(Error::HashIntegrity(0))
+        ));
+        // An entry cut short by a crash is dropped, so that the next one follows a complete one.
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        let mut audit_log = AuditLog::open(&path).unwrap();
+        assert_eq!(audit_log.len(), 2);
+        let last_len = bincode::serialize(&entries[2]).unwrap().len();
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            (bytes.len() - last_len) as u64
+        );
+        audit_log.record(&votes[2], &Outcome::Accepted).unwrap();
+        drop(audit_log);
+        AuditLog::open(&path).unwrap().validate().unwrap();
+    }
+}
@@ -5,16 +5,70 @@
 use std::{sync::Arc, time::Duration};
 
 use chrono::Utc;
+use ring::rand::{SecureRandom, SystemRandom};
+use thiserror::Error;
 use tokio::sync::{
-    mpsc::{self, Receiver, Sender},
+    mpsc::{self, error::TrySendError, Receiver, Sender},
     Notify,
 };
+use tracing::warn;
 
 use protocol::timestamp::Timestamp;
 
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Batcher is busy, try again later")]
+    Busy,
+    #[error("Batcher is closed")]
+    Closed,
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// The amount of items which can wait in the channel before the batcher receives them.
+pub const CHANNEL_CAPACITY: usize = 5;
+
+/// Submit an item to the batcher without waiting for space in the channel, so that callers
+/// can shed load when the batcher can't keep up, instead of stalling until it catches up.
+///
+/// # Arguments
+/// - `tx` - The Sender returned by `Batcher::new`.
+/// - `item` - The item to batch.
+///
+/// # Errors
+/// If the channel is full or if the batcher had been dropped.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use digital_voting::batcher::{self, Batcher};
+///
+/// let (mut batcher, tx) = Batcher::<u32>::new(3, Duration::from_secs(1));
+/// batcher::try_submit(&tx, 1).unwrap();
+/// ```
+pub fn try_submit<T>(tx: &Sender<T>, item: T) -> Result<()> {
+    tx.try_send(item).map_err(|e| match e {
+        TrySendError::Full(_) => Error::Busy,
+        TrySendError::Closed(_) => Error::Closed,
+    })
+}
+
+/// Counters describing the load of a batcher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BatcherStats {
+    /// Amount of items the batcher had received from the channel.
+    pub items_received: u64,
+    /// Amount of non-empty batches the batcher had returned.
+    pub batches_emitted: u64,
+    /// Amount of items waiting to be returned, both in the channel and in the batch.
+    pub queue_depth: usize,
+}
+
 /// The Batcher instance itself holding the context for batching and the batched items.
 /// The intended way to add items to the batcher is through the Sender returned by the new function.
 /// This is done so that a single Batcher instance shouldn't be shared between multiple threads.
+/// Items which were not returned yet are lost when the batcher is dropped, so the batcher should
+/// be stopped with `shutdown`, which returns them. Dropping a non-empty batcher logs a warning.
 pub struct Batcher<T> {
     /// The maximum size of the batch. More items can be added, but the batch will return
     /// only this amount of items at a time, returning the rest in the next batch.
@@ -23,6 +77,9 @@ pub struct Batcher<T> {
     /// Time interval for which the batcher will wait before returning the batch if a
     /// full batch had not yet been accumulated.
     batch_time_interval: Duration,
+    /// Upper bound of the random delay added to every `batch_time_interval`, so that batchers
+    /// created with the same interval don't flush in lockstep. Zero by default.
+    max_jitter: Duration,
     /// Variable to track when the next batch should be returned.
     next_batch_time: Timestamp,
     /// Notify instance to notify the batcher that the batch is full and ready to be
@@ -32,6 +89,10 @@ pub struct Batcher<T> {
     rx: Receiver<T>,
     /// Buffer for the actual batched items.
     batch: Vec<T>,
+    /// Amount of items received from the channel.
+    items_received: u64,
+    /// Amount of non-empty batches returned.
+    batches_emitted: u64,
 }
 
 impl<T> Batcher<T> {
@@ -56,22 +117,61 @@ impl<T> Batcher<T> {
     /// ```
     #[must_use]
     pub fn new(batch_size: usize, batch_time_interval: Duration) -> (Self, Sender<T>) {
-        let (tx, rx) = mpsc::channel(5);
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
         let now = Utc::now();
         let batch_ready_notify = Arc::new(Notify::new());
         (
             Self {
                 batch_size,
                 batch_time_interval,
+                max_jitter: Duration::ZERO,
                 next_batch_time: now + batch_time_interval,
                 batch_ready_notify,
                 rx,
                 batch: Vec::new(),
+                items_received: 0,
+                batches_emitted: 0,
             },
             tx,
         )
     }
 
+    /// Delay every batch time interval by a random duration up to `max_jitter`, so that many
+    /// batchers with the same interval spread their flushes out instead of spiking the load.
+    ///
+    /// # Arguments
+    /// - `max_jitter` - The upper bound of the random delay.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use digital_voting::batcher::Batcher;
+    ///
+    /// let (batcher, tx) = Batcher::<u32>::new(3, Duration::from_secs(1));
+    /// let batcher = batcher.with_jitter(Duration::from_millis(100));
+    /// ```
+    #[must_use]
+    pub fn with_jitter(mut self, max_jitter: Duration) -> Self {
+        self.max_jitter = max_jitter;
+        self.next_batch_time = self.next_batch_time();
+        self
+    }
+
+    /// Calculate when the next batch should be returned, counting from now.
+    fn next_batch_time(&self) -> Timestamp {
+        let max_jitter_nanos = u64::try_from(self.max_jitter.as_nanos()).unwrap_or(u64::MAX);
+        let mut jitter = Duration::ZERO;
+        let mut random = [0; 8];
+        // Failing to generate the jitter only means that this flush isn't delayed.
+        if max_jitter_nanos > 0 && SystemRandom::new().fill(&mut random).is_ok() {
+            jitter = Duration::from_nanos(
+                u64::from_le_bytes(random) % max_jitter_nanos.saturating_add(1),
+            );
+        }
+        Utc::now() + self.batch_time_interval + jitter
+    }
+
     /// Wait for the batch to be full or batch time interval to end and return the batch.
     ///
     /// # Returns
@@ -120,6 +220,7 @@ impl<T> Batcher<T> {
                                     // Since we only got an item and it's not necessarily time to return the batch,
                                     // we're looping again to check if it's time to return the batch and continue waiting.
                                     self.batch.push(item);
+                                    self.items_received += 1;
                                 }
                                 // Channel is closed, so we're just returning the last batch.
                                 // The application should handle dripping this sturct then.
@@ -157,21 +258,102 @@ impl<T> Batcher<T> {
     /// }
     /// ```
     pub fn flush(&mut self) -> Vec<T> {
-        self.next_batch_time = Utc::now() + self.batch_time_interval;
-        let batch_size = if self.batch.len() < self.batch_size {
-            self.batch.len()
-        } else {
-            self.batch_size
-        };
+        let mut batch = Vec::with_capacity(self.batch.len().min(self.batch_size));
+        self.flush_into(&mut batch);
+        batch
+    }
+
+    /// Return batched items without waiting, moving them into a buffer owned by the caller.
+    /// The buffer is cleared first, but its capacity is kept, so reusing the same buffer
+    /// avoids allocating a new vector on every flush.
+    ///
+    /// # Arguments
+    /// - `out` - The buffer into which the batched items will be moved.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use digital_voting::batcher::Batcher;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let (mut batcher, tx) = Batcher::<u32>::new(3, Duration::from_secs(1));
+    ///     let mut batch = Vec::with_capacity(3);
+    ///     tx.send(1).await.unwrap();
+    ///     batcher.flush_into(&mut batch);
+    ///     println!("{:?}", batch);
+    /// }
+    /// ```
+    pub fn flush_into(&mut self, out: &mut Vec<T>) {
+        self.next_batch_time = self.next_batch_time();
+        let batch_size = self.batch.len().min(self.batch_size);
+        out.clear();
         // Using drain in case there are more than the maximum amount of items in the vector.
-        // Not sure how fast this is due to extra allocations.
-        self.batch.drain(0..batch_size).collect()
+        out.extend(self.batch.drain(0..batch_size));
+        if !out.is_empty() {
+            self.batches_emitted += 1;
+        }
+    }
+
+    /// Stop the batcher, returning all the items which were not returned yet, including the
+    /// items still waiting in the channel. Any further items sent to the batcher are rejected.
+    /// This is the way to dispose of a batcher without losing items.
+    ///
+    /// # Returns
+    /// The remaining items, which may be more than the batch size.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use digital_voting::batcher::{self, Batcher};
+    ///
+    /// let (batcher, tx) = Batcher::<u32>::new(3, Duration::from_secs(1));
+    /// batcher::try_submit(&tx, 1).unwrap();
+    /// assert_eq!(batcher.shutdown(), vec![1]);
+    /// assert!(batcher::try_submit(&tx, 2).is_err());
+    /// ```
+    #[must_use]
+    pub fn shutdown(mut self) -> Vec<T> {
+        self.rx.close();
+        while let Ok(item) = self.rx.try_recv() {
+            self.batch.push(item);
+            self.items_received += 1;
+        }
+        std::mem::take(&mut self.batch)
+    }
+
+    /// Get the counters describing the load of the batcher.
+    ///
+    /// # Returns
+    /// The current statistics of the batcher.
+    #[must_use]
+    pub fn stats(&self) -> BatcherStats {
+        BatcherStats {
+            items_received: self.items_received,
+            batches_emitted: self.batches_emitted,
+            queue_depth: self.rx.len() + self.batch.len(),
+        }
+    }
+}
+
+impl<T> Drop for Batcher<T> {
+    fn drop(&mut self) {
+        let lost = self.rx.len() + self.batch.len();
+        if lost > 0 {
+            warn!(
+                lost,
+                "Batcher dropped with {lost} unreturned items, use shutdown instead"
+            );
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeDelta;
     use std::time::Duration;
 
     // TODO Split into multiple tests.
@@ -206,4 +388,140 @@ mod tests {
         let batch = batcher.wait_for_batch().await;
         assert_eq!(batch, vec![6, 7, 8]);
     }
+
+    #[tokio::test]
+    async fn test_busy() {
+        let (mut batcher, tx) = Batcher::<u32>::new(3, Duration::from_secs(1));
+        for item in 0..CHANNEL_CAPACITY {
+            try_submit(&tx, u32::try_from(item).unwrap()).unwrap();
+        }
+        assert!(matches!(try_submit(&tx, 99), Err(Error::Busy)));
+        assert_eq!(
+            batcher.stats(),
+            BatcherStats {
+                items_received: 0,
+                batches_emitted: 0,
+                queue_depth: CHANNEL_CAPACITY,
+            }
+        );
+
+        assert_eq!(batcher.wait_for_batch().await, vec![0, 1, 2]);
+        assert_eq!(
+            batcher.stats(),
+            BatcherStats {
+                items_received: 3,
+                batches_emitted: 1,
+                queue_depth: CHANNEL_CAPACITY - 3,
+            }
+        );
+        // Space frees up once the batcher catches up.
+        try_submit(&tx, 5).unwrap();
+
+        drop(batcher);
+        assert!(matches!(try_submit(&tx, 6), Err(Error::Closed)));
+    }
+
+    #[tokio::test]
+    async fn test_flush_into_reuses_buffer() {
+        let (mut batcher, _tx) = Batcher::<u32>::new(3, Duration::from_secs(1));
+        let mut batch = Vec::with_capacity(3);
+        let capacity = batch.capacity();
+        let buffer = batch.as_ptr();
+
+        batcher.batch.extend([1, 2, 3, 4, 5]);
+        batcher.flush_into(&mut batch);
+        assert_eq!(batch, vec![1, 2, 3]);
+        batcher.flush_into(&mut batch);
+        assert_eq!(batch, vec![4, 5]);
+        batcher.flush_into(&mut batch);
+        assert!(batch.is_empty());
+
+        assert_eq!(batch.capacity(), capacity);
+        assert_eq!(batch.as_ptr(), buffer);
+    }
+
+    #[test]
+    fn test_jitter() {
+        let interval = Duration::from_secs(1);
+        let max_jitter = Duration::from_millis(500);
+        // Jitter which can't be mistaken for the time spent flushing.
+        let floor = TimeDelta::milliseconds(1);
+        let (batcher, _tx) = Batcher::<u32>::new(3, interval);
+        let mut batcher = batcher.with_jitter(max_jitter);
+
+        // The jitter is only known up to the time spent flushing, so it's bounded from below by
+        // the delay past `after` and from above by the delay past `before`.
+        let mut jittered = 0;
+        for _ in 0..20 {
+            let before = Utc::now();
+            batcher.flush();
+            let after = Utc::now();
+            let lower = batcher.next_batch_time - interval - after;
+            let upper = batcher.next_batch_time - interval - before;
+            assert!(upper >= TimeDelta::zero());
+            assert!(lower <= TimeDelta::from_std(max_jitter).unwrap());
+            if lower > floor {
+                jittered += 1;
+            }
+        }
+        assert!(jittered > 0);
+
+        // Without jitter the flushes happen exactly one interval apart.
+        let (mut batcher, _tx) = Batcher::<u32>::new(3, interval);
+        for _ in 0..20 {
+            let before = Utc::now();
+            batcher.flush();
+            let after = Utc::now();
+            assert!(batcher.next_batch_time >= before + interval);
+            assert!(batcher.next_batch_time <= after + interval);
+            assert!(batcher.next_batch_time - interval - before < floor);
+        }
+    }
+
+    #[test]
+    fn test_drop_warning() {
+        use std::sync::Mutex;
+        use tracing::field::{Field, Visit};
+        use tracing_subscriber::layer::{Context, SubscriberExt};
+
+        /// Layer collecting the amounts of lost items from the warnings.
+        struct LostItems(Arc<Mutex<Vec<u64>>>);
+
+        impl Visit for &LostItems {
+            fn record_u64(&mut self, field: &Field, value: u64) {
+                if field.name() == "lost" {
+                    self.0.lock().unwrap().push(value);
+                }
+            }
+
+            fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+        }
+
+        impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for LostItems {
+            fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+                if *event.metadata().level() == tracing::Level::WARN {
+                    event.record(&mut &*self);
+                }
+            }
+        }
+
+        let lost_items = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(LostItems(lost_items.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let (mut batcher, tx) = Batcher::<u32>::new(3, Duration::from_secs(1));
+            batcher.batch.extend([1, 2]);
+            try_submit(&tx, 3).unwrap();
+            drop(batcher);
+
+            // Nothing is lost if the batcher is shut down or empty.
+            let (mut batcher, tx) = Batcher::<u32>::new(3, Duration::from_secs(1));
+            batcher.batch.extend([1, 2]);
+            try_submit(&tx, 3).unwrap();
+            assert_eq!(batcher.shutdown(), vec![1, 2, 3]);
+            assert!(matches!(try_submit(&tx, 4), Err(Error::Closed)));
+            drop(Batcher::<u32>::new(3, Duration::from_secs(1)));
+        });
+        assert_eq!(*lost_items.lock().unwrap(), [3]);
+    }
 }
@@ -1,16 +1,33 @@
 //! This is a mock of the election authorities servers which will be responsible for ensuring the
 //! eligibility of the voters by signing their public keys. This is only used for testing purposes.
 
-use std::{io::Write, sync::Arc};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
 
-use actix_web::{get, post, routes, web, App, HttpResponse, HttpServer, Responder};
+use actix_web::{
+    get, middleware::from_fn, post, routes, web, App, HttpResponse, HttpServer, Responder,
+};
 use anyhow::{anyhow, Result};
 use clap::Parser;
 use serde::{self, Deserialize, Serialize};
+use tracing::warn;
 
 use crypto::signature::blind_sign;
-use digital_voting::logging::start_logger;
-use process_io::cli::StdioReader;
+#[cfg(unix)]
+use digital_voting::data_paths::SECRET_FILE_MODE;
+use digital_voting::{
+    api::{
+        in_flight::{limit_in_flight, InFlightLimit},
+        server_cli::ServerLimits,
+    },
+    data_paths::{create_secret_file, DataPaths},
+    logging::start_logger,
+};
+use process_io::{cli::StdioReader, output::Output};
 
 #[derive(Parser, Clone, Debug)]
 pub struct Args {
@@ -28,6 +45,13 @@ pub struct Args {
         help = "Generate new blind signer keys instead of loading them from FS"
     )]
     pub new_keys: bool,
+    #[clap(
+        long = "keys-from-env",
+        default_value_t = false,
+        conflicts_with = "new_keys",
+        help = "Read the blind signer keys from the AUTHORITY_PUBLIC_KEY and AUTHORITY_SECRET_KEY environment variables instead of the FS"
+    )]
+    pub keys_from_env: bool,
     #[clap(
         short = 'n',
         long = "no-http",
@@ -35,6 +59,21 @@ pub struct Args {
         help = "Only run CLI and do not start an http server"
     )]
     pub no_http_server: bool,
+    #[clap(
+        short = 'd',
+        long = "data-path",
+        default_value = ".",
+        help = "Directory in which to keep the keys and the logs"
+    )]
+    pub data_path: std::path::PathBuf,
+    #[clap(
+        short = 'o',
+        long = "output",
+        help = "Append the results of the commands to this file instead of printing them to stdout"
+    )]
+    pub output: Option<std::path::PathBuf>,
+    #[clap(flatten)]
+    pub server_limits: ServerLimits,
 }
 
 #[derive(Parser, Clone, Debug)]
@@ -53,11 +92,7 @@ struct AppState {
 
 fn new_blind_signer(path: &str) -> Result<blind_sign::BlindSigner> {
     let blind_signer = blind_sign::BlindSigner::new()?;
-    let mut blind_signer_cfg_file = std::fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(path)?;
+    let mut blind_signer_cfg_file = create_secret_file(std::path::Path::new(path))?;
 
     writeln!(blind_signer_cfg_file, "{}", blind_signer.get_public_key()?)?;
     writeln!(blind_signer_cfg_file, "{}", blind_signer.get_secret_key()?)?;
@@ -65,8 +100,30 @@ fn new_blind_signer(path: &str) -> Result<blind_sign::BlindSigner> {
     Ok(blind_signer)
 }
 
-fn setup_blind_signer(arg_new_keys: bool) -> Result<blind_sign::BlindSigner> {
-    let blind_signer_cfg_path = "authority-blind-signer-cfg";
+/// Warn if a file containing secret keys can be accessed by anyone other than its owner.
+/// This is a no-op on non-Unix systems.
+fn warn_if_key_file_exposed(path: &str) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let mode = std::fs::metadata(path)?.permissions().mode() & 0o777;
+        if mode & !SECRET_FILE_MODE != 0 {
+            warn!("Key file {path} has permissions {mode:o}, expected {SECRET_FILE_MODE:o}");
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+fn setup_blind_signer(
+    arg_new_keys: bool,
+    data_paths: &DataPaths,
+) -> Result<blind_sign::BlindSigner> {
+    let blind_signer_cfg_path = data_paths.authority_keys();
+    let blind_signer_cfg_path = blind_signer_cfg_path
+        .to_str()
+        .ok_or(anyhow!("Data path is not valid UTF-8"))?;
 
     if arg_new_keys {
         if let Err(err) = std::fs::remove_file(blind_signer_cfg_path) {
@@ -85,37 +142,109 @@ fn setup_blind_signer(arg_new_keys: bool) -> Result<blind_sign::BlindSigner> {
 
 fn load_blind_signer_from_fs(path: &str) -> Result<blind_sign::BlindSigner> {
     if std::path::Path::new(path).exists() {
-        let blind_signer_cfg = std::fs::read_to_string(path)?;
+        load_blind_signer(&mut FileKeySource(path))
+    } else {
+        Err(anyhow!("Blind signer config not found"))
+    }
+}
+
+/// Environment variables from which the keys are read, see `EnvKeySource`.
+const PUBLIC_KEY_ENV: &str = "AUTHORITY_PUBLIC_KEY";
+const SECRET_KEY_ENV: &str = "AUTHORITY_SECRET_KEY";
+
+/// Source of the blind signer keys, so that deployments using secret managers don't have to
+/// keep the keys on disk.
+trait KeySource {
+    /// Read the public and the secret keys, encoded the same way as in the key file.
+    fn read_keys(&mut self) -> Result<(String, String)>;
+}
+
+/// Reads the keys from the key file, one key per line.
+struct FileKeySource<'a>(&'a str);
+
+impl KeySource for FileKeySource<'_> {
+    fn read_keys(&mut self) -> Result<(String, String)> {
+        warn_if_key_file_exposed(self.0)?;
+        ReaderKeySource(std::fs::File::open(self.0)?).read_keys()
+    }
+}
+
+/// Reads the keys from the `PUBLIC_KEY_ENV` and `SECRET_KEY_ENV` environment variables.
+struct EnvKeySource;
+
+impl KeySource for EnvKeySource {
+    fn read_keys(&mut self) -> Result<(String, String)> {
+        let read =
+            |name| std::env::var(name).map_err(|e| anyhow!("Failed to read {name} variable: {e}"));
+        Ok((read(PUBLIC_KEY_ENV)?, read(SECRET_KEY_ENV)?))
+    }
+}
+
+/// Reads the keys in the format of the key file from any reader, like a pipe from a secret
+/// manager.
+struct ReaderKeySource<R>(R);
+
+impl<R: Read> KeySource for ReaderKeySource<R> {
+    fn read_keys(&mut self) -> Result<(String, String)> {
+        let mut blind_signer_cfg = String::new();
+        self.0.read_to_string(&mut blind_signer_cfg)?;
         let mut blind_signer_cfg = blind_signer_cfg.lines().take(2);
-        let (pk, sk) = (
+        Ok((
             blind_signer_cfg
                 .next()
                 .ok_or(anyhow!("Failed to parse blind signer public key"))?
-                .parse()?,
+                .to_owned(),
             blind_signer_cfg
                 .next()
                 .ok_or(anyhow!("Failed to parse blind signer secret key"))?
-                .parse()?,
-        );
-        Ok(blind_sign::BlindSigner::new_from_keys(pk, sk)?)
-    } else {
-        Err(anyhow!("Blind signer config not found"))
+                .to_owned(),
+        ))
     }
 }
 
+/// Load the blind signer from the keys of the source and check that it signs with the options
+/// with which the voters and the nodes blind and verify the access tokens.
+fn load_blind_signer(source: &mut impl KeySource) -> Result<blind_sign::BlindSigner> {
+    let (pk, sk) = source.read_keys()?;
+    let blind_signer =
+        blind_sign::BlindSigner::new_from_keys(pk.trim().parse()?, sk.trim().parse()?)?;
+    blind_sign::assert_options_match(
+        &blind_sign::OptionsFingerprint::protocol(),
+        &blind_signer.options_fingerprint(),
+    )?;
+
+    Ok(blind_signer)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let _tracing_worker_guard = start_logger("mock_authority.log")?;
     let args = Args::parse();
-    let blind_signer = Arc::new(setup_blind_signer(args.new_keys)?);
+    let data_paths = DataPaths::new(&args.data_path)?;
+    let _tracing_worker_guard = start_logger(&data_paths.log_file("mock_authority.log"), false)?;
+    let blind_signer = if args.keys_from_env {
+        load_blind_signer(&mut EnvKeySource)?
+    } else {
+        setup_blind_signer(args.new_keys, &data_paths)?
+    };
+    let blind_signer = Arc::new(blind_signer);
 
-    println!("Starting mock authority server on: {}...", args.addr);
-    println!("With authority PK:\n{}", blind_signer.get_public_key()?);
+    let mut output = Output::open(args.output.as_deref())?;
+
+    writeln!(
+        output,
+        "Starting mock authority server on: {}...",
+        args.addr
+    )?;
+    writeln!(
+        output,
+        "With authority PK:\n{}",
+        blind_signer.get_public_key()?
+    )?;
     if args.no_http_server {
-        run_cli(&blind_signer)?;
+        run_cli(&blind_signer, output)?;
     } else {
         let blind_signer_clone = blind_signer.clone();
-        tokio::task::spawn_blocking(move || run_cli(&blind_signer_clone));
+        tokio::task::spawn_blocking(move || run_cli(&blind_signer_clone, output));
 
         run_server(blind_signer, args).await?;
     }
@@ -123,7 +252,7 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-fn run_cli(blind_signer: &blind_sign::BlindSigner) -> Result<()> {
+fn run_cli(blind_signer: &blind_sign::BlindSigner, mut output: Output) -> Result<()> {
     let mut stdio_reader = StdioReader::new()?;
 
     loop {
@@ -131,44 +260,64 @@ fn run_cli(blind_signer: &blind_sign::BlindSigner) -> Result<()> {
             Ok(line) => line,
             Err(e) => {
                 // TODO
-                println!("Quitting: {e:?}, send interrupt again to kill the server (WIP)");
+                writeln!(
+                    output,
+                    "Quitting: {e:?}, send interrupt again to kill the server (WIP)"
+                )?;
                 break;
             }
         };
-        let res = match Cmd::try_parse_from(line) {
-            Ok(Cmd::BlindSign { blinded_msg }) => blind_signer
-                .bling_sign(&blinded_msg)
-                .map_err(std::convert::Into::into)
-                .map(|blinded_signature| blinded_signature.to_string()),
-            Ok(Cmd::GetPubkey) => blind_signer
-                .get_public_key()
-                .map_err(std::convert::Into::into)
-                .map(|blinder_pk| blinder_pk.to_string()),
-            Err(e) => Err(anyhow!("Unsupported command: {e}")),
-        };
-
-        match res {
-            Ok(res) => println!("{res}"),
-            Err(error) => println!("ERROR: {error}"),
-        }
+        writeln!(output, "{}", execute_command(blind_signer, line))?;
     }
 
     Ok(())
 }
 
+/// Execute a command read from the CLI.
+///
+/// # Returns
+///
+/// The result of the command or the error message, which should be written to the output.
+fn execute_command(blind_signer: &blind_sign::BlindSigner, line: Vec<String>) -> String {
+    let res = match Cmd::try_parse_from(line) {
+        Ok(Cmd::BlindSign { blinded_msg }) => blind_signer
+            .bling_sign(&blinded_msg)
+            .map_err(std::convert::Into::into)
+            .map(|blinded_signature| blinded_signature.to_string()),
+        Ok(Cmd::GetPubkey) => blind_signer
+            .get_public_key()
+            .map_err(std::convert::Into::into)
+            .map(|blinder_pk| blinder_pk.to_string()),
+        Err(e) => Err(anyhow!("Unsupported command: {e}")),
+    };
+
+    match res {
+        Ok(res) => res,
+        Err(error) => format!("ERROR: {error}"),
+    }
+}
+
 async fn run_server(blind_signer: Arc<blind_sign::BlindSigner>, args: Args) -> Result<()> {
-    HttpServer::new(move || {
+    let in_flight_limit = web::Data::new(InFlightLimit::new(args.server_limits.max_in_flight));
+    let mut server = HttpServer::new(move || {
         App::new()
+            .wrap(from_fn(limit_in_flight))
             .app_data(web::Data::new(AppState {
                 blind_signer: blind_signer.clone(),
             }))
+            .app_data(in_flight_limit.clone())
             .service(greet)
             .service(authenticate)
             .service(get_pkey)
-    })
-    .bind(args.addr)?
-    .run()
-    .await?;
+    });
+    if let Some(workers) = args.server_limits.workers {
+        server = server.workers(workers.get());
+    }
+    server
+        .backlog(args.server_limits.backlog)
+        .bind(args.addr)?
+        .run()
+        .await?;
 
     Ok(())
 }
@@ -206,3 +355,51 @@ pub async fn get_pkey(data: web::Data<AppState>) -> impl Responder {
 struct VerificationRequest {
     blinded_pkey: blind_sign::BlindedMessage,
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use process_io::test_utils::TempDir;
+
+    #[test]
+    fn test_key_file_mode() {
+        let dir = TempDir::new("mock-authority");
+        let path = dir.file("keys");
+        // A pre-existing world readable file must be restricted as well.
+        std::fs::write(&path, "").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        new_blind_signer(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        load_blind_signer_from_fs(&path).unwrap();
+    }
+
+    #[test]
+    fn test_in_memory_key_source() {
+        let generated = blind_sign::BlindSigner::new().unwrap();
+        let keys = format!(
+            "{}\n{}\n",
+            generated.get_public_key().unwrap(),
+            generated.get_secret_key().unwrap()
+        );
+        let blind_signer = load_blind_signer(&mut ReaderKeySource(keys.as_bytes())).unwrap();
+
+        let pk = blind_signer.get_public_key().unwrap();
+        let msg = b"Voter public key";
+        let (blinded_msg, unblinder) = blind_sign::Blinder::new(pk.clone())
+            .unwrap()
+            .blind(msg)
+            .unwrap();
+        let signature = unblinder
+            .unblind_signature(blind_signer.bling_sign(&blinded_msg).unwrap(), msg)
+            .unwrap();
+        blind_sign::Verifier::new(pk)
+            .unwrap()
+            .verify_signature(signature, msg)
+            .unwrap();
+
+        assert!(load_blind_signer(&mut ReaderKeySource(b"only one line".as_slice())).is_err());
+    }
+}
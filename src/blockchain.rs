@@ -1,47 +1,163 @@
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::io::{BufRead, Read, Write};
+use std::marker::PhantomData;
+use std::path::Path;
 
+use bincode::Options;
 use ring::digest;
 use thiserror::Error;
 
+use crypto::encryption::symmetric::{self, Encryption, MetaData, METADATA_LEN};
 use protocol::timestamp::Timestamp;
 
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("Blockchain hash integrity error {} != {}", .0, .1)]
-    BlockchainHashIntegrity(Hash, Hash),
-    #[error("Array length mismatch error: {}", .0)]
-    ArrayLenMismatch(#[from] std::array::TryFromSliceError),
+    #[error("Blockchain hash integrity error at block {}: {} != {}", .0, .1, .2)]
+    BlockchainHashIntegrity(usize, Hash, Hash),
+    #[error("Invalid hash length: {} bytes instead of {}", .0, HASH_LEN)]
+    InvalidHashLength(usize),
     #[error("Binary serialization error: {}", .0)]
     BinSerialization(#[from] bincode::Error),
+    #[error("JSON serialization error: {}", .0)]
+    JsonSerialization(#[from] serde_json::Error),
     #[error("File IO error: {}", .0)]
     FileIO(#[from] std::io::Error),
+    #[error("Block frame of {} bytes exceeds the maximum of {}", .0, MAX_FRAME_LEN)]
+    FrameTooLarge(usize),
+    #[error("Block was expected at height {}, but the blockchain is at height {}", .0, .1)]
+    StaleHeight(usize, usize),
+    #[error("Block stream ended in the middle of a frame")]
+    TruncatedFrame,
+    #[error("Encryption at rest error: {}", .0)]
+    Encryption(#[from] symmetric::Error),
+    #[error("Encrypted blockchain file is missing its encryption metadata")]
+    MissingEncryptionMetadata,
+    #[error("Compressed blockchain files can't be repaired, decompress the file first")]
+    CompressedRepair,
+    #[error(
+        "Only plain blockchain files can be repaired, the file is encrypted or not a blockchain"
+    )]
+    EncryptedRepair,
+    #[error("Unsupported block format version {}, expected {}", .0, BLOCK_FORMAT_VERSION)]
+    UnsupportedBlockFormat(u8),
+    #[error(
+        "The blockchain file was written before blocks had a format version and can't be loaded \
+         by this version of the node, see the upgrade notes in the README"
+    )]
+    UnversionedFile,
     #[error("Unknown blockchain error")]
     Unknown,
 }
 
-pub trait BlockValue: for<'de> serde::Deserialize<'de> + serde::Serialize + Display {}
+pub trait BlockValue: for<'de> serde::Deserialize<'de> + serde::Serialize + Display {
+    /// Key by which the value can be looked up in the blockchain with `Blockchain::find`.
+    /// Values are not indexed by default.
+    fn index_key(&self) -> Option<&[u8]> {
+        None
+    }
+}
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct Blockchain<T> {
     blocks: Vec<Block<T>>,
+    /// Height of the first block containing a value with the index key, see `BlockValue`.
+    /// Not saved with the blocks, since it can always be rebuilt from them.
+    #[serde(skip)]
+    index: HashMap<Vec<u8>, usize>,
 }
 
 impl<T: BlockValue> Blockchain<T> {
     pub fn new() -> Self {
-        Self { blocks: Vec::new() }
+        Self {
+            blocks: Vec::new(),
+            index: HashMap::new(),
+        }
     }
 
     pub fn add_block(&mut self, block_value: Vec<T>) -> Result<(), Error> {
-        let prev_block_hash = match self.blocks.last() {
-            Some(block) => block.get_hash()?,
-            None => Hash([0; 32]),
-        };
+        self.add_block_at(self.len(), block_value)
+    }
+
+    /// Add a block, but only if the blockchain is still at the height at which the caller
+    /// built the block, so that a block based on a stale tip can't fork the blockchain.
+    /// On failure the caller should retry with the new tip.
+    pub fn add_block_at(
+        &mut self,
+        expected_height: usize,
+        block_value: Vec<T>,
+    ) -> Result<(), Error> {
+        if expected_height != self.len() {
+            return Err(Error::StaleHeight(expected_height, self.len()));
+        }
+        let prev_block_hash = self.tip_hash()?;
         let block = Block::new(block_value, prev_block_hash);
+        index_block(&mut self.index, self.blocks.len(), &block);
         self.blocks.push(block);
         Ok(())
     }
 
-    pub fn iter(&self) -> ChainIter<T> {
+    /// Remove the last block, for example when it is replaced by a block from a longer fork.
+    pub fn pop_block(&mut self) -> Option<Block<T>> {
+        let block = self.blocks.pop()?;
+        let height = self.blocks.len();
+        for key in block.values.iter().filter_map(BlockValue::index_key) {
+            if self.index.get(key) == Some(&height) {
+                self.index.remove(key);
+            }
+        }
+        Some(block)
+    }
+
+    /// Find the height of the first block containing a value with the index key.
+    pub fn find(&self, key: &[u8]) -> Option<usize> {
+        self.index.get(key).copied()
+    }
+
+    /// Check that all the loaded blocks are in the format of this version of the node and
+    /// rebuild the index, which must be done for every loaded blockchain.
+    fn loaded(mut self) -> Result<Self, Error> {
+        self.blocks
+            .iter()
+            .try_for_each(Block::check_format_version)?;
+        self.rebuild_index();
+        Ok(self)
+    }
+
+    /// Create a blockchain from blocks which were read one by one, for example from a
+    /// `BlockLog`, checking that they are linked by their hashes.
+    fn from_blocks(blocks: Vec<Block<T>>) -> Result<Self, Error> {
+        let blockchain = Self {
+            blocks,
+            index: HashMap::new(),
+        }
+        .loaded()?;
+        blockchain.validate_hashes()?;
+        Ok(blockchain)
+    }
+
+    /// Rebuild the index after the blocks were loaded, since the index is not saved.
+    fn rebuild_index(&mut self) {
+        self.index.clear();
+        for (height, block) in self.blocks.iter().enumerate() {
+            index_block(&mut self.index, height, block);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Get the hash of the last block, which identifies the current state of the whole chain.
+    /// An empty chain has an all-zero hash, same as the previous block hash of the first block.
+    pub fn tip_hash(&self) -> Result<Hash, Error> {
+        match self.blocks.last() {
+            Some(block) => block.get_hash(),
+            None => Ok(Hash([0; 32])),
+        }
+    }
+
+    pub fn iter(&self) -> ChainIter<'_, T> {
         ChainIter {
             container: self,
             index: 0,
@@ -50,10 +166,11 @@ impl<T: BlockValue> Blockchain<T> {
 
     pub fn validate_hashes(&self) -> Result<(), Error> {
         let mut prev_block_hash = Hash([0; 32]);
-        for block in &self.blocks {
+        for (index, block) in self.blocks.iter().enumerate() {
             let block_hash = block.get_hash()?;
             if block.prev_block_hash != prev_block_hash {
                 return Err(Error::BlockchainHashIntegrity(
+                    index,
                     block.prev_block_hash.clone(),
                     prev_block_hash.clone(),
                 ));
@@ -63,16 +180,356 @@ impl<T: BlockValue> Blockchain<T> {
         Ok(())
     }
 
+    /// Find all the blocks whose previous block hash does not match the block before them.
+    /// Unlike `validate_hashes` this doesn't stop at the first broken link.
+    pub fn broken_links(&self) -> Result<Vec<usize>, Error> {
+        let mut broken_links = Vec::new();
+        let mut prev_block_hash = Hash([0; 32]);
+        for (index, block) in self.blocks.iter().enumerate() {
+            if block.prev_block_hash != prev_block_hash {
+                broken_links.push(index);
+            }
+            prev_block_hash = block.get_hash()?;
+        }
+        Ok(broken_links)
+    }
+
+    /// Get the blocks from the height onwards.
+    pub fn blocks_from(&self, height: usize) -> &[Block<T>] {
+        self.blocks.get(height..).unwrap_or_default()
+    }
+
+    /// Encode the blocks from the height onwards into a stream of frames, see `encode_block`.
+    pub fn encode_blocks_from(&self, height: usize) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        for block in self.blocks_from(height) {
+            encode_block(block, &mut bytes)?;
+        }
+        Ok(bytes)
+    }
+
+    /// Encode the blocks from the height onwards as a JSON array of blocks in the shape of
+    /// `Block::to_json`, for the peers and tools which can't decode `encode_blocks_from`.
+    /// The array can be decoded with `decode_json_blocks`.
+    pub fn encode_json_blocks_from(&self, height: usize) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec(self.blocks_from(height))?)
+    }
+
+    /// Save the blockchain into a file, which can later be loaded with `load_from_file`.
+    /// The file is compressed according to its extension, see `Compression::from_extension`.
     pub fn save_to_file(&self, filename: &str) -> Result<(), Error> {
-        let file = std::fs::File::create(filename)?;
-        bincode::serialize_into(file, &self)?;
-        Ok(())
+        self.save_to_compressed_file(filename, Compression::from_extension(filename))
+    }
+
+    /// Save the blockchain into a file compressed with the compression, which can later be
+    /// loaded with `load_from_file`, regardless of the extension of the file.
+    /// The file is replaced atomically, see `write_atomically`.
+    pub fn save_to_compressed_file(
+        &self,
+        filename: &str,
+        compression: Compression,
+    ) -> Result<(), Error> {
+        write_atomically(filename, |writer| {
+            match compression {
+                Compression::None => bincode::serialize_into(writer, &self)?,
+                Compression::Gzip => {
+                    let mut encoder =
+                        flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+                    bincode::serialize_into(&mut encoder, &self)?;
+                    encoder.finish()?;
+                }
+                Compression::Zstd => {
+                    let mut encoder = zstd::Encoder::new(writer, 0)?;
+                    bincode::serialize_into(&mut encoder, &self)?;
+                    encoder.finish()?;
+                }
+            }
+            Ok(())
+        })
     }
 
+    /// Load a blockchain which had been saved with `save_to_file`.
+    /// The compression of the file is detected from its contents, see `Compression::detect`.
+    /// An existing but empty file is treated as a fresh blockchain, since such a file is left
+    /// behind if the node is stopped before anything is saved into it.
+    /// A file written before blocks had a format version fails with `Error::UnversionedFile`.
     pub fn load_from_file(filename: &str) -> Result<Self, Error> {
+        if std::fs::metadata(filename)?.len() == 0 {
+            return Ok(Self::new());
+        }
+        decode_file::<Self>(filename)
+            .and_then(Self::loaded)
+            .map_err(|e| unversioned_or::<T>(e, decode_file(filename).ok()))
+    }
+
+    /// Recover the valid prefix of a partially corrupted file saved with `save_to_file`, for
+    /// example after a crash in the middle of a write. The blocks are read one by one until
+    /// the first unreadable block or until the first block whose hash doesn't match the
+    /// previous block hash of the block after it. The file is rewritten with the blocks
+    /// before that point, if any blocks had to be dropped.
+    /// The file is replaced atomically, so it's left intact if the repair is interrupted.
+    /// Encrypted files can't be repaired, since they can only be decrypted as a whole. They start
+    /// with random encryption metadata instead of the amount of blocks, which is detected by the
+    /// amount of blocks not fitting into the file, so that they're never overwritten.
+    pub fn repair(filename: &str) -> Result<RepairReport, Error> {
         let file = std::fs::File::open(filename)?;
-        let blockchain: Self = bincode::deserialize_from(file)?;
-        Ok(blockchain)
+        let len = file.metadata()?.len();
+        let options = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(len);
+        let mut reader = std::io::BufReader::new(file);
+        if Compression::detect(reader.fill_buf()?) != Compression::None {
+            return Err(Error::CompressedRepair);
+        }
+        // Blocks without a format version would all be dropped as unreadable.
+        if decode_file::<Vec<UnversionedBlock<T>>>(filename)
+            .is_ok_and(|blocks| is_unversioned_chain(&blocks))
+        {
+            return Err(Error::UnversionedFile);
+        }
+        // A blockchain is serialized as the amount of blocks followed by the blocks.
+        let declared: u64 = options.deserialize_from(&mut reader)?;
+        // Every block takes up far more than a byte.
+        if declared > len {
+            return Err(Error::EncryptedRepair);
+        }
+
+        let mut blockchain = Self::new();
+        let mut prev_block_hash = Hash([0; 32]);
+        for _ in 0..declared {
+            let Ok(block) = options.deserialize_from::<_, Block<T>>(&mut reader) else {
+                break;
+            };
+            if block.check_format_version().is_err() {
+                break;
+            }
+            if block.prev_block_hash != prev_block_hash {
+                // The previous block was altered, so it can't be trusted either.
+                blockchain.pop_block();
+                break;
+            }
+            prev_block_hash = block.get_hash()?;
+            index_block(&mut blockchain.index, blockchain.blocks.len(), &block);
+            blockchain.blocks.push(block);
+        }
+
+        let report = RepairReport {
+            recovered: blockchain.len(),
+            dropped: usize::try_from(declared)
+                .unwrap_or(usize::MAX)
+                .saturating_sub(blockchain.len()),
+        };
+        if report.dropped > 0 {
+            blockchain.save_to_file(filename)?;
+        }
+        Ok(report)
+    }
+
+    /// Save the blockchain into a file encrypted with a key derived from the secret, which can
+    /// later be loaded with `load_from_encrypted_file`. The file starts with the encryption
+    /// metadata, followed by the encrypted blockchain. The hashes of the blocks are computed
+    /// over the plain blocks, so the encryption is transparent to the rest of the blockchain.
+    /// The file is replaced atomically, see `write_atomically`.
+    pub fn save_to_encrypted_file(&self, filename: &str, secret: &[u8]) -> Result<(), Error> {
+        let mut bytes = bincode::serialize(&self)?;
+        let metadata = Encryption::new(secret)?.encrypt(&mut bytes)?;
+        write_atomically(filename, |writer| {
+            writer.write_all(metadata.as_ref())?;
+            writer.write_all(&bytes)?;
+            Ok(())
+        })
+    }
+
+    /// Load a blockchain which had been saved with `save_to_encrypted_file`.
+    /// An existing but empty file is treated as a fresh blockchain and a file written before
+    /// blocks had a format version is refused, same as in `load_from_file`.
+    pub fn load_from_encrypted_file(filename: &str, secret: &[u8]) -> Result<Self, Error> {
+        let mut bytes = std::fs::read(filename)?;
+        if bytes.is_empty() {
+            return Ok(Self::new());
+        }
+        let (metadata, encrypted) = split_metadata(&mut bytes)?;
+        let decrypted = Encryption::load(secret, &metadata)?.decrypt(encrypted, &metadata)?;
+        bincode::deserialize::<Self>(decrypted)
+            .map_err(Error::from)
+            .and_then(Self::loaded)
+            .map_err(|e| unversioned_or::<T>(e, bincode::deserialize(decrypted).ok()))
+    }
+}
+
+/// Split encrypted bytes into the encryption metadata in front of them and the encrypted bytes.
+fn split_metadata(bytes: &mut [u8]) -> Result<(MetaData, &mut [u8]), Error> {
+    if bytes.len() < METADATA_LEN {
+        return Err(Error::MissingEncryptionMetadata);
+    }
+    let (metadata, encrypted) = bytes.split_at_mut(METADATA_LEN);
+    let metadata = MetaData::from_bytes(
+        <[u8; METADATA_LEN]>::try_from(&*metadata).map_err(|_| Error::MissingEncryptionMetadata)?,
+    );
+    Ok((metadata, encrypted))
+}
+
+/// Decode a file saved with `save_to_file`, detecting its compression, see `Compression::detect`.
+/// Decoding is limited to the size of the file, so that a corrupted or an encrypted file
+/// fails to load instead of making the node try to allocate the lengths read from it.
+/// Compressed files are limited to `MAX_COMPRESSION_RATIO` times their size instead.
+fn decode_file<D: serde::de::DeserializeOwned>(filename: &str) -> Result<D, Error> {
+    let file = std::fs::File::open(filename)?;
+    let len = file.metadata()?.len();
+    let mut reader = std::io::BufReader::new(file);
+    // Same options as `bincode::deserialize_from`, apart from the limit.
+    let options = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes();
+    let decompressed_limit = len.saturating_mul(MAX_COMPRESSION_RATIO);
+    Ok(match Compression::detect(reader.fill_buf()?) {
+        Compression::None => options.with_limit(len).deserialize_from(reader)?,
+        Compression::Gzip => options
+            .with_limit(decompressed_limit)
+            .deserialize_from(flate2::bufread::GzDecoder::new(reader))?,
+        Compression::Zstd => options
+            .with_limit(decompressed_limit)
+            .deserialize_from(zstd::Decoder::with_buffer(reader)?)?,
+    })
+}
+
+/// Layout of the blocks written before `BLOCK_FORMAT_VERSION` was introduced, which is only
+/// used to tell such files apart from the corrupted ones.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct UnversionedBlock<T> {
+    values: Vec<T>,
+    timestamp: Timestamp,
+    prev_block_hash: Hash,
+}
+
+/// Check whether the blocks decoded in the layout without a format version are linked by
+/// their hashes, in which case they were really written in that layout.
+fn is_unversioned_chain<T: BlockValue>(blocks: &[UnversionedBlock<T>]) -> bool {
+    let mut prev_block_hash = Hash([0; 32]);
+    for block in blocks {
+        if block.prev_block_hash != prev_block_hash {
+            return false;
+        }
+        let Ok(bytes) = bincode::serialize(block) else {
+            return false;
+        };
+        let Ok(hash) = digest::digest(&digest::SHA256, &bytes).try_into() else {
+            return false;
+        };
+        prev_block_hash = hash;
+    }
+    !blocks.is_empty()
+}
+
+/// Replace the error of loading a blockchain with `Error::UnversionedFile`, if the blockchain
+/// could also be decoded as blocks without a format version.
+fn unversioned_or<T: BlockValue>(e: Error, unversioned: Option<Vec<UnversionedBlock<T>>>) -> Error {
+    match unversioned {
+        Some(blocks) if is_unversioned_chain(&blocks) => Error::UnversionedFile,
+        _ => e,
+    }
+}
+
+/// Replace the file with the contents written by `write`, so that a crash in the middle of
+/// saving leaves either the old or the new file behind, but never a mix of the two.
+/// The contents are written into a temporary file next to the file, which is synced before it's
+/// renamed over the file. Dropping a file only hands its contents to the OS without making them
+/// durable.
+fn write_atomically(
+    filename: &str,
+    write: impl FnOnce(&mut std::io::BufWriter<std::fs::File>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let tmp_filename = format!("{filename}.tmp");
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(&tmp_filename)?);
+    let written = write(&mut writer).and_then(|()| {
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        Ok(())
+    });
+    drop(writer);
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&tmp_filename);
+        return Err(e);
+    }
+    std::fs::rename(&tmp_filename, filename)?;
+    Ok(())
+}
+
+/// The most by which a compressed blockchain file may expand when it's loaded. Votes consist
+/// mostly of keys and signatures, which don't compress, so real files stay far below it.
+const MAX_COMPRESSION_RATIO: u64 = 64;
+
+/// Compression of a saved blockchain file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Plain bincode, which is also how encrypted files are stored.
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Gzip magic bytes, followed by the deflate compression method.
+    const GZIP_MAGIC: [u8; 3] = [0x1f, 0x8b, 0x08];
+    /// Zstandard frame magic bytes.
+    const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+    /// Choose the compression by the extension of the file: `.gz` for gzip, `.zst` for
+    /// Zstandard and none otherwise.
+    #[must_use]
+    pub fn from_extension(filename: &str) -> Self {
+        match std::path::Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("gz") => Self::Gzip,
+            Some("zst") => Self::Zstd,
+            _ => Self::None,
+        }
+    }
+
+    /// Detect the compression from the start of the file.
+    /// Plain files start with the amount of blocks as a little endian `u64`, which would have
+    /// to be in the hundreds of thousands for gzip and in the billions for Zstandard magic bytes
+    /// to appear there, way beyond the size of any election.
+    #[must_use]
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(&Self::GZIP_MAGIC) {
+            Self::Gzip
+        } else if header.starts_with(&Self::ZSTD_MAGIC) {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+}
+
+/// Add the index keys of the values in the block at the height to the index.
+fn index_block<T: BlockValue>(
+    index: &mut HashMap<Vec<u8>, usize>,
+    height: usize,
+    block: &Block<T>,
+) {
+    for key in block.values.iter().filter_map(BlockValue::index_key) {
+        index.entry(key.to_vec()).or_insert(height);
+    }
+}
+
+/// Outcome of `Blockchain::repair`.
+#[derive(PartialEq, Debug)]
+pub struct RepairReport {
+    /// Amount of valid blocks kept in the file.
+    pub recovered: usize,
+    /// Amount of blocks dropped from the end of the blockchain.
+    pub dropped: usize,
+}
+
+impl Display for RepairReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Recovered blocks: {}", self.recovered)?;
+        writeln!(f, "Dropped blocks: {}", self.dropped)
     }
 }
 
@@ -103,8 +560,15 @@ impl<T: BlockValue> Display for Blockchain<T> {
     }
 }
 
+/// Version of the layout in which blocks are serialized, which must be bumped whenever the
+/// layout of `Block` or of the values stored in it changes, so that blocks written in another
+/// layout are refused instead of being misread.
+pub const BLOCK_FORMAT_VERSION: u8 = 1;
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct Block<T> {
+    /// Serialized first, so that it can be checked before the rest of the block is trusted.
+    format_version: u8,
     values: Vec<T>,
     timestamp: Timestamp,
     prev_block_hash: Hash,
@@ -116,17 +580,46 @@ impl<T: BlockValue> Block<T> {
         let timestamp = chrono::Utc::now();
 
         Self {
+            format_version: BLOCK_FORMAT_VERSION,
             values: block_value,
             timestamp,
             prev_block_hash,
         }
     }
 
+    /// Check that the block is in the format of this version of the node.
+    fn check_format_version(&self) -> Result<(), Error> {
+        if self.format_version != BLOCK_FORMAT_VERSION {
+            return Err(Error::UnsupportedBlockFormat(self.format_version));
+        }
+        Ok(())
+    }
+
     fn get_hash(&self) -> Result<Hash, Error> {
         let bytes = bincode::serialize(&self)?;
         let hash = digest::digest(&digest::SHA256, &bytes).try_into()?;
         Ok(hash)
     }
+
+    /// Export the block as pretty printed JSON, for inspecting it with other tools.
+    /// The blocks are always stored and hashed as bincode, JSON is only meant for debugging.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Import a block which had been exported with `to_json`.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let block: Self = serde_json::from_str(json)?;
+        block.check_format_version()?;
+        Ok(block)
+    }
+}
+
+impl<T> Block<T> {
+    #[must_use]
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
 }
 
 impl<T: BlockValue> Display for Block<T> {
@@ -145,15 +638,272 @@ impl<T: BlockValue> Display for Block<T> {
     }
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Clone)]
-pub struct Hash([u8; 32]);
+/// The maximum size of a single encoded block in a block stream, so that a malicious peer
+/// can't make the node allocate an arbitrary amount of memory.
+pub const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Size of the length in front of every frame in bytes, see `encode_block`.
+const FRAME_HEADER_LEN: usize = 4;
+
+/// Encode a block for streaming it to a peer.
+/// Each block is framed as its length in bytes as a little endian `u32`, followed by the bincode
+/// encoding of the block, so that the blocks can be decoded one by one as the stream arrives.
+pub fn encode_block<T: BlockValue>(block: &Block<T>, out: &mut Vec<u8>) -> Result<(), Error> {
+    encode_frame(&bincode::serialize(block)?, out)
+}
+
+/// Frame the bytes the same way as the blocks of `encode_block`.
+fn encode_frame(bytes: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+    let len = u32::try_from(bytes.len())
+        .ok()
+        .filter(|len| *len as usize <= MAX_FRAME_LEN)
+        .ok_or(Error::FrameTooLarge(bytes.len()))?;
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+/// Read the next frame written by `encode_frame`, or return `None` if the stream ended cleanly
+/// before it.
+fn read_frame(reader: &mut impl Read) -> Result<Option<Vec<u8>>, Error> {
+    let mut len = [0; FRAME_HEADER_LEN];
+    let mut read = 0;
+    while read < len.len() {
+        match reader.read(&mut len[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(Error::TruncatedFrame),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(Error::FrameTooLarge(len));
+    }
+
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::UnexpectedEof {
+            Error::TruncatedFrame
+        } else {
+            e.into()
+        }
+    })?;
+    Ok(Some(frame))
+}
+
+/// Decode a block from a frame written by `encode_block`.
+fn decode_block<T: BlockValue>(frame: &[u8]) -> Result<Block<T>, Error> {
+    let block: Block<T> = bincode::deserialize(frame)?;
+    block.check_format_version()?;
+    Ok(block)
+}
+
+/// Decode a stream of blocks encoded with `encode_block`.
+/// The returned iterator ends when the stream ends between two frames and yields an error if
+/// the stream ends in the middle of a frame.
+pub fn decode_block_stream<T: BlockValue, R: Read>(reader: R) -> BlockStream<R, T> {
+    BlockStream {
+        reader,
+        value: PhantomData,
+    }
+}
+
+/// Decode the JSON array of blocks encoded with `Blockchain::encode_json_blocks_from`.
+pub fn decode_json_blocks<T: BlockValue>(json: &[u8]) -> Result<Vec<Block<T>>, Error> {
+    let blocks: Vec<Block<T>> = serde_json::from_slice(json)?;
+    for block in &blocks {
+        block.check_format_version()?;
+    }
+    Ok(blocks)
+}
+
+/// Iterator over the blocks of a block stream, see `decode_block_stream`.
+pub struct BlockStream<R, T> {
+    reader: R,
+    value: PhantomData<T>,
+}
+
+impl<R: Read, T: BlockValue> Iterator for BlockStream<R, T> {
+    type Item = Result<Block<T>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match read_frame(&mut self.reader) {
+            Ok(Some(frame)) => Some(decode_block(&frame)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Append-only file of the blocks of a blockchain, so that appending a block only writes that
+/// block instead of rewriting the whole blockchain like `save_to_file` does.
+/// The blocks are written as the frames of `encode_block`. If the blocks are encrypted at rest,
+/// every frame contains the encryption metadata followed by the encrypted block instead, so that
+/// every block is encrypted on its own.
+pub struct BlockLog {
+    file: std::fs::File,
+    /// Size of the complete frames in the file in bytes.
+    size: u64,
+    /// Encryption of the blocks, if they are encrypted at rest.
+    encryption: Option<Encryption>,
+}
+
+impl BlockLog {
+    /// Open the block log in the file, creating the file if it doesn't exist yet, and replay the
+    /// blocks in it. A frame cut short at the end of the file, because the node stopped in the
+    /// middle of appending it, had never been appended, so it's truncated instead.
+    ///
+    /// # Arguments
+    ///
+    /// - `path` - The file of the log.
+    /// - `secret` - The secret with which the blocks are encrypted at rest, if they are.
+    ///
+    /// # Returns
+    ///
+    /// The blockchain of the replayed blocks and the log, to which the blocks appended to the
+    /// blockchain are written with `append`.
+    ///
+    /// # Errors
+    ///
+    /// If the file could not be opened, read or truncated, if a block could not be decrypted or
+    /// decoded or if the blocks are not linked by their hashes.
+    pub fn open<T: BlockValue>(
+        path: &Path,
+        secret: Option<&[u8]>,
+    ) -> Result<(Blockchain<T>, Self), Error> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = std::io::BufReader::new(&file);
+        let mut blocks = Vec::new();
+        let mut size = 0;
+        let mut encryption = None;
+        loop {
+            let mut frame = match read_frame(&mut reader) {
+                Ok(Some(frame)) => frame,
+                Ok(None) | Err(Error::TruncatedFrame) => break,
+                Err(e) => return Err(e),
+            };
+            size += (FRAME_HEADER_LEN + frame.len()) as u64;
+            let block = match secret {
+                Some(secret) => {
+                    let (metadata, encrypted) = split_metadata(&mut frame)?;
+                    // All the blocks are encrypted with the key derived for the first one.
+                    let encryption = match &mut encryption {
+                        Some(encryption) => encryption,
+                        None => encryption.insert(Encryption::load(secret, &metadata)?),
+                    };
+                    decode_block(encryption.decrypt(encrypted, &metadata)?)?
+                }
+                None => decode_block(&frame)?,
+            };
+            blocks.push(block);
+        }
+        drop(reader);
+        if size < file_len {
+            tracing::warn!(
+                "Truncating {} bytes of an incomplete block at the end of block log {}",
+                file_len - size,
+                path.display()
+            );
+            file.set_len(size)?;
+        }
+        let blockchain = Blockchain::from_blocks(blocks)?;
+        let encryption = match (encryption, secret) {
+            (None, Some(secret)) => Some(Encryption::new(secret)?),
+            (encryption, _) => encryption,
+        };
+
+        Ok((
+            blockchain,
+            Self {
+                file,
+                size,
+                encryption,
+            },
+        ))
+    }
+
+    /// Get the size of the log in bytes, with which the blocks appended afterwards can be
+    /// removed again, see `truncate`.
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Append the blocks to the log and sync them to the disk, so that they survive a crash once
+    /// this returns. The blocks are written at once and removed again if writing them fails.
+    pub fn append<'a, T: BlockValue + 'a>(
+        &mut self,
+        blocks: impl IntoIterator<Item = &'a Block<T>>,
+    ) -> Result<(), Error> {
+        let mut bytes = Vec::new();
+        for block in blocks {
+            match &self.encryption {
+                Some(encryption) => {
+                    let mut encrypted = bincode::serialize(block)?;
+                    let metadata = encryption.encrypt(&mut encrypted)?;
+                    encode_frame(&[metadata.as_ref(), &encrypted].concat(), &mut bytes)?;
+                }
+                None => encode_block(block, &mut bytes)?,
+            }
+        }
+        if let Err(e) = self
+            .file
+            .write_all(&bytes)
+            .and_then(|()| self.file.sync_data())
+        {
+            // A part of the blocks may have been written, which must not be replayed.
+            self.truncate(self.size)?;
+            return Err(e.into());
+        }
+        self.size += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Remove the blocks appended after the log had the size, for example when the blocks had
+    /// to be removed from the blockchain again.
+    pub fn truncate(&mut self, size: u64) -> Result<(), Error> {
+        self.file.set_len(size)?;
+        self.file.sync_data()?;
+        self.size = size;
+        Ok(())
+    }
+}
+
+/// Length of the block hashes in bytes.
+pub const HASH_LEN: usize = 32;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Clone, Default)]
+pub struct Hash([u8; HASH_LEN]);
+
+impl Hash {
+    /// Create a hash from the output of a hasher, checking that it has the right length,
+    /// so that plugging in a hasher with a different output size fails loudly.
+    pub fn try_from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| Error::InvalidHashLength(bytes.len()))?;
+        Ok(Self(bytes))
+    }
+}
 
 impl TryFrom<digest::Digest> for Hash {
     type Error = Error;
 
     fn try_from(value: digest::Digest) -> Result<Self, Self::Error> {
-        let bytes = value.as_ref().try_into()?;
-        Ok(Self(bytes))
+        Self::try_from_slice(value.as_ref())
+    }
+}
+
+impl AsRef<[u8]> for Hash {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
     }
 }
 
@@ -165,3 +915,441 @@ impl std::fmt::Display for Hash {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use process_io::test_utils::TempDir;
+    use protocol::{candidate_id::CandidateId, test_utils::make_vote_at, vote::Vote};
+
+    impl BlockValue for u32 {}
+
+    #[test]
+    fn test_load_empty_file() {
+        let dir = TempDir::new("empty-chain");
+        let path = dir.file("empty.chain");
+        std::fs::File::create(&path).unwrap();
+
+        let mut blockchain = Blockchain::<u32>::load_from_file(&path).unwrap();
+        assert_eq!(blockchain.len(), 0);
+        blockchain.validate_hashes().unwrap();
+        blockchain.add_block(vec![1, 2, 3]).unwrap();
+        blockchain.save_to_file(&path).unwrap();
+        assert_eq!(Blockchain::<u32>::load_from_file(&path).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_stale_height() {
+        let mut blockchain = Blockchain::<u32>::new();
+        let height = blockchain.len();
+        blockchain.add_block_at(height, vec![1]).unwrap();
+
+        // Another writer built its block on the same tip, which has moved since.
+        assert!(matches!(
+            blockchain.add_block_at(height, vec![2]),
+            Err(Error::StaleHeight(0, 1))
+        ));
+        assert_eq!(blockchain.len(), 1);
+        blockchain.add_block_at(blockchain.len(), vec![2]).unwrap();
+        blockchain.validate_hashes().unwrap();
+    }
+
+    #[test]
+    fn test_repair() {
+        let dir = TempDir::new("repair");
+        let path = dir.file("repair.chain");
+        let mut blockchain = Blockchain::<u32>::new();
+        for block in [vec![1], vec![2], vec![0xdead_beef], vec![4]] {
+            blockchain.add_block(block).unwrap();
+        }
+        blockchain.save_to_file(&path).unwrap();
+        let intact = std::fs::read(&path).unwrap();
+        assert_eq!(
+            Blockchain::<u32>::repair(&path).unwrap(),
+            RepairReport {
+                recovered: 4,
+                dropped: 0
+            }
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), intact);
+
+        // Corrupting the third block breaks the previous block hash of the fourth one.
+        let mut bytes = intact.clone();
+        let position = bytes
+            .windows(4)
+            .position(|window| window == 0xdead_beef_u32.to_le_bytes())
+            .unwrap();
+        bytes[position] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(
+            Blockchain::<u32>::repair(&path).unwrap(),
+            RepairReport {
+                recovered: 2,
+                dropped: 2
+            }
+        );
+        let repaired = Blockchain::<u32>::load_from_file(&path).unwrap();
+        repaired.validate_hashes().unwrap();
+        assert_eq!(repaired.iter().collect::<Vec<_>>(), [&vec![1], &vec![2]]);
+
+        // A write cut short in the middle of the last block.
+        std::fs::write(&path, &intact[..intact.len() - 1]).unwrap();
+        assert_eq!(
+            Blockchain::<u32>::repair(&path).unwrap(),
+            RepairReport {
+                recovered: 3,
+                dropped: 1
+            }
+        );
+        assert_eq!(Blockchain::<u32>::load_from_file(&path).unwrap().len(), 3);
+        assert!(!std::path::Path::new(&format!("{path}.tmp")).exists());
+
+        // Encrypted files are left untouched.
+        blockchain.save_to_encrypted_file(&path, b"secret").unwrap();
+        let encrypted = std::fs::read(&path).unwrap();
+        assert!(matches!(
+            Blockchain::<u32>::repair(&path),
+            Err(Error::EncryptedRepair)
+        ));
+        assert_eq!(std::fs::read(&path).unwrap(), encrypted);
+    }
+
+    #[test]
+    fn test_block_stream() {
+        let mut blockchain = Blockchain::<u32>::new();
+        for block in [vec![1, 2], vec![3], vec![4, 5, 6]] {
+            blockchain.add_block(block).unwrap();
+        }
+
+        let bytes = blockchain.encode_blocks_from(0).unwrap();
+        let blocks = decode_block_stream::<u32, _>(bytes.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(blocks.len(), 3);
+        for (decoded, block) in blocks.iter().zip(&blockchain.blocks) {
+            assert_eq!(decoded.values(), block.values());
+            assert_eq!(decoded.get_hash().unwrap(), block.get_hash().unwrap());
+        }
+
+        let bytes = blockchain.encode_blocks_from(2).unwrap();
+        let blocks = decode_block_stream::<u32, _>(bytes.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(
+            blocks[0].prev_block_hash,
+            blockchain.blocks[1].get_hash().unwrap()
+        );
+        assert!(blockchain.encode_blocks_from(3).unwrap().is_empty());
+
+        let mut stream = decode_block_stream::<u32, _>(&bytes[..bytes.len() - 1]);
+        assert!(matches!(stream.next(), Some(Err(Error::TruncatedFrame))));
+        let mut stream = decode_block_stream::<u32, _>(&bytes[..2]);
+        assert!(matches!(stream.next(), Some(Err(Error::TruncatedFrame))));
+        let too_large = u32::try_from(MAX_FRAME_LEN + 1).unwrap().to_le_bytes();
+        let mut stream = decode_block_stream::<u32, _>(too_large.as_slice());
+        assert!(matches!(stream.next(), Some(Err(Error::FrameTooLarge(_)))));
+    }
+
+    #[test]
+    fn test_block_log() {
+        let dir = TempDir::new("block-log");
+        let path = dir.join("log.blocks");
+
+        let (mut blockchain, mut log) = BlockLog::open::<u32>(&path, None).unwrap();
+        assert_eq!(blockchain.len(), 0);
+        for block in [vec![1, 2], vec![3]] {
+            blockchain.add_block(block).unwrap();
+            log.append(blockchain.blocks_from(blockchain.len() - 1))
+                .unwrap();
+        }
+        let size = log.size();
+        blockchain.add_block(vec![4, 5, 6]).unwrap();
+        log.append(blockchain.blocks_from(2)).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), log.size());
+        drop(log);
+
+        let (replayed, mut log) = BlockLog::open::<u32>(&path, None).unwrap();
+        assert_eq!(replayed.tip_hash().unwrap(), blockchain.tip_hash().unwrap());
+
+        // Removing the last block, for example after failing to audit it.
+        log.truncate(size).unwrap();
+        blockchain.pop_block();
+        drop(log);
+        let (replayed, log) = BlockLog::open::<u32>(&path, None).unwrap();
+        assert_eq!(replayed.tip_hash().unwrap(), blockchain.tip_hash().unwrap());
+        drop(log);
+
+        // A block cut short by a crash is truncated, so that the next block follows the last
+        // complete one.
+        let complete = std::fs::read(&path).unwrap();
+        let mut cut = complete.clone();
+        encode_block(
+            &Block::new(vec![7], blockchain.tip_hash().unwrap()),
+            &mut cut,
+        )
+        .unwrap();
+        cut.pop();
+        std::fs::write(&path, &cut).unwrap();
+        let (replayed, mut log) = BlockLog::open::<u32>(&path, None).unwrap();
+        assert_eq!(replayed.len(), 2);
+        assert_eq!(std::fs::read(&path).unwrap(), complete);
+        blockchain.add_block(vec![8]).unwrap();
+        log.append(blockchain.blocks_from(2)).unwrap();
+        drop(log);
+        let (replayed, _) = BlockLog::open::<u32>(&path, None).unwrap();
+        assert_eq!(replayed.tip_hash().unwrap(), blockchain.tip_hash().unwrap());
+
+        // Blocks which are not linked by their hashes are refused.
+        let mut unlinked = Vec::new();
+        encode_block(&blockchain.blocks[0], &mut unlinked).unwrap();
+        encode_block(&blockchain.blocks[2], &mut unlinked).unwrap();
+        std::fs::write(&path, &unlinked).unwrap();
+        assert!(matches!(
+            BlockLog::open::<u32>(&path, None),
+            Err(Error::BlockchainHashIntegrity(1, _, _))
+        ));
+    }
+
+    #[test]
+    fn test_encrypted_block_log() {
+        let dir = TempDir::new("encrypted-block-log");
+        let path = dir.join("log.blocks");
+
+        let mut blockchain = Blockchain::<u32>::new();
+        for block in [vec![0xdead_beef], vec![0xdead_beef, 7]] {
+            let (_, mut log) = BlockLog::open::<u32>(&path, Some(b"secret")).unwrap();
+            blockchain.add_block(block).unwrap();
+            log.append(blockchain.blocks_from(blockchain.len() - 1))
+                .unwrap();
+        }
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(!bytes
+            .windows(4)
+            .any(|window| window == 0xdead_beef_u32.to_le_bytes()));
+        assert!(matches!(
+            BlockLog::open::<u32>(&path, Some(b"wrong secret")),
+            Err(Error::Encryption(symmetric::Error::Decryption))
+        ));
+        assert!(BlockLog::open::<u32>(&path, None).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+
+        let (replayed, _) = BlockLog::open::<u32>(&path, Some(b"secret")).unwrap();
+        assert_eq!(replayed.tip_hash().unwrap(), blockchain.tip_hash().unwrap());
+        assert_eq!(
+            replayed.iter().collect::<Vec<_>>(),
+            blockchain.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_block_format_version() {
+        let dir = TempDir::new("format");
+        let path = dir.file("format.chain");
+        let mut blockchain = Blockchain::<u32>::new();
+        for block in [vec![1, 2], vec![3], vec![4, 5, 6]] {
+            blockchain.add_block(block).unwrap();
+        }
+        assert!(blockchain
+            .blocks
+            .iter()
+            .all(|block| block.format_version == BLOCK_FORMAT_VERSION));
+        // The version is the first byte of a serialized block.
+        assert_eq!(
+            bincode::serialize(&blockchain.blocks[0]).unwrap()[0],
+            BLOCK_FORMAT_VERSION
+        );
+
+        // A block written by a node with another layout is refused wherever it's read.
+        blockchain.blocks[1].format_version = BLOCK_FORMAT_VERSION + 1;
+        blockchain.save_to_file(&path).unwrap();
+        assert!(matches!(
+            Blockchain::<u32>::load_from_file(&path),
+            Err(Error::UnsupportedBlockFormat(version)) if version == BLOCK_FORMAT_VERSION + 1
+        ));
+        let bytes = blockchain.encode_blocks_from(0).unwrap();
+        let mut stream = decode_block_stream::<u32, _>(bytes.as_slice());
+        assert!(matches!(stream.next(), Some(Ok(_))));
+        assert!(matches!(
+            stream.next(),
+            Some(Err(Error::UnsupportedBlockFormat(_)))
+        ));
+        let json = blockchain.blocks[1].to_json().unwrap();
+        assert!(matches!(
+            Block::<u32>::from_json(&json),
+            Err(Error::UnsupportedBlockFormat(_))
+        ));
+
+        // Repairing keeps only the blocks before it.
+        let report = Blockchain::<u32>::repair(&path).unwrap();
+        assert_eq!(report.recovered, 1);
+        assert_eq!(Blockchain::<u32>::load_from_file(&path).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_unversioned_file() {
+        let dir = TempDir::new("unversioned");
+        let path = dir.file("unversioned.chain");
+        let mut blocks = Vec::new();
+        let mut prev_block_hash = Hash([0; 32]);
+        for values in [vec![1u32, 2], vec![3]] {
+            let block = UnversionedBlock {
+                values,
+                timestamp: chrono::Utc::now(),
+                prev_block_hash,
+            };
+            prev_block_hash = digest::digest(&digest::SHA256, &bincode::serialize(&block).unwrap())
+                .try_into()
+                .unwrap();
+            blocks.push(block);
+        }
+        let mut bytes = bincode::serialize(&blocks).unwrap();
+
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            Blockchain::<u32>::load_from_file(&path),
+            Err(Error::UnversionedFile)
+        ));
+        // Repairing would drop every block, so the file is left as it is.
+        assert!(matches!(
+            Blockchain::<u32>::repair(&path),
+            Err(Error::UnversionedFile)
+        ));
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+
+        // Unlinked blocks are just a corrupted file.
+        blocks[1].prev_block_hash = Hash([0; 32]);
+        std::fs::write(&path, bincode::serialize(&blocks).unwrap()).unwrap();
+        assert!(!matches!(
+            Blockchain::<u32>::load_from_file(&path),
+            Err(Error::UnversionedFile) | Ok(_)
+        ));
+
+        let metadata = Encryption::new(b"secret")
+            .unwrap()
+            .encrypt(&mut bytes)
+            .unwrap();
+        std::fs::write(&path, [metadata.as_ref(), &bytes].concat()).unwrap();
+        assert!(matches!(
+            Blockchain::<u32>::load_from_encrypted_file(&path, b"secret"),
+            Err(Error::UnversionedFile)
+        ));
+    }
+
+    #[test]
+    fn test_hash_length() {
+        let hash = Hash::try_from_slice(&[7; HASH_LEN]).unwrap();
+        assert_eq!(hash.as_ref(), &[7; HASH_LEN]);
+        assert!(Hash::try_from(digest::digest(&digest::SHA256, b"block")).is_ok());
+
+        assert!(matches!(
+            Hash::try_from_slice(&[7; HASH_LEN - 1]),
+            Err(Error::InvalidHashLength(31))
+        ));
+        assert!(matches!(
+            Hash::try_from(digest::digest(&digest::SHA512, b"block")),
+            Err(Error::InvalidHashLength(64))
+        ));
+    }
+
+    #[test]
+    fn test_encrypted_file() {
+        let dir = TempDir::new("encrypted");
+        let path = dir.file("encrypted.chain");
+        let mut blockchain = Blockchain::<u32>::new();
+        for block in [vec![0xdead_beef], vec![0xdead_beef, 7]] {
+            blockchain.add_block(block).unwrap();
+        }
+        blockchain
+            .save_to_encrypted_file(&path, b"at rest secret")
+            .unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(!bytes
+            .windows(4)
+            .any(|window| window == 0xdead_beef_u32.to_le_bytes()));
+        assert!(matches!(
+            Blockchain::<u32>::load_from_encrypted_file(&path, b"wrong secret"),
+            Err(Error::Encryption(symmetric::Error::Decryption))
+        ));
+
+        let loaded = Blockchain::<u32>::load_from_encrypted_file(&path, b"at rest secret").unwrap();
+        loaded.validate_hashes().unwrap();
+        assert_eq!(loaded.tip_hash().unwrap(), blockchain.tip_hash().unwrap());
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            blockchain.iter().collect::<Vec<_>>()
+        );
+
+        std::fs::write(&path, &bytes[..METADATA_LEN - 1]).unwrap();
+        assert!(matches!(
+            Blockchain::<u32>::load_from_encrypted_file(&path, b"at rest secret"),
+            Err(Error::MissingEncryptionMetadata)
+        ));
+    }
+
+    #[test]
+    fn test_compressed_file() {
+        let dir = TempDir::new("compressed");
+        let path = |extension: &str| dir.file(&format!("compressed.{extension}"));
+        let mut blockchain = Blockchain::<u32>::new();
+        for block in [vec![7; 1000], vec![7, 8]] {
+            blockchain.add_block(block).unwrap();
+        }
+        let plain = path("chain");
+        blockchain.save_to_file(&plain).unwrap();
+        let plain_len = std::fs::metadata(&plain).unwrap().len();
+
+        for (extension, compression) in [("gz", Compression::Gzip), ("zst", Compression::Zstd)] {
+            let compressed = path(extension);
+            assert_eq!(Compression::from_extension(&compressed), compression);
+            blockchain.save_to_file(&compressed).unwrap();
+            assert!(std::fs::metadata(&compressed).unwrap().len() < plain_len);
+
+            let loaded = Blockchain::<u32>::load_from_file(&compressed).unwrap();
+            loaded.validate_hashes().unwrap();
+            assert_eq!(loaded.tip_hash().unwrap(), blockchain.tip_hash().unwrap());
+            assert_eq!(
+                loaded.iter().collect::<Vec<_>>(),
+                blockchain.iter().collect::<Vec<_>>()
+            );
+            assert!(matches!(
+                Blockchain::<u32>::repair(&compressed),
+                Err(Error::CompressedRepair)
+            ));
+        }
+
+        // The compression is detected from the contents, not from the extension.
+        blockchain
+            .save_to_compressed_file(&plain, Compression::Zstd)
+            .unwrap();
+        let loaded = Blockchain::<u32>::load_from_file(&plain).unwrap();
+        assert_eq!(loaded.tip_hash().unwrap(), blockchain.tip_hash().unwrap());
+    }
+
+    #[test]
+    fn test_block_json() {
+        let authority = crypto::signature::blind_sign::BlindSigner::new().unwrap();
+        let votes = (0..2)
+            .map(|candidate| {
+                make_vote_at(&authority, chrono::Utc::now(), CandidateId::new(candidate))
+            })
+            .collect();
+        let block = Block::<Vote>::new(votes, Hash([7; HASH_LEN]));
+
+        let json = block.to_json().unwrap();
+        let decoded = Block::<Vote>::from_json(&json).unwrap();
+        assert_eq!(
+            bincode::serialize(&decoded).unwrap(),
+            bincode::serialize(&block).unwrap()
+        );
+        assert_eq!(decoded.get_hash().unwrap(), block.get_hash().unwrap());
+
+        assert!(matches!(
+            Block::<Vote>::from_json(&json[1..]),
+            Err(Error::JsonSerialization(_))
+        ));
+    }
+}
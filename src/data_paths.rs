@@ -0,0 +1,220 @@
+//! Layout of the files which the node and the mock authority keep on disk.
+//! All of the paths are derived from a single data directory, which is created and checked for
+//! writability at startup, so that a misconfigured directory fails early with a clear error.
+
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Failed to create data directory {}: {}", .0.display(), .1)]
+    CreateDir(PathBuf, std::io::Error),
+    #[error("Data directory {} is not writable: {}", .0.display(), .1)]
+    NotWritable(PathBuf, std::io::Error),
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// Name of the file used to check that a directory is writable.
+const WRITE_PROBE: &str = ".write-probe";
+
+/// Permissions of the files containing secrets, see `create_secret_file`.
+/// Only the owner may read or write them.
+#[cfg(unix)]
+pub const SECRET_FILE_MODE: u32 = 0o600;
+
+/// Paths of the files in the data directory.
+#[derive(Debug, Clone)]
+pub struct DataPaths {
+    /// The data directory itself.
+    root: PathBuf,
+}
+
+impl DataPaths {
+    /// Create the directory tree under the data directory and check that it is writable.
+    ///
+    /// # Arguments
+    ///
+    /// - `root` - The data directory.
+    ///
+    /// # Errors
+    ///
+    /// If any of the directories could not be created or written to.
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let data_paths = Self { root: root.into() };
+        for dir in [
+            data_paths.root.clone(),
+            data_paths.logs(),
+            data_paths.chains(),
+        ] {
+            std::fs::create_dir_all(&dir).map_err(|e| Error::CreateDir(dir.clone(), e))?;
+            check_writable(&dir)?;
+        }
+
+        Ok(data_paths)
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The directory containing the log files.
+    #[must_use]
+    pub fn logs(&self) -> PathBuf {
+        self.root.join("logs")
+    }
+
+    /// The path of a log file.
+    #[must_use]
+    pub fn log_file(&self, name: &str) -> PathBuf {
+        self.logs().join(name)
+    }
+
+    /// The directory containing the blockchains of the elections.
+    #[must_use]
+    pub fn chains(&self) -> PathBuf {
+        self.root.join("chains")
+    }
+
+    /// The block log in which the blockchain of an election is kept, see `BlockLog`.
+    /// Election names are free form, so any characters which are not ASCII letters, digits, `-`
+    /// or `_` are percent encoded, so that the name can't point outside of the directory and
+    /// two elections never share a file.
+    #[must_use]
+    pub fn block_log(&self, election: &str) -> PathBuf {
+        let mut name = String::with_capacity(election.len());
+        for byte in election.bytes() {
+            if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+                name.push(char::from(byte));
+            } else {
+                name.push_str(&format!("%{byte:02X}"));
+            }
+        }
+        self.chains().join(format!("{name}.blocks"))
+    }
+
+    /// The file in which the history of the commands entered into the node is kept.
+    #[must_use]
+    pub fn cmd_history(&self) -> PathBuf {
+        self.root.join(process_io::cli::HISTORY_FILE)
+    }
+
+    /// The file containing the keys of the mock election authority.
+    #[must_use]
+    pub fn authority_keys(&self) -> PathBuf {
+        self.root.join("authority-blind-signer-cfg")
+    }
+
+    /// The file containing the key pair with which the node signs the results it announces.
+    #[must_use]
+    pub fn node_keys(&self) -> PathBuf {
+        self.root.join("node-signer-keys")
+    }
+
+    /// The file containing the secret with which the node commits the receipts of the votes.
+    #[must_use]
+    pub fn receipt_secret(&self) -> PathBuf {
+        self.root.join("receipt-secret")
+    }
+
+    /// The file to which the node appends its audit log.
+    #[must_use]
+    pub fn audit_log(&self) -> PathBuf {
+        self.root.join("audit.log")
+    }
+}
+
+/// Create or truncate a file for storing secrets, so that only the owner can access it.
+/// On non-Unix systems this is the same as creating a regular file, since file permissions
+/// can't be restricted portably there.
+///
+/// # Errors
+///
+/// If the file could not be created or its permissions could not be set.
+pub fn create_secret_file(path: &Path) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // Setting the mode on creation, so that the secret is never readable by others,
+    // not even for a moment.
+    #[cfg(unix)]
+    options.mode(SECRET_FILE_MODE);
+    let file = options.open(path)?;
+    // The mode is only applied to newly created files, so fixing up pre-existing ones too.
+    #[cfg(unix)]
+    file.set_permissions(std::fs::Permissions::from_mode(SECRET_FILE_MODE))?;
+
+    Ok(file)
+}
+
+/// Check that files can be created in the directory by creating and removing a probe file.
+fn check_writable(dir: &Path) -> Result<()> {
+    let probe = dir.join(WRITE_PROBE);
+    std::fs::write(&probe, []).map_err(|e| Error::NotWritable(dir.to_owned(), e))?;
+    std::fs::remove_file(&probe).map_err(|e| Error::NotWritable(dir.to_owned(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use process_io::test_utils::TempDir;
+
+    #[test]
+    fn test_data_paths() {
+        let dir = TempDir::new("data-paths");
+        let root = dir.join("data");
+        let data_paths = DataPaths::new(&root).unwrap();
+        assert!(data_paths.logs().is_dir());
+        assert_eq!(data_paths.log_file("node.log"), root.join("logs/node.log"));
+        assert_eq!(data_paths.audit_log(), root.join("audit.log"));
+        assert_eq!(data_paths.node_keys(), root.join("node-signer-keys"));
+        assert_eq!(data_paths.receipt_secret(), root.join("receipt-secret"));
+        assert_eq!(data_paths.cmd_history(), root.join("node-cmd-history.txt"));
+        assert!(data_paths.chains().is_dir());
+        assert_eq!(
+            data_paths.block_log("Test election"),
+            root.join("chains/Test%20election.blocks")
+        );
+        assert_eq!(
+            data_paths.block_log("../audit"),
+            root.join("chains/%2E%2E%2Faudit.blocks")
+        );
+        assert!(!data_paths.logs().join(WRITE_PROBE).exists());
+
+        // A file in place of the data directory.
+        let root = dir.join("data-file");
+        std::fs::write(&root, []).unwrap();
+        let err = DataPaths::new(&root).unwrap_err();
+        assert!(matches!(err, Error::CreateDir(..)));
+        assert!(err.to_string().contains(&root.display().to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_only_data_dir() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let dir = TempDir::new("data-read-only");
+        let root = dir.join("data");
+        std::fs::create_dir_all(root.join("logs")).unwrap();
+        // Permissions don't apply to root, so there's nothing to test when running as root.
+        // The directory is owned by the user running the test.
+        if std::fs::metadata(&root).unwrap().uid() == 0 {
+            return;
+        }
+        std::fs::set_permissions(&root, std::fs::Permissions::from_mode(0o555)).unwrap();
+
+        let err = DataPaths::new(&root).unwrap_err();
+        assert!(matches!(err, Error::NotWritable(..)));
+        assert_eq!(
+            err.to_string().split(':').next().unwrap(),
+            format!("Data directory {} is not writable", root.display())
+        );
+
+        // Restoring the permissions, so that the directory can be removed.
+        std::fs::set_permissions(&root, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
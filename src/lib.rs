@@ -1,18 +1,40 @@
 // TODO
 #![allow(clippy::missing_errors_doc)]
 
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt::Display,
+};
 
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
 use thiserror::Error;
 
 pub mod api;
+pub mod audit;
 
 pub mod batcher;
+pub mod data_paths;
+pub use protocol::inclusion;
 pub mod logging;
+pub mod self_test;
 
 mod blockchain;
+pub use blockchain::{
+    decode_block_stream, decode_json_blocks, Block, BlockLog, BlockStream, Compression, Hash,
+    RepairReport,
+};
 use blockchain::{BlockValue, Blockchain, Error as BlockchainError};
-use protocol::{candidate_id::CandidateId, vote::Vote};
+use crypto::signature::{blind_sign, digital_sign};
+use protocol::{
+    candidate_id::CandidateId,
+    choice::{Choice, WriteIn},
+    config::{ElectionConfig, ElectionContext, Error as ConfigError, VoteRejection},
+    timestamp::Timestamp,
+    vote::{ApiChoice, Error as VoteError, Nonce, Vote},
+};
+
+use inclusion::{Error as InclusionError, InclusionProof};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -20,28 +42,206 @@ pub enum Error {
     VotesTampered,
     #[error(transparent)]
     BlockchainError(#[from] BlockchainError),
+    #[error("Invalid election config: {}", .0)]
+    InvalidConfig(#[from] ConfigError),
+    #[error("Invalid authority public key: {}", .0)]
+    InvalidAuthorityKey(#[from] blind_sign::Error),
+    #[error("Failed to prove vote inclusion: {}", .0)]
+    Inclusion(#[from] InclusionError),
+    #[error("Invalid tally signature: {}", .0)]
+    InvalidTallySignature(#[from] digital_sign::Error),
+    #[error("Failed to read election config: {}", .0)]
+    ConfigFile(std::io::Error),
+    #[error("Failed to parse election config: {}", .0)]
+    ConfigParsing(serde_json::Error),
+    #[error("Blockchain failed the audit: {}", .0)]
+    AuditFailed(AuditFinding),
     #[error("Unknown error")]
     Unknown,
 }
 
-impl BlockValue for Vote {}
+/// The default maximum amount of votes in a single block.
+pub const DEFAULT_MAX_VOTES_PER_BLOCK: usize = 1000;
+
+impl BlockValue for Vote {
+    fn index_key(&self) -> Option<&[u8]> {
+        Some(self.get_public_key())
+    }
+}
 
 #[derive(Debug)]
 pub struct VotingSystem {
     blockchain: Blockchain<Vote>,
+    /// Maximum amount of votes in a single block.
+    max_votes_per_block: usize,
+    /// Results of the votes in the blockchain, kept up to date as blocks are added and removed,
+    /// so that they don't have to be counted from the whole blockchain on every request.
+    tally: Tally,
 }
 
 impl VotingSystem {
     #[must_use]
     pub fn new() -> Self {
+        Self::with_max_votes_per_block(DEFAULT_MAX_VOTES_PER_BLOCK)
+    }
+
+    /// Create an empty voting system which splits large batches of votes into multiple blocks.
+    ///
+    /// # Arguments
+    ///
+    /// - `max_votes_per_block` - Maximum amount of votes in a single block. Zero is treated as one.
+    #[must_use]
+    pub fn with_max_votes_per_block(max_votes_per_block: usize) -> Self {
         Self {
             blockchain: Blockchain::new(),
+            max_votes_per_block: max_votes_per_block.max(1),
+            tally: Tally::default(),
+        }
+    }
+
+    /// Wrap a loaded blockchain, counting the votes in it.
+    fn from_blockchain(blockchain: Blockchain<Vote>) -> Result<Self, Error> {
+        let mut voting_system = Self {
+            blockchain,
+            max_votes_per_block: DEFAULT_MAX_VOTES_PER_BLOCK,
+            tally: Tally::default(),
+        };
+        voting_system.tally = voting_system.tally_votes()?;
+        Ok(voting_system)
+    }
+
+    /// Change the maximum amount of votes in a single block, for example after loading the
+    /// blockchain from a file. Zero is treated as one.
+    pub fn set_max_votes_per_block(&mut self, max_votes_per_block: usize) {
+        self.max_votes_per_block = max_votes_per_block.max(1);
+    }
+
+    /// Add a batch of votes to the blockchain.
+    /// Batches larger than the maximum block size are split into multiple sequential blocks
+    /// and empty batches don't produce any blocks at all.
+    /// The votes of a block are only counted once the block is appended, so that the tally
+    /// always matches the blockchain, even if appending one of the blocks fails.
+    pub fn add_votes(&mut self, mut votes: Vec<Vote>) -> Result<(), Error> {
+        while !votes.is_empty() {
+            let rest = votes.split_off(votes.len().min(self.max_votes_per_block));
+            let choices: Vec<Choice> = votes.iter().map(|vote| vote.get_choice().clone()).collect();
+            self.blockchain.add_block(votes)?;
+            for choice in &choices {
+                self.tally.add(choice);
+            }
+            self.tally.chain_tip = self.blockchain.tip_hash()?;
+            votes = rest;
+        }
+        Ok(())
+    }
+
+    /// Remove the last block together with its votes, for example on a reorganization.
+    ///
+    /// # Returns
+    ///
+    /// The votes of the removed block, or `None` if the blockchain is empty.
+    pub fn pop_block(&mut self) -> Result<Option<Vec<Vote>>, Error> {
+        let Some(block) = self.blockchain.pop_block() else {
+            return Ok(None);
+        };
+        for vote in block.values() {
+            self.tally.remove(vote.get_choice());
         }
+        self.tally.chain_tip = self.blockchain.tip_hash()?;
+        Ok(Some(block.values().to_vec()))
+    }
+
+    /// Get the results of the votes in the blockchain without counting them again.
+    /// Same as `tally_votes`, as long as the blockchain had only been changed through this type.
+    #[must_use]
+    pub fn tally(&self) -> &Tally {
+        &self.tally
+    }
+
+    /// Find the block containing the first vote of the voter, without scanning the blockchain.
+    ///
+    /// # Returns
+    ///
+    /// The height of the block, or `None` if the voter has not voted.
+    #[must_use]
+    pub fn find_voter(&self, public_key: &digital_sign::PublicKey) -> Option<usize> {
+        self.blockchain.find(public_key)
+    }
+
+    /// Iterate over the votes across all blocks, in the order in which they were added.
+    pub fn votes(&self) -> impl Iterator<Item = &Vote> {
+        self.blockchain.iter().flatten()
+    }
+
+    /// Get a page of the votes across all blocks, in the order in which they were added.
+    ///
+    /// # Arguments
+    ///
+    /// - `from` - Index of the first matching vote to return.
+    /// - `limit` - Maximum amount of votes to return.
+    /// - `candidate` - If present, only votes for this candidate are returned.
+    ///
+    /// # Returns
+    ///
+    /// The requested votes, which may be fewer than `limit` or none at all.
+    #[must_use]
+    pub fn votes_page(
+        &self,
+        from: usize,
+        limit: usize,
+        candidate: Option<&CandidateId>,
+    ) -> Vec<Vote> {
+        self.votes()
+            .filter(|vote| match candidate {
+                Some(candidate) => {
+                    matches!(vote.get_choice(), Choice::Candidate(voted) if voted == candidate)
+                }
+                None => true,
+            })
+            .skip(from)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Encode the blocks from the height onwards for streaming them to a peer.
+    /// The stream can be decoded with `decode_block_stream`.
+    pub fn encode_blocks_from(&self, height: usize) -> Result<Vec<u8>, Error> {
+        Ok(self.blockchain.encode_blocks_from(height)?)
     }
 
-    pub fn add_votes(mut self, votes: Vec<Vote>) -> Result<Self, Error> {
-        self.blockchain.add_block(votes)?;
-        Ok(self)
+    /// Encode the blocks from the height onwards as a JSON array for the peers and tools which
+    /// can't decode the binary stream. The array can be decoded with `decode_json_blocks`.
+    pub fn encode_json_blocks_from(&self, height: usize) -> Result<Vec<u8>, Error> {
+        Ok(self.blockchain.encode_json_blocks_from(height)?)
+    }
+
+    /// Get the votes in the block at the height, if there is such a block.
+    #[must_use]
+    pub fn block_votes(&self, height: usize) -> Option<&[Vote]> {
+        self.blockchain.iter().nth(height).map(Vec::as_slice)
+    }
+
+    /// Prove that the vote with the id is included in the blockchain.
+    ///
+    /// # Returns
+    ///
+    /// The proof of inclusion in the block containing the vote, or `None` if there is no such
+    /// vote.
+    pub fn inclusion_proof(&self, vote_id: &Nonce) -> Result<Option<InclusionProof>, Error> {
+        for (height, votes) in self.blockchain.iter().enumerate() {
+            if let Some(index) = votes.iter().position(|vote| vote.id() == *vote_id) {
+                return Ok(Some(InclusionProof::new(height, votes, index)?));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Get the amount of blocks in the blockchain.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.blockchain.len()
     }
 
     pub fn validate(&self) -> Result<(), Error> {
@@ -52,27 +252,278 @@ impl VotingSystem {
         }
     }
 
-    pub fn tally_votes(&self) -> Result<Tally, Error> {
-        let mut tally = HashMap::new();
+    /// Verify the whole blockchain, for example after it had been exported to a file.
+    ///
+    /// # Arguments
+    ///
+    /// - `config` - If present, every vote is also verified against the rules of this election.
+    ///
+    /// # Returns
+    ///
+    /// A report containing the height of the blockchain and the first invalid block, if any.
+    pub fn verify(&self, config: Option<&ElectionConfig>) -> Result<VerificationReport, Error> {
+        let mut first_invalid_block = match self.blockchain.validate_hashes() {
+            Ok(()) => None,
+            Err(BlockchainError::BlockchainHashIntegrity(index, ..)) => Some((
+                index,
+                "previous block hash does not match the previous block".to_owned(),
+            )),
+            Err(e) => return Err(e.into()),
+        };
 
-        self.blockchain.iter().for_each(|values| {
-            for vote in values {
-                let count = tally.entry(vote.get_candidate().clone()).or_insert(0);
-                *count += 1;
+        if let Some(config) = config {
+            let context = ElectionContext::new(config.clone())?;
+            // Only the blocks before the broken hash can contain an earlier invalid block.
+            let blocks_to_check = first_invalid_block
+                .as_ref()
+                .map_or(usize::MAX, |(index, _)| *index);
+            for (index, votes) in self.blockchain.iter().enumerate().take(blocks_to_check) {
+                let vote_error = votes.iter().find_map(|vote| {
+                    context
+                        .verify_cast_vote(vote)
+                        .err()
+                        .map(|e| format!("{vote}: {e}"))
+                });
+                if let Some(vote_error) = vote_error {
+                    first_invalid_block = Some((index, vote_error));
+                    break;
+                }
             }
+        }
+
+        Ok(VerificationReport {
+            height: self.height(),
+            first_invalid_block,
+        })
+    }
+
+    /// Run every check on the blockchain against the config of its election, without stopping at
+    /// the first problem, so that auditors get the complete picture in a single report.
+    ///
+    /// # Arguments
+    ///
+    /// - `config` - The config of the election to which the blockchain belongs.
+    ///
+    /// # Returns
+    ///
+    /// A report of all the broken hash links, invalid votes, votes cast outside of the election
+    /// period and double votes.
+    ///
+    /// # Errors
+    ///
+    /// If the config is invalid or if a block could not be hashed.
+    pub fn full_audit(&self, config: &ElectionConfig) -> Result<AuditReport, Error> {
+        let context = ElectionContext::new(config.clone())?;
+        let mut findings: Vec<AuditFinding> = self
+            .blockchain
+            .broken_links()?
+            .into_iter()
+            .map(|block| AuditFinding::BrokenLink { block })
+            .collect();
+        // Blocks in which the access tokens were first used, to find the double votes.
+        let mut access_tokens = HashMap::new();
+        let mut votes = 0;
+
+        for (block, block_votes) in self.blockchain.iter().enumerate() {
+            for vote in block_votes {
+                votes += 1;
+                let finding = match context.verify_cast_vote(vote) {
+                    Ok(()) => None,
+                    Err(VoteRejection::Vote(VoteError::InvalidTimestmap(_))) => {
+                        Some(AuditFinding::OutsideElectionPeriod {
+                            block,
+                            vote: vote.to_string(),
+                        })
+                    }
+                    Err(e) => Some(AuditFinding::InvalidVote {
+                        block,
+                        vote: vote.to_string(),
+                        reason: e.to_string(),
+                    }),
+                };
+                findings.extend(finding);
+                match access_tokens.entry(vote.get_access_token().to_vec()) {
+                    Entry::Occupied(first_block) => findings.push(AuditFinding::DoubleVote {
+                        block,
+                        vote: vote.to_string(),
+                        first_block: *first_block.get(),
+                    }),
+                    Entry::Vacant(entry) => {
+                        entry.insert(block);
+                    }
+                }
+            }
+        }
+        findings.sort_by_key(AuditFinding::block);
+
+        Ok(AuditReport {
+            height: self.height(),
+            votes,
+            findings,
+        })
+    }
+
+    pub fn tally_votes(&self) -> Result<Tally, Error> {
+        let mut tally = Tally {
+            chain_tip: self.blockchain.tip_hash()?,
+            ..Tally::default()
+        };
+
+        for vote in self.votes() {
+            tally.add(vote.get_choice());
+        }
+        Ok(tally)
+    }
+
+    /// Summarize the turnout and the results of the election.
+    #[must_use]
+    pub fn summary(&self) -> ElectionSummary {
+        let mut counts: HashMap<&Choice, u64> = HashMap::new();
+        let mut voters = HashSet::new();
+        let mut summary = ElectionSummary::default();
+        for vote in self.votes() {
+            *counts.entry(vote.get_choice()).or_insert(0) += 1;
+            voters.insert(vote.get_public_key().as_slice());
+            summary.total_votes += 1;
+            let timestamp = vote.get_timestamp();
+            summary.first_vote = Some(summary.first_vote.map_or(timestamp, |t| t.min(timestamp)));
+            summary.last_vote = Some(summary.last_vote.map_or(timestamp, |t| t.max(timestamp)));
+        }
+        summary.unique_voters = voters.len() as u64;
+
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        // Most voted first, ties broken by the choice, so that the order is stable.
+        counts.sort_by(|(a_choice, a_votes), (b_choice, b_votes)| {
+            b_votes.cmp(a_votes).then_with(|| a_choice.cmp(b_choice))
         });
-        Ok(Tally(tally))
+        summary.results = counts
+            .into_iter()
+            .map(|(choice, votes)| ChoiceResult {
+                choice: ApiChoice::from(choice),
+                votes,
+                // Vote counts are far too small to lose precision as floats.
+                #[allow(clippy::cast_precision_loss)]
+                percentage: votes as f64 * 100.0 / summary.total_votes as f64,
+            })
+            .collect();
+
+        summary
     }
 
+    /// Save the votes into a file, which is compressed if it has a `.gz` or a `.zst` extension.
     pub fn save_to_file(&self, filename: &str) -> Result<(), Error> {
         self.blockchain.save_to_file(filename)?;
         Ok(())
     }
 
+    /// Save the votes into a file compressed with the compression, regardless of its extension.
+    /// The compression is detected when the file is loaded with `load_from_file`.
+    pub fn save_to_compressed_file(
+        &self,
+        filename: &str,
+        compression: Compression,
+    ) -> Result<(), Error> {
+        self.blockchain
+            .save_to_compressed_file(filename, compression)?;
+        Ok(())
+    }
+
     pub fn load_from_file(filename: &str) -> Result<Self, Error> {
-        let blockchain = Blockchain::load_from_file(filename)?;
-        Ok(Self { blockchain })
+        Self::from_blockchain(Blockchain::load_from_file(filename)?)
+    }
+
+    /// Recover the valid prefix of a partially corrupted blockchain file.
+    /// See `Blockchain::repair` for the details. Files encrypted at rest, which is the case if
+    /// the secret is provided, are refused, since they can't be repaired.
+    pub fn repair_file(filename: &str, secret: Option<&[u8]>) -> Result<RepairReport, Error> {
+        if secret.is_some() {
+            return Err(BlockchainError::EncryptedRepair.into());
+        }
+        Ok(Blockchain::<Vote>::repair(filename)?)
+    }
+
+    /// Save the votes into a file encrypted with a key derived from the secret, so that the
+    /// public keys of the voters are not readable from the disk before the tally.
+    pub fn save_to_encrypted_file(&self, filename: &str, secret: &[u8]) -> Result<(), Error> {
+        self.blockchain.save_to_encrypted_file(filename, secret)?;
+        Ok(())
+    }
+
+    pub fn load_from_encrypted_file(filename: &str, secret: &[u8]) -> Result<Self, Error> {
+        Self::from_blockchain(Blockchain::load_from_encrypted_file(filename, secret)?)
+    }
+
+    /// Save the votes into a file, which is encrypted with the secret if one is provided.
+    pub fn save(&self, filename: &str, secret: Option<&[u8]>) -> Result<(), Error> {
+        match secret {
+            Some(secret) => self.save_to_encrypted_file(filename, secret),
+            None => self.save_to_file(filename),
+        }
+    }
+
+    /// Load the votes from a file, which is decrypted with the secret if one is provided.
+    pub fn load(filename: &str, secret: Option<&[u8]>) -> Result<Self, Error> {
+        match secret {
+            Some(secret) => Self::load_from_encrypted_file(filename, secret),
+            None => Self::load_from_file(filename),
+        }
+    }
+
+    /// Open the block log in the file and load the votes of the blocks in it, see
+    /// `BlockLog::open`.
+    pub fn open_block_log(
+        path: &std::path::Path,
+        secret: Option<&[u8]>,
+    ) -> Result<(Self, BlockLog), Error> {
+        let (blockchain, block_log) = BlockLog::open(path, secret)?;
+        Ok((Self::from_blockchain(blockchain)?, block_log))
+    }
+
+    /// Write the blocks from the height onwards to the block log, for example the blocks
+    /// appended by `add_votes`.
+    pub fn append_to_log(&self, block_log: &mut BlockLog, height: usize) -> Result<(), Error> {
+        block_log.append(self.blockchain.blocks_from(height))?;
+        Ok(())
+    }
+}
+
+/// Count the votes of an exported blockchain file, without having to run a node, so that anyone
+/// with the file and the config of the election can count the votes independently.
+///
+/// # Arguments
+///
+/// - `chain_path` - Path to the exported blockchain file.
+/// - `config_path` - Path to the JSON file containing the config of the election.
+/// - `at_rest_secret` - The secret with which the file is encrypted at rest, if it is.
+///
+/// # Returns
+///
+/// The results of the election.
+///
+/// # Errors
+///
+/// If either of the files could not be loaded or if the blockchain fails the full audit, for
+/// example because of a double vote, in which case the votes are not counted at all.
+pub fn tally_from_file(
+    chain_path: &str,
+    config_path: &std::path::Path,
+    at_rest_secret: Option<&[u8]>,
+) -> Result<Tally, Error> {
+    let config_file = std::fs::File::open(config_path).map_err(Error::ConfigFile)?;
+    let config: ElectionConfig =
+        serde_json::from_reader(config_file).map_err(Error::ConfigParsing)?;
+    config.validate()?;
+    let voting_system = VotingSystem::load(chain_path, at_rest_secret)?;
+    if let Some(finding) = voting_system
+        .full_audit(&config)?
+        .findings
+        .into_iter()
+        .next()
+    {
+        return Err(Error::AuditFailed(finding));
     }
+
+    voting_system.tally_votes()
 }
 
 impl Default for VotingSystem {
@@ -87,14 +538,961 @@ impl Display for VotingSystem {
     }
 }
 
-#[derive(PartialEq, Debug)]
-pub struct Tally(HashMap<CandidateId, u64>);
+#[serde_as]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default, Clone)]
+pub struct Tally {
+    /// Votes for the candidates from the election config.
+    /// Candidate ids are serialized as strings, so that they can be used as JSON object keys.
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    pub candidates: HashMap<CandidateId, u64>,
+    /// Votes for write-in candidates, kept apart from the configured candidates.
+    pub write_ins: HashMap<WriteIn, u64>,
+    /// Deliberate abstentions, which count towards the turnout, but not towards any candidate.
+    #[serde(default)]
+    pub abstentions: u64,
+    /// Hash of the last block of the blockchain from which the votes were counted.
+    pub chain_tip: Hash,
+}
+
+impl Tally {
+    /// Count a vote for the choice.
+    fn add(&mut self, choice: &Choice) {
+        let count = match choice {
+            Choice::Candidate(candidate) => self.candidates.entry(candidate.clone()).or_insert(0),
+            Choice::WriteIn(write_in) => self.write_ins.entry(write_in.clone()).or_insert(0),
+            Choice::Abstain => &mut self.abstentions,
+        };
+        *count += 1;
+    }
+
+    /// Uncount a vote for the choice, dropping choices which are left without votes, so that
+    /// the results are the same as if the vote had never been counted.
+    fn remove(&mut self, choice: &Choice) {
+        match choice {
+            Choice::Candidate(candidate) => decrement(&mut self.candidates, candidate),
+            Choice::WriteIn(write_in) => decrement(&mut self.write_ins, write_in),
+            Choice::Abstain => self.abstentions = self.abstentions.saturating_sub(1),
+        }
+    }
+
+    /// Add the votes counted in another tally, like the tally of another regional chain of the
+    /// election. Choices counted in either tally are counted in the merged one.
+    /// The merged results no longer correspond to a single chain, so the chain tip is cleared.
+    ///
+    /// # Arguments
+    ///
+    /// - `other` - The tally whose votes are added to this one.
+    pub fn merge(&mut self, other: &Tally) {
+        for (candidate, count) in &other.candidates {
+            let total = self.candidates.entry(candidate.clone()).or_insert(0);
+            *total = total.saturating_add(*count);
+        }
+        for (write_in, count) in &other.write_ins {
+            let total = self.write_ins.entry(write_in.clone()).or_insert(0);
+            *total = total.saturating_add(*count);
+        }
+        self.abstentions = self.abstentions.saturating_add(other.abstentions);
+        self.chain_tip = Hash::default();
+    }
+
+    /// Merge all the tallies into one, see `Tally::merge`.
+    ///
+    /// # Arguments
+    ///
+    /// - `tallies` - The tallies to merge.
+    ///
+    /// # Returns
+    ///
+    /// The combined results, which are empty if there are no tallies.
+    pub fn sum<'a>(tallies: impl IntoIterator<Item = &'a Tally>) -> Self {
+        tallies.into_iter().fold(Self::default(), |mut sum, tally| {
+            sum.merge(tally);
+            sum
+        })
+    }
+
+    /// Sign the results, so that observers can check that they were announced by the node and
+    /// that they correspond to a specific state of the blockchain.
+    ///
+    /// # Arguments
+    ///
+    /// - `signer` - The signer holding the key pair of the node.
+    ///
+    /// # Returns
+    ///
+    /// The tally together with the signature and the public key of the node.
+    #[must_use]
+    pub fn sign(&self, signer: &digital_sign::Signer) -> SignedTally {
+        SignedTally {
+            signature: signer.sign(&self.signed_bytes()),
+            public_key: signer.get_public_key(),
+            tally: self.clone(),
+        }
+    }
+
+    /// Get the counts of all the choices sorted by the choice, since the order of the hash maps
+    /// is not stable. Abstentions are only included if there are any, so that the signed bytes
+    /// of tallies without abstentions are not affected by them.
+    fn counts(&self) -> Vec<(Choice, u64)> {
+        let mut counts: Vec<(Choice, u64)> = self
+            .candidates
+            .iter()
+            .map(|(candidate, count)| (Choice::from(candidate.clone()), *count))
+            .chain(
+                self.write_ins
+                    .iter()
+                    .map(|(write_in, count)| (Choice::from(write_in.clone()), *count)),
+            )
+            .chain((self.abstentions > 0).then_some((Choice::Abstain, self.abstentions)))
+            .collect();
+        // Candidates sort before the write-ins and the abstentions go last.
+        counts.sort();
+        counts
+    }
+
+    /// Encode the chain tip followed by the results sorted by the choice, so that the signed
+    /// bytes are reproducible by the observers. Every choice is encoded unambiguously, see
+    /// `Choice::to_bytes`.
+    fn signed_bytes(&self) -> Vec<u8> {
+        let results = self.counts();
+
+        let mut bytes = self.chain_tip.as_ref().to_vec();
+        for (choice, count) in results {
+            bytes.append(&mut choice.to_bytes());
+            bytes.extend_from_slice(&count.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// Decrement the count of the key, removing the key once the count reaches zero.
+fn decrement<K: std::hash::Hash + Eq>(counts: &mut HashMap<K, u64>, key: &K) {
+    if let Some(count) = counts.get_mut(key) {
+        *count = count.saturating_sub(1);
+        if *count == 0 {
+            counts.remove(key);
+        }
+    }
+}
+
+/// Results of an election, signed by the node which counted them.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct SignedTally {
+    /// The signed results.
+    pub tally: Tally,
+    /// Public key of the node, included for convenience.
+    /// Observers should verify against the key which the node had published instead.
+    pub public_key: digital_sign::PublicKey,
+    /// Signature of the node over the results and the chain tip.
+    pub signature: digital_sign::Signature,
+}
+
+impl SignedTally {
+    /// Verify that the results were signed by the node.
+    ///
+    /// # Arguments
+    ///
+    /// - `public_key` - The published public key of the node.
+    ///
+    /// # Errors
+    ///
+    /// If the signature does not match the results, the chain tip or the public key.
+    pub fn verify(&self, public_key: &digital_sign::PublicKey) -> Result<(), Error> {
+        digital_sign::verify(&self.tally.signed_bytes(), &self.signature, public_key)?;
+        Ok(())
+    }
+}
 
 impl Display for Tally {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        for (candidate_id, count) in &self.0 {
-            writeln!(f, "Candidate {candidate_id} has {count} votes")?;
+        self.display_ordered_by(TallyOrder::ByCandidateId).fmt(f)
+    }
+}
+
+/// Order in which the results of a tally are displayed.
+#[derive(Debug, Clone, Copy)]
+pub enum TallyOrder<'a> {
+    /// Most voted first, ties broken by the candidate ids and then by the write-ins.
+    ByVotesDesc,
+    /// Candidates by their ids, followed by the write-ins in alphabetical order.
+    ByCandidateId,
+    /// Candidates in the order of the config, including those without any votes, followed by
+    /// the write-ins in alphabetical order. This is the official order of the election.
+    AsConfigured(&'a ElectionConfig),
+}
+
+impl Tally {
+    /// Display the results in a deterministic order, unlike iterating over the counts.
+    #[must_use]
+    pub fn display_ordered_by<'a>(&'a self, order: TallyOrder<'a>) -> OrderedTally<'a> {
+        OrderedTally { tally: self, order }
+    }
+
+    /// Get the counts of all the choices in the order.
+    fn ordered_counts(&self, order: TallyOrder) -> Vec<(Choice, u64)> {
+        let mut counts = self.counts();
+        match order {
+            TallyOrder::ByVotesDesc => counts.sort_by(|(_, a), (_, b)| b.cmp(a)),
+            TallyOrder::ByCandidateId => {}
+            TallyOrder::AsConfigured(config) => {
+                let position = |choice: &Choice| match choice {
+                    Choice::Candidate(id) => config
+                        .candidates
+                        .iter()
+                        .position(|candidate| candidate.id == *id),
+                    Choice::WriteIn(_) | Choice::Abstain => None,
+                };
+                for candidate in &config.candidates {
+                    if !self.candidates.contains_key(&candidate.id) {
+                        counts.push((Choice::Candidate(candidate.id.clone()), 0));
+                    }
+                }
+                // Choices missing from the config go last, in the order of their ids.
+                counts.sort_by_key(|(choice, _)| position(choice).unwrap_or(usize::MAX));
+            }
+        }
+
+        counts
+    }
+}
+
+/// The results of a tally displayed in a specific order, see `Tally::display_ordered_by`.
+pub struct OrderedTally<'a> {
+    tally: &'a Tally,
+    order: TallyOrder<'a>,
+}
+
+impl Display for OrderedTally<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (choice, count) in self.tally.ordered_counts(self.order) {
+            match &choice {
+                Choice::Candidate(candidate_id) => {
+                    write!(f, "Candidate {candidate_id}")?;
+                    if let TallyOrder::AsConfigured(config) = self.order {
+                        if let Ok(candidate) = config.validate_candidate(candidate_id) {
+                            write!(f, " ({})", candidate.name)?;
+                        }
+                    }
+                    writeln!(f, " has {count} votes")?;
+                }
+                Choice::WriteIn(write_in) => {
+                    writeln!(f, "Write-in \"{write_in}\" has {count} votes")?;
+                }
+                Choice::Abstain => writeln!(f, "{count} voters abstained")?,
+            }
         }
         Ok(())
     }
 }
+
+/// Turnout and results of an election, meant for the election officials.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct ElectionSummary {
+    /// Amount of votes in the blockchain.
+    pub total_votes: u64,
+    /// Amount of distinct voter public keys among the votes.
+    pub unique_voters: u64,
+    /// Results of every choice which received votes, most voted first.
+    pub results: Vec<ChoiceResult>,
+    /// Timestamp of the earliest vote.
+    pub first_vote: Option<Timestamp>,
+    /// Timestamp of the latest vote.
+    pub last_vote: Option<Timestamp>,
+}
+
+/// Result of a single choice in the `ElectionSummary`.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ChoiceResult {
+    /// The candidate or write-in.
+    pub choice: ApiChoice,
+    /// Amount of votes for the choice.
+    pub votes: u64,
+    /// Share of all the votes in percent.
+    pub percentage: f64,
+}
+
+/// Summary of the verification of a blockchain.
+#[derive(PartialEq, Debug)]
+pub struct VerificationReport {
+    /// Amount of blocks in the blockchain.
+    pub height: usize,
+    /// Index of the first invalid block and the reason why it is invalid.
+    pub first_invalid_block: Option<(usize, String)>,
+}
+
+impl VerificationReport {
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.first_invalid_block.is_none()
+    }
+}
+
+/// A single problem found by `VotingSystem::full_audit`.
+#[derive(PartialEq, Debug, Clone)]
+pub enum AuditFinding {
+    /// The previous block hash of the block does not match the block before it.
+    BrokenLink { block: usize },
+    /// The choice of the vote is not allowed, or its access token or its signature is invalid.
+    InvalidVote {
+        block: usize,
+        vote: String,
+        reason: String,
+    },
+    /// The vote was cast outside of the election period.
+    OutsideElectionPeriod { block: usize, vote: String },
+    /// The access token of the vote had already been used by another vote.
+    DoubleVote {
+        block: usize,
+        vote: String,
+        first_block: usize,
+    },
+}
+
+impl AuditFinding {
+    /// The index of the block in which the problem was found.
+    #[must_use]
+    pub fn block(&self) -> usize {
+        match self {
+            Self::BrokenLink { block }
+            | Self::InvalidVote { block, .. }
+            | Self::OutsideElectionPeriod { block, .. }
+            | Self::DoubleVote { block, .. } => *block,
+        }
+    }
+}
+
+impl Display for AuditFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::BrokenLink { block } => write!(
+                f,
+                "Block {block}: previous block hash does not match the previous block"
+            ),
+            Self::InvalidVote {
+                block,
+                vote,
+                reason,
+            } => write!(f, "Block {block}: invalid vote {vote}: {reason}"),
+            Self::OutsideElectionPeriod { block, vote } => {
+                write!(f, "Block {block}: vote cast outside of the election period {vote}")
+            }
+            Self::DoubleVote {
+                block,
+                vote,
+                first_block,
+            } => write!(
+                f,
+                "Block {block}: double vote {vote}, the access token was first used in block {first_block}"
+            ),
+        }
+    }
+}
+
+/// Result of the full audit of a blockchain against the config of its election.
+#[derive(PartialEq, Debug)]
+pub struct AuditReport {
+    /// Amount of blocks in the blockchain.
+    pub height: usize,
+    /// Amount of votes in the blockchain.
+    pub votes: usize,
+    /// All the problems found, ordered by the block in which they were found.
+    pub findings: Vec<AuditFinding>,
+}
+
+impl AuditReport {
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+impl Display for AuditReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Height: {}", self.height)?;
+        writeln!(f, "Votes: {}", self.votes)?;
+        if self.is_clean() {
+            return writeln!(f, "No problems found");
+        }
+        writeln!(f, "Problems found: {}", self.findings.len())?;
+        for finding in &self.findings {
+            writeln!(f, "{finding}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for VerificationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Height: {}", self.height)?;
+        match &self.first_invalid_block {
+            None => writeln!(f, "Blockchain is valid"),
+            Some((index, reason)) => {
+                writeln!(f, "Blockchain is invalid")?;
+                writeln!(f, "First invalid block: {index} ({reason})")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use process_io::test_utils::TempDir;
+    use protocol::{
+        config::Candidate,
+        test_utils::{make_access_token, make_config, make_expired_vote, make_vote_at},
+    };
+
+    #[test]
+    fn test_verify_file() {
+        let dir = TempDir::new("verify-file");
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let mut config = make_config(authority.get_public_key().unwrap());
+        config.allow_write_ins = true;
+        let now = chrono::Utc::now();
+        let mut voting_system = VotingSystem::new();
+        for name in ["Jane Doe", "John Doe", "Jack Doe"] {
+            let vote = make_vote_at(&authority, now, WriteIn::new(name).unwrap());
+            voting_system.add_votes(vec![vote]).unwrap();
+        }
+
+        let valid_file = dir.file("valid.chain");
+        voting_system.save_to_file(&valid_file).unwrap();
+        let report = VotingSystem::load_from_file(&valid_file)
+            .unwrap()
+            .verify(Some(&config))
+            .unwrap();
+        assert_eq!(report.height, 3);
+        assert!(report.is_valid());
+
+        // Rewrite the write-in of the first vote, keeping the file deserializable.
+        let mut bytes = std::fs::read(&valid_file).unwrap();
+        let position = bytes
+            .windows(b"Jane".len())
+            .position(|window| window == b"Jane")
+            .unwrap();
+        bytes[position..position + b"Jane".len()].copy_from_slice(b"Joan");
+        let tampered_file = dir.file("tampered.chain");
+        std::fs::write(&tampered_file, bytes).unwrap();
+        let voting_system = VotingSystem::load_from_file(&tampered_file).unwrap();
+
+        // Without the config only the broken hash of the following block can be detected.
+        let report = voting_system.verify(None).unwrap();
+        assert_eq!(report.height, 3);
+        assert!(matches!(report.first_invalid_block, Some((1, _))));
+        // With the config the invalid vote signature pinpoints the tampered block itself.
+        let report = voting_system.verify(Some(&config)).unwrap();
+        assert!(matches!(report.first_invalid_block, Some((0, _))));
+    }
+
+    #[test]
+    fn test_full_audit() {
+        let dir = TempDir::new("full-audit");
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let mut config = make_config(authority.get_public_key().unwrap());
+        config.allow_write_ins = true;
+        let now = chrono::Utc::now();
+        let voter = digital_sign::Signer::new().unwrap();
+        let access_token = make_access_token(&authority, &voter);
+        let mut voting_system = VotingSystem::new();
+        for vote in [
+            make_vote_at(&authority, now, WriteIn::new("Jane Doe").unwrap()),
+            make_vote_at(&authority, now, CandidateId::new(0)),
+            make_expired_vote(&authority, config.end, CandidateId::new(1)),
+            Vote::new(&voter, CandidateId::new(1), now, &access_token).unwrap(),
+            Vote::new(&voter, CandidateId::new(2), now, &access_token).unwrap(),
+        ] {
+            voting_system.add_votes(vec![vote]).unwrap();
+        }
+        let report = voting_system.full_audit(&config).unwrap();
+        assert_eq!(report.votes, 5);
+        assert_eq!(report.findings.len(), 2);
+        voting_system.verify(Some(&config)).unwrap();
+
+        // Rewrite the write-in of the first vote, keeping the file deserializable.
+        let file = dir.file("audit.chain");
+        voting_system.save_to_file(&file).unwrap();
+        let mut bytes = std::fs::read(&file).unwrap();
+        let position = bytes
+            .windows(b"Jane".len())
+            .position(|window| window == b"Jane")
+            .unwrap();
+        bytes[position..position + b"Jane".len()].copy_from_slice(b"Joan");
+        std::fs::write(&file, bytes).unwrap();
+        let voting_system = VotingSystem::load_from_file(&file).unwrap();
+
+        let report = voting_system.full_audit(&config).unwrap();
+        assert_eq!(report.height, 5);
+        assert!(!report.is_clean());
+        assert_eq!(
+            report
+                .findings
+                .iter()
+                .map(AuditFinding::block)
+                .collect::<Vec<_>>(),
+            [0, 1, 2, 4]
+        );
+        assert!(matches!(
+            report.findings[0],
+            AuditFinding::InvalidVote { .. }
+        ));
+        assert!(matches!(
+            report.findings[1],
+            AuditFinding::BrokenLink { .. }
+        ));
+        assert!(matches!(
+            report.findings[2],
+            AuditFinding::OutsideElectionPeriod { .. }
+        ));
+        assert!(matches!(
+            report.findings[3],
+            AuditFinding::DoubleVote { first_block: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn test_tally_from_file() {
+        let dir = TempDir::new("tally-from-file");
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let config = make_config(authority.get_public_key().unwrap());
+        let now = chrono::Utc::now();
+        let mut voting_system = VotingSystem::new();
+        for candidate in [0, 1, 1, 2, 1] {
+            let vote = make_vote_at(&authority, now, CandidateId::new(candidate));
+            voting_system.add_votes(vec![vote]).unwrap();
+        }
+        let chain_file = dir.file("tally.chain");
+        voting_system.save_to_file(&chain_file).unwrap();
+        let config_file = dir.join("tally-config.json");
+        std::fs::write(&config_file, serde_json::to_vec(&config).unwrap()).unwrap();
+
+        let tally = tally_from_file(&chain_file, &config_file, None).unwrap();
+        assert_eq!(
+            tally.candidates,
+            [
+                (CandidateId::new(0), 1),
+                (CandidateId::new(1), 3),
+                (CandidateId::new(2), 1)
+            ]
+            .into_iter()
+            .collect()
+        );
+        assert_eq!(tally, voting_system.tally_votes().unwrap());
+
+        let encrypted_file = dir.file("tally-encrypted.chain");
+        voting_system
+            .save_to_encrypted_file(&encrypted_file, b"secret")
+            .unwrap();
+        assert!(tally_from_file(&encrypted_file, &config_file, None).is_err());
+        assert_eq!(
+            tally_from_file(&encrypted_file, &config_file, Some(b"secret")).unwrap(),
+            tally
+        );
+
+        // Votes signed by another authority can't be counted.
+        let other_config = make_config(
+            blind_sign::BlindSigner::new()
+                .unwrap()
+                .get_public_key()
+                .unwrap(),
+        );
+        std::fs::write(&config_file, serde_json::to_vec(&other_config).unwrap()).unwrap();
+        assert!(matches!(
+            tally_from_file(&chain_file, &config_file, None),
+            Err(Error::AuditFailed(AuditFinding::InvalidVote {
+                block: 0,
+                ..
+            }))
+        ));
+
+        // Double votes are not counted either.
+        std::fs::write(&config_file, serde_json::to_vec(&config).unwrap()).unwrap();
+        let double_vote = voting_system.votes().next().unwrap().clone();
+        voting_system.add_votes(vec![double_vote]).unwrap();
+        voting_system.save_to_file(&chain_file).unwrap();
+        assert!(matches!(
+            tally_from_file(&chain_file, &config_file, None),
+            Err(Error::AuditFailed(AuditFinding::DoubleVote {
+                block: 5,
+                first_block: 0,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_tally_order() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let tally = Tally {
+            candidates: [(0, 2), (1, 5), (2, 2)]
+                .into_iter()
+                .map(|(id, count)| (CandidateId::new(id), count))
+                .collect(),
+            write_ins: [("Zed", 3), ("Amy", 3)]
+                .into_iter()
+                .map(|(name, count)| (WriteIn::new(name).unwrap(), count))
+                .collect(),
+            ..Tally::default()
+        };
+
+        assert_eq!(
+            tally
+                .display_ordered_by(TallyOrder::ByVotesDesc)
+                .to_string(),
+            "Candidate 1 has 5 votes\n\
+             Write-in \"Amy\" has 3 votes\n\
+             Write-in \"Zed\" has 3 votes\n\
+             Candidate 0 has 2 votes\n\
+             Candidate 2 has 2 votes\n"
+        );
+        assert_eq!(
+            tally
+                .display_ordered_by(TallyOrder::ByCandidateId)
+                .to_string(),
+            "Candidate 0 has 2 votes\n\
+             Candidate 1 has 5 votes\n\
+             Candidate 2 has 2 votes\n\
+             Write-in \"Amy\" has 3 votes\n\
+             Write-in \"Zed\" has 3 votes\n"
+        );
+        assert_eq!(
+            tally.to_string(),
+            tally
+                .display_ordered_by(TallyOrder::ByCandidateId)
+                .to_string()
+        );
+
+        // Candidates without votes are listed too and candidates missing from the config last.
+        let mut config = make_config(authority.get_public_key().unwrap());
+        config.candidates.reverse();
+        config.candidates.remove(1);
+        config.candidates.insert(
+            0,
+            Candidate {
+                name: "Nobody".to_owned(),
+                id: CandidateId::new(3),
+            },
+        );
+        assert_eq!(
+            tally
+                .display_ordered_by(TallyOrder::AsConfigured(&config))
+                .to_string(),
+            "Candidate 3 (Nobody) has 0 votes\n\
+             Candidate 2 (Candidate 2) has 2 votes\n\
+             Candidate 0 (Candidate 0) has 2 votes\n\
+             Candidate 1 has 5 votes\n\
+             Write-in \"Amy\" has 3 votes\n\
+             Write-in \"Zed\" has 3 votes\n"
+        );
+    }
+
+    #[test]
+    fn test_votes_page() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let now = chrono::Utc::now();
+        let mut voting_system = VotingSystem::new();
+        // Batches of 3, 0 and 2 votes, alternating between candidates 0 and 1.
+        for block_size in [3, 0, 2] {
+            let votes = (0..block_size)
+                .map(|i| make_vote_at(&authority, now, CandidateId::new(i % 2)))
+                .collect();
+            voting_system.add_votes(votes).unwrap();
+        }
+
+        assert_eq!(voting_system.votes_page(0, 10, None).len(), 5);
+        assert_eq!(voting_system.votes_page(2, 2, None).len(), 2);
+        assert_eq!(voting_system.votes_page(4, 2, None).len(), 1);
+        assert!(voting_system.votes_page(5, 2, None).is_empty());
+        assert!(voting_system.votes_page(0, 0, None).is_empty());
+
+        let candidate = CandidateId::new(1);
+        let votes = voting_system.votes_page(0, 10, Some(&candidate));
+        assert_eq!(votes.len(), 2);
+        assert!(votes
+            .iter()
+            .all(|vote| vote.get_choice() == &Choice::Candidate(candidate.clone())));
+        assert_eq!(voting_system.votes_page(1, 10, Some(&candidate)).len(), 1);
+        assert!(voting_system
+            .votes_page(0, 10, Some(&CandidateId::new(2)))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_block_splitting() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let now = chrono::Utc::now();
+        let mut voting_system = VotingSystem::with_max_votes_per_block(3);
+        let votes: Vec<Vote> = (0..7)
+            .map(|i| make_vote_at(&authority, now, CandidateId::new(i)))
+            .collect();
+
+        voting_system.add_votes(votes.clone()).unwrap();
+        assert_eq!(voting_system.height(), 3);
+        let block_sizes: Vec<usize> = voting_system.blockchain.iter().map(Vec::len).collect();
+        assert_eq!(block_sizes, vec![3, 3, 1]);
+        voting_system.validate().unwrap();
+        assert_eq!(voting_system.tally(), &voting_system.tally_votes().unwrap());
+        // The order of the votes must be preserved across the blocks.
+        let stored = voting_system.votes_page(0, 10, None);
+        assert!(stored
+            .iter()
+            .zip(&votes)
+            .all(|(stored, vote)| stored.get_choice() == vote.get_choice()));
+
+        voting_system.add_votes(Vec::new()).unwrap();
+        assert_eq!(voting_system.height(), 3);
+    }
+
+    #[test]
+    fn test_votes() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let now = chrono::Utc::now();
+        let mut voting_system = VotingSystem::new();
+        assert_eq!(voting_system.votes().count(), 0);
+
+        let mut votes = Vec::new();
+        for batch in [3, 1, 2] {
+            let batch: Vec<Vote> = (0..batch)
+                .map(|i| make_vote_at(&authority, now, CandidateId::new(i)))
+                .collect();
+            votes.extend(batch.iter().cloned());
+            voting_system.add_votes(batch).unwrap();
+        }
+        assert_eq!(voting_system.height(), 3);
+        assert_eq!(voting_system.votes().count(), 6);
+        // The votes of all the blocks are returned in chain order.
+        assert!(voting_system
+            .votes()
+            .map(Vote::get_nonce)
+            .eq(votes.iter().map(Vote::get_nonce)));
+    }
+
+    #[test]
+    fn test_signed_tally() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let now = chrono::Utc::now();
+        let mut voting_system = VotingSystem::new();
+        voting_system
+            .add_votes(
+                (0..4)
+                    .map(|i| make_vote_at(&authority, now, CandidateId::new(i % 2)))
+                    .collect(),
+            )
+            .unwrap();
+        let signer = digital_sign::Signer::new().unwrap();
+        let public_key = signer.get_public_key();
+
+        let signed_tally = voting_system.tally_votes().unwrap().sign(&signer);
+        signed_tally.verify(&public_key).unwrap();
+        assert!(signed_tally
+            .verify(&digital_sign::Signer::new().unwrap().get_public_key())
+            .is_err());
+
+        let mut tampered = signed_tally.clone();
+        *tampered
+            .tally
+            .candidates
+            .get_mut(&CandidateId::new(0))
+            .unwrap() += 1;
+        assert!(tampered.verify(&public_key).is_err());
+
+        // The same results signed for a different state of the chain must not verify either.
+        voting_system
+            .add_votes(vec![make_vote_at(&authority, now, CandidateId::new(2))])
+            .unwrap();
+        let mut tampered = signed_tally.clone();
+        tampered.tally.chain_tip = voting_system.tally_votes().unwrap().chain_tip;
+        assert!(tampered.verify(&public_key).is_err());
+    }
+
+    #[test]
+    fn test_abstentions() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let now = chrono::Utc::now();
+        let mut config = make_config(authority.get_public_key().unwrap());
+        config.allow_abstentions = true;
+        let abstention = make_vote_at(&authority, now, Choice::Abstain);
+        config.verify_vote(&abstention, now).unwrap();
+
+        let mut voting_system = VotingSystem::new();
+        voting_system
+            .add_votes(vec![
+                abstention,
+                make_vote_at(&authority, now, CandidateId::new(1)),
+                make_vote_at(&authority, now, Choice::Abstain),
+            ])
+            .unwrap();
+        let tally = voting_system.tally_votes().unwrap();
+        assert_eq!(voting_system.tally(), &tally);
+        assert_eq!(tally.abstentions, 2);
+        assert_eq!(
+            tally.candidates,
+            [(CandidateId::new(1), 1)].into_iter().collect()
+        );
+        assert_eq!(
+            tally
+                .display_ordered_by(TallyOrder::AsConfigured(&config))
+                .to_string(),
+            "Candidate 0 (Candidate 0) has 0 votes\n\
+             Candidate 1 (Candidate 1) has 1 votes\n\
+             Candidate 2 (Candidate 2) has 0 votes\n\
+             2 voters abstained\n"
+        );
+
+        // The abstentions are covered by the signature.
+        let signer = digital_sign::Signer::new().unwrap();
+        let mut tampered = tally.sign(&signer);
+        tampered.verify(&signer.get_public_key()).unwrap();
+        tampered.tally.abstentions = 0;
+        assert!(tampered.verify(&signer.get_public_key()).is_err());
+    }
+
+    #[test]
+    fn test_summary() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let now = chrono::Utc::now();
+        let mut voting_system = VotingSystem::new();
+        assert_eq!(voting_system.summary(), ElectionSummary::default());
+
+        voting_system
+            .add_votes(
+                [(0, 1), (1, 0), (2, 1), (3, 2)]
+                    .into_iter()
+                    .map(|(minutes, candidate)| {
+                        make_vote_at(
+                            &authority,
+                            now + std::time::Duration::from_secs(minutes * 60),
+                            CandidateId::new(candidate),
+                        )
+                    })
+                    .collect(),
+            )
+            .unwrap();
+        let summary = voting_system.summary();
+        assert_eq!(summary.total_votes, 4);
+        assert_eq!(summary.unique_voters, 4);
+        assert_eq!(summary.first_vote, Some(now));
+        assert_eq!(
+            summary.last_vote,
+            Some(now + std::time::Duration::from_secs(180))
+        );
+        assert_eq!(
+            summary
+                .results
+                .iter()
+                .map(|result| (result.choice.clone(), result.votes))
+                .collect::<Vec<_>>(),
+            [
+                (ApiChoice::Candidate(1), 2),
+                (ApiChoice::Candidate(0), 1),
+                (ApiChoice::Candidate(2), 1)
+            ]
+        );
+        assert!((summary.results[0].percentage - 50.0).abs() < 1e-9);
+        let total: f64 = summary.results.iter().map(|result| result.percentage).sum();
+        assert!((total - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_find_voter() {
+        let dir = TempDir::new("find-voter");
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let now = chrono::Utc::now();
+        let mut voting_system = VotingSystem::with_max_votes_per_block(2);
+        let votes: Vec<_> = (0..3)
+            .map(|candidate| make_vote_at(&authority, now, CandidateId::new(candidate)))
+            .collect();
+        voting_system.add_votes(votes.clone()).unwrap();
+
+        assert_eq!(voting_system.find_voter(votes[0].get_public_key()), Some(0));
+        assert_eq!(voting_system.find_voter(votes[2].get_public_key()), Some(1));
+        let non_voter = digital_sign::Signer::new().unwrap().get_public_key();
+        assert_eq!(voting_system.find_voter(&non_voter), None);
+
+        // The index is not saved, so it must be rebuilt when loading.
+        let chain_file = dir.file("find-voter.chain");
+        voting_system.save_to_file(&chain_file).unwrap();
+        let voting_system = VotingSystem::load_from_file(&chain_file).unwrap();
+        assert_eq!(voting_system.find_voter(votes[2].get_public_key()), Some(1));
+        assert_eq!(voting_system.find_voter(&non_voter), None);
+    }
+
+    #[test]
+    fn test_incremental_tally() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let now = chrono::Utc::now();
+        let mut voting_system = VotingSystem::with_max_votes_per_block(2);
+        assert_eq!(voting_system.tally(), &voting_system.tally_votes().unwrap());
+
+        for batch in [vec![0, 1, 1], vec![2], vec![1]] {
+            let votes = batch
+                .into_iter()
+                .map(|candidate| make_vote_at(&authority, now, CandidateId::new(candidate)))
+                .collect();
+            voting_system.add_votes(votes).unwrap();
+            assert_eq!(voting_system.tally(), &voting_system.tally_votes().unwrap());
+        }
+        let write_in = make_vote_at(&authority, now, WriteIn::new("Jane Doe").unwrap());
+        voting_system.add_votes(vec![write_in]).unwrap();
+        assert_eq!(voting_system.tally(), &voting_system.tally_votes().unwrap());
+
+        let popped = voting_system.pop_block().unwrap().unwrap();
+        assert_eq!(popped.len(), 1);
+        assert_eq!(voting_system.find_voter(popped[0].get_public_key()), None);
+        let tally = voting_system.tally();
+        assert_eq!(tally, &voting_system.tally_votes().unwrap());
+        assert!(tally.write_ins.is_empty());
+        assert_eq!(
+            tally.candidates,
+            [
+                (CandidateId::new(0), 1),
+                (CandidateId::new(1), 3),
+                (CandidateId::new(2), 1)
+            ]
+            .into_iter()
+            .collect()
+        );
+
+        while voting_system.pop_block().unwrap().is_some() {}
+        assert_eq!(voting_system.tally(), &Tally::default());
+    }
+
+    #[test]
+    fn test_merge_tallies() {
+        let write_in = WriteIn::new("Jane Doe").unwrap();
+        let mut north = Tally::default();
+        for choice in [
+            Choice::from(CandidateId::new(0)),
+            Choice::from(CandidateId::new(1)),
+            Choice::from(CandidateId::new(1)),
+            Choice::from(write_in.clone()),
+        ] {
+            north.add(&choice);
+        }
+        north.chain_tip = Hash::try_from_slice(&[1; blockchain::HASH_LEN]).unwrap();
+        let mut south = Tally::default();
+        for candidate in [1, 2, 2] {
+            south.add(&Choice::from(CandidateId::new(candidate)));
+        }
+
+        let mut merged = north.clone();
+        merged.merge(&south);
+        assert_eq!(
+            merged.candidates,
+            [
+                (CandidateId::new(0), 1),
+                (CandidateId::new(1), 3),
+                (CandidateId::new(2), 2)
+            ]
+            .into_iter()
+            .collect()
+        );
+        assert_eq!(merged.write_ins, [(write_in, 1)].into_iter().collect());
+        assert_eq!(merged.chain_tip, Hash::default());
+
+        assert_eq!(Tally::sum([&north, &south]), merged);
+        assert_eq!(Tally::sum([&south, &north]), merged);
+        assert_eq!(Tally::sum([]), Tally::default());
+    }
+}
@@ -1,14 +1,39 @@
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+use ring::digest;
+use thiserror::Error;
 use tracing::level_filters::LevelFilter;
 use tracing_appender::non_blocking::WorkerGuard as TracingWorkerGuard;
 
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Log hash integrity error at line {}", .0)]
+    HashIntegrity(usize),
+    #[error("Log line {} is not hash-chained", .0)]
+    MissingHash(usize),
+    #[error("Last log line is incomplete")]
+    IncompleteLine,
+    #[error("File IO error: {}", .0)]
+    FileIO(#[from] std::io::Error),
+}
+type Result<T> = std::result::Result<T, Error>;
+
 /// Start a logger that writes traces to a file without blocking.
-pub fn start_logger(log_filename: &str) -> std::io::Result<TracingWorkerGuard> {
-    // Set up a rolling file appender
-    std::fs::create_dir_all("logs")?;
-    let log_file = std::fs::File::create(format!("logs/{log_filename}"))?;
+/// The directory of the file must already exist, see `DataPaths`.
+/// The traces are appended to the file, so that the logs of the previous runs are kept.
+///
+/// # Arguments
+///
+/// - `log_path` - The file to which the traces are written.
+/// - `hash_chained` - Whether to hash-chain the lines of the log, see `open_log`.
+pub fn start_logger(
+    log_path: &std::path::Path,
+    hash_chained: bool,
+) -> std::io::Result<TracingWorkerGuard> {
     // Do not let _tracing_worker_guard go out of scope, or the logging thread will be terminated.
-    let (non_blocking_tracing_writer, tracing_worker_guard) =
-        tracing_appender::non_blocking(log_file);
+    let (log, truncated) = open_log(log_path, hash_chained)?;
+    let (non_blocking_tracing_writer, tracing_worker_guard) = tracing_appender::non_blocking(log);
 
     tracing_subscriber::fmt()
         // TODO For now allowing all log levels.
@@ -18,12 +43,279 @@ pub fn start_logger(log_filename: &str) -> std::io::Result<TracingWorkerGuard> {
         .with_ansi(false)
         .with_level(true)
         .try_init()
+        .map_err(|e| std::io::Error::other(format!("Failed to initialize logger {e}")))?;
+    if truncated > 0 {
+        tracing::warn!("Truncated {truncated} bytes of an incomplete line at the end of the log");
+    }
+
+    Ok(tracing_worker_guard)
+}
+
+/// Open the log file for appending.
+/// A hash-chained log is verified first and continued from the hash of its last line, see
+/// `HashChainWriter::resume`, so that the lines of all runs form a single chain.
+/// A line cut short at the end of a hash-chained log, because the node stopped in the middle of
+/// writing it, is truncated, so that the next line doesn't continue it.
+///
+/// # Returns
+///
+/// The writer appending to the log and how many bytes of an incomplete line were truncated.
+///
+/// # Errors
+///
+/// If the file could not be opened or if the hash chain of an existing log is broken, in which
+/// case the log has to be moved away before the node can continue logging.
+fn open_log(
+    log_path: &std::path::Path,
+    hash_chained: bool,
+) -> std::io::Result<(Box<dyn Write + Send>, u64)> {
+    let log_file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)?;
+    if !hash_chained {
+        return Ok((Box::new(log_file), 0));
+    }
+    let end = verify_complete_lines(std::io::BufReader::new(std::fs::File::open(log_path)?))
         .map_err(|e| {
-            std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Failed to initialize logger {e}"),
-            )
+            std::io::Error::other(format!(
+                "Can't continue the hash chain of log {}: {e}",
+                log_path.display()
+            ))
         })?;
+    if end.incomplete_len > 0 {
+        log_file.set_len(end.complete_len)?;
+    }
 
-    Ok(tracing_worker_guard)
+    Ok((
+        Box::new(HashChainWriter::resume(log_file, end.hash)),
+        end.incomplete_len,
+    ))
+}
+
+/// Writer which prefixes every line with a hash of the line and of the hash of the previous
+/// line, the same way the blocks of the blockchain are chained, so that a line can't be removed
+/// or altered without breaking the hashes of all the lines that follow it.
+/// Lines are written as `<hex hash> <line>` once they are complete.
+pub struct HashChainWriter<W> {
+    writer: W,
+    /// Hash of the last written line or zeroes before the first line.
+    prev_hash: [u8; digest::SHA256_OUTPUT_LEN],
+    /// The part of the current line which had been written so far.
+    line: Vec<u8>,
+}
+
+impl<W: Write> HashChainWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self::resume(writer, [0; digest::SHA256_OUTPUT_LEN])
+    }
+
+    /// Continue a hash-chained log after the line with the hash.
+    ///
+    /// # Arguments
+    ///
+    /// - `writer` - Writer appending to the log.
+    /// - `prev_hash` - Hash of the last line of the log, see `verify_hash_chain`.
+    pub fn resume(writer: W, prev_hash: [u8; digest::SHA256_OUTPUT_LEN]) -> Self {
+        Self {
+            writer,
+            prev_hash,
+            line: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for HashChainWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for byte in buf {
+            if *byte == b'\n' {
+                self.prev_hash = chain_hash(&self.prev_hash, &self.line);
+                // The line is written at once, so that a crash can only cut it short at the end
+                // of the log instead of interleaving it with the next run.
+                let mut entry = hex(&self.prev_hash).into_bytes();
+                entry.push(b' ');
+                entry.append(&mut self.line);
+                entry.push(b'\n');
+                self.writer.write_all(&entry)?;
+            } else {
+                self.line.push(*byte);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Verify the hashes of a log written through `HashChainWriter`.
+///
+/// # Returns
+///
+/// The hash of the last line, or zeroes if the log is empty.
+///
+/// # Errors
+///
+/// If a line is missing its hash or if its hash doesn't match the line and the lines before it,
+/// which means that the line or a line before it had been altered, removed or inserted, or if
+/// the last line is incomplete.
+pub fn verify_hash_chain(reader: impl BufRead) -> Result<[u8; digest::SHA256_OUTPUT_LEN]> {
+    let end = verify_complete_lines(reader)?;
+    if end.incomplete_len > 0 {
+        return Err(Error::IncompleteLine);
+    }
+    Ok(end.hash)
+}
+
+/// The end of the hash chain of a log, see `verify_complete_lines`.
+struct ChainEnd {
+    /// Hash of the last complete line, or zeroes if there are none.
+    hash: [u8; digest::SHA256_OUTPUT_LEN],
+    /// Length of the complete lines in bytes.
+    complete_len: u64,
+    /// Length of the last line in bytes, if it was cut short before its line break.
+    incomplete_len: u64,
+}
+
+/// Verify the hashes of the lines of a log, apart from a last line which is missing its line
+/// break and so had never been written completely.
+///
+/// # Errors
+///
+/// Same as `verify_hash_chain`, apart from the incomplete last line.
+fn verify_complete_lines(mut reader: impl BufRead) -> Result<ChainEnd> {
+    let mut end = ChainEnd {
+        hash: [0; digest::SHA256_OUTPUT_LEN],
+        complete_len: 0,
+        incomplete_len: 0,
+    };
+    let mut line = Vec::new();
+    for index in 0.. {
+        line.clear();
+        let len = reader.read_until(b'\n', &mut line)? as u64;
+        let Some(line) = line.strip_suffix(b"\n") else {
+            end.incomplete_len = len;
+            break;
+        };
+        let separator = line
+            .iter()
+            .position(|byte| *byte == b' ')
+            .ok_or(Error::MissingHash(index))?;
+        let (hash, line) = (&line[..separator], &line[separator + 1..]);
+        end.hash = chain_hash(&end.hash, line);
+        if hash != hex(&end.hash).as_bytes() {
+            return Err(Error::HashIntegrity(index));
+        }
+        end.complete_len += len;
+    }
+    Ok(end)
+}
+
+fn chain_hash(prev_hash: &[u8], line: &[u8]) -> [u8; digest::SHA256_OUTPUT_LEN] {
+    let mut context = digest::Context::new(&digest::SHA256);
+    context.update(prev_hash);
+    context.update(line);
+    let mut hash = [0; digest::SHA256_OUTPUT_LEN];
+    hash.copy_from_slice(context.finish().as_ref());
+    hash
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 * bytes.len());
+    for byte in bytes {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use process_io::test_utils::TempDir;
+
+    #[test]
+    fn test_hash_chain() {
+        let mut writer = HashChainWriter::new(Vec::new());
+        writer.write_all(b"first line\nsecond ").unwrap();
+        writer.write_all(b"line\nthird line\n").unwrap();
+        let log = String::from_utf8(writer.writer).unwrap();
+        assert_eq!(log.lines().count(), 3);
+        assert!(log
+            .lines()
+            .all(|line| line.len() > 2 * digest::SHA256_OUTPUT_LEN));
+        verify_hash_chain(log.as_bytes()).unwrap();
+
+        let modified = log.replace("second line", "forged line");
+        assert!(matches!(
+            verify_hash_chain(modified.as_bytes()),
+            Err(Error::HashIntegrity(1))
+        ));
+        let mut lines: Vec<_> = log.lines().collect();
+        lines.remove(0);
+        assert!(matches!(
+            verify_hash_chain(lines.join("\n").as_bytes()),
+            Err(Error::HashIntegrity(0))
+        ));
+        assert!(matches!(
+            verify_hash_chain("unchained\n".as_bytes()),
+            Err(Error::MissingHash(0))
+        ));
+    }
+
+    #[test]
+    fn test_reopen_log() {
+        let dir = TempDir::new("reopen-log");
+        let path = dir.join("node.log");
+
+        for run in ["first", "second"] {
+            let (mut log, truncated) = open_log(&path, true).unwrap();
+            assert_eq!(truncated, 0);
+            writeln!(log, "{run} run").unwrap();
+        }
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        verify_hash_chain(log.as_bytes()).unwrap();
+
+        // A tampered log is not extended.
+        std::fs::write(&path, log.replace("first", "forged")).unwrap();
+        assert!(open_log(&path, true).is_err());
+        let (mut log, _) = open_log(&path, false).unwrap();
+        writeln!(log, "unchained").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+    }
+
+    #[test]
+    fn test_reopen_cut_short_log() {
+        let dir = TempDir::new("reopen-cut-short-log");
+        let path = dir.join("node.log");
+
+        let (mut log, _) = open_log(&path, true).unwrap();
+        writeln!(log, "first run").unwrap();
+        writeln!(log, "cut short").unwrap();
+        drop(log);
+        let complete = std::fs::read(&path).unwrap();
+        let first_len = complete.iter().position(|byte| *byte == b'\n').unwrap() + 1;
+        let cut_len = complete.len() - 5;
+        std::fs::write(&path, &complete[..cut_len]).unwrap();
+        assert!(matches!(
+            verify_hash_chain(std::io::BufReader::new(std::fs::File::open(&path).unwrap())),
+            Err(Error::IncompleteLine)
+        ));
+
+        // The incomplete line is truncated and the chain continues from the line before it.
+        let (mut log, truncated) = open_log(&path, true).unwrap();
+        assert_eq!(truncated, (cut_len - first_len) as u64);
+        writeln!(log, "second run").unwrap();
+        drop(log);
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            log.lines()
+                .map(|line| line.split_once(' ').unwrap().1)
+                .collect::<Vec<_>>(),
+            ["first run", "second run"]
+        );
+        verify_hash_chain(log.as_bytes()).unwrap();
+    }
 }
@@ -1,31 +1,212 @@
-use anyhow::Result;
+use std::io::Write;
+
+use anyhow::{anyhow, bail, Result};
 use clap::Parser;
 
-use digital_voting::{api::server_cli::Args, logging::start_logger};
-use process_io::cli::StdioReader;
+use digital_voting::{
+    api::{
+        node_key,
+        receipt::Receipts,
+        server_cli::{Args, Cmd},
+        state::State,
+        verification_pool::VerificationPool,
+    },
+    audit::AuditLog,
+    data_paths::DataPaths,
+    logging::{self, start_logger},
+    self_test, tally_from_file, VotingSystem,
+};
+use process_io::{cli::StdioReader, output::Output};
+use protocol::config::ElectionConfig;
+
+fn load_config(path: &std::path::Path) -> Result<ElectionConfig> {
+    Ok(serde_json::from_reader(std::fs::File::open(path)?)?)
+}
+
+/// Load the election configs either from a single JSON file or from all the JSON files in a
+/// directory, so that a single node could host multiple elections.
+/// A directory without any configs is an error, since the node would have nothing to serve.
+fn load_configs(path: &std::path::Path) -> Result<Vec<ElectionConfig>> {
+    if !path.is_dir() {
+        return Ok(vec![load_config(path)?]);
+    }
+    let mut paths = std::fs::read_dir(path)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    paths.retain(|path| {
+        path.extension()
+            .is_some_and(|extension| extension == "json")
+    });
+    if paths.is_empty() {
+        bail!("No election configs found in {}", path.display());
+    }
+    // Sorting so that the elections are always loaded in the same order.
+    paths.sort();
+
+    paths.iter().map(|path| load_config(path)).collect()
+}
+
+/// Read the secret for the encryption at rest, if it is enabled.
+/// Trailing whitespace is ignored, since key files usually end with a newline.
+fn load_at_rest_secret(args: &Args) -> Result<Option<Vec<u8>>> {
+    if !args.encrypt_at_rest {
+        return Ok(None);
+    }
+    let path = args
+        .at_rest_key_file
+        .as_deref()
+        .ok_or_else(|| anyhow!("Key file is required for encryption at rest"))?;
+    let secret = std::fs::read(path)?.trim_ascii_end().to_vec();
+    if secret.is_empty() {
+        bail!("Key file {} is empty", path.display());
+    }
+
+    Ok(Some(secret))
+}
+
+fn verify_file(
+    path: &str,
+    config: Option<&std::path::Path>,
+    at_rest_secret: Option<&[u8]>,
+    output: &mut Output,
+) -> Result<()> {
+    let config = config.map(load_config).transpose()?;
+    let report = VotingSystem::load(path, at_rest_secret)?.verify(config.as_ref())?;
+    write!(output, "{report}")?;
+    if !report.is_valid() {
+        bail!("Blockchain file {path} is invalid");
+    }
+
+    Ok(())
+}
+
+fn audit_file(
+    path: &str,
+    config: Option<&std::path::Path>,
+    at_rest_secret: Option<&[u8]>,
+    output: &mut Output,
+) -> Result<()> {
+    let config = config.ok_or_else(|| anyhow!("Election config is required to audit a file"))?;
+    let report = VotingSystem::load(path, at_rest_secret)?.full_audit(&load_config(config)?)?;
+    write!(output, "{report}")?;
+    if !report.is_clean() {
+        bail!("Blockchain file {path} failed the audit");
+    }
+
+    Ok(())
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
-    println!("Args: {args:?}");
-    let _tracing_worker_guard = start_logger("digital_voting.log")?;
+    let at_rest_secret = load_at_rest_secret(&args)?;
+    let mut output = Output::open(args.output.as_deref())?;
+    match &args.cmd {
+        Cmd::VerifyFile { path } => {
+            return verify_file(
+                path,
+                args.config.as_deref(),
+                at_rest_secret.as_deref(),
+                &mut output,
+            );
+        }
+        Cmd::AuditFile { path } => {
+            return audit_file(
+                path,
+                args.config.as_deref(),
+                at_rest_secret.as_deref(),
+                &mut output,
+            );
+        }
+        Cmd::TallyFile { path } => {
+            let config = args
+                .config
+                .as_deref()
+                .ok_or_else(|| anyhow!("Election config is required to tally a file"))?;
+            write!(
+                output,
+                "{}",
+                tally_from_file(path, config, at_rest_secret.as_deref())?
+            )?;
+            return Ok(());
+        }
+        Cmd::VerifyLog { path } => {
+            let log_file = std::io::BufReader::new(std::fs::File::open(path)?);
+            logging::verify_hash_chain(log_file)?;
+            writeln!(output, "Log file {} is intact", path.display())?;
+            return Ok(());
+        }
+        Cmd::RepairFile { path } => {
+            write!(
+                output,
+                "{}",
+                VotingSystem::repair_file(path, at_rest_secret.as_deref())?
+            )?;
+            return Ok(());
+        }
+        Cmd::SelfTest {} => {
+            let config_path = args
+                .config
+                .as_deref()
+                .ok_or_else(|| anyhow!("Election config is required to run the self test"))?;
+            let data_paths = DataPaths::new(&args.data_path)?;
+            for config in load_configs(config_path)? {
+                self_test::run(&config, &data_paths, at_rest_secret.as_deref())
+                    .map_err(|e| anyhow!("Self test of election {} failed: {e}", config.name))?;
+                writeln!(output, "Self test of election {} passed", config.name)?;
+            }
+            return Ok(());
+        }
+        Cmd::Genesis {} | Cmd::Connect { .. } => {}
+    }
+    writeln!(output, "Args: {args:?}")?;
+    let data_paths = DataPaths::new(&args.data_path)?;
+    let _tracing_worker_guard = start_logger(
+        &data_paths.log_file("digital_voting.log"),
+        args.hash_chain_logs,
+    )?;
+    let config_path = args
+        .config
+        .as_deref()
+        .ok_or_else(|| anyhow!("Election config is required to run the node"))?;
+    let mut verification_pool = VerificationPool::new(args.verification_threads);
+    if let Some(max_queued) = args.max_queued_verifications {
+        verification_pool = verification_pool.with_max_queued(max_queued);
+    }
+    let mut state = State::new(load_configs(config_path)?, verification_pool)?
+        .with_proof_of_work(args.pow_difficulty)
+        .with_max_votes_per_block(args.max_votes_per_block)?
+        .with_node_signer(node_key::load_or_create(&data_paths.node_keys())?)
+        .with_receipts(Receipts::load_or_create(&data_paths.receipt_secret())?)
+        .with_audit_log(AuditLog::open(&data_paths.audit_log())?);
+    if let Some(secret) = at_rest_secret {
+        state = state.with_encryption_at_rest(secret);
+    }
+    state.persist_chains(&data_paths)?;
+    // The blockchains are loaded and the indices rebuilt from them by `persist_chains`.
+    state.mark_ready();
 
-    tokio::task::spawn_blocking(|| {
-        let mut stdio_reader = StdioReader::new().unwrap();
+    let mut stdio_reader =
+        StdioReader::with_history(data_paths.cmd_history(), args.max_history_entries)?;
+    tokio::task::spawn_blocking(move || {
         loop {
             let line = match stdio_reader.read_stdio_blocking() {
                 Ok(line) => line,
                 Err(e) => {
                     // TODO
-                    println!("Quitting: {e:?}, send interrupt again to kill the server (WIP)");
+                    let _ = writeln!(
+                        output,
+                        "Quitting: {e:?}, send interrupt again to kill the server (WIP)"
+                    );
                     break;
                 }
             };
-            println!("Read line: {line:?}");
+            let _ = writeln!(output, "Read line: {line:?}");
         }
     });
 
-    digital_voting::api::server::run(args.socket_addr).await?;
+    digital_voting::api::server::run(args.socket_addr, args.server_limits, args.url_prefix, state)
+        .await?;
 
     Ok(())
 }
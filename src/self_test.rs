@@ -0,0 +1,175 @@
+//! Self test of a node, which checks that everything is wired correctly before an election.
+//! A vote of a throwaway voter, whose access token is issued by a throwaway authority, is run
+//! through the same verification as the submitted votes and stored the same way as the votes
+//! of the election, so that a misconfigured node fails before any real votes are cast.
+
+use thiserror::Error;
+
+use crypto::signature::{blind_sign, digital_sign};
+use protocol::{
+    choice::{Choice, WriteIn},
+    config::{ElectionConfig, ElectionContext, Error as ConfigError, VoteRejection},
+    vote::{Error as VoteError, Vote},
+};
+
+use crate::{data_paths::DataPaths, VotingSystem};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Invalid election config: {}", .0)]
+    InvalidConfig(#[from] ConfigError),
+    #[error("The election doesn't allow any choice on the ballot")]
+    NoAllowedChoice,
+    #[error("Failed to issue the access token: {}", .0)]
+    AccessToken(#[from] blind_sign::Error),
+    #[error("Failed to generate the voter keys: {}", .0)]
+    VoterKeys(#[from] digital_sign::Error),
+    #[error("Failed to cast the vote: {}", .0)]
+    Vote(#[from] VoteError),
+    #[error("Valid vote was rejected: {}", .0)]
+    VoteRejected(#[from] VoteRejection),
+    #[error("Storage failed: {}", .0)]
+    Storage(#[from] crate::Error),
+    #[error("Stored votes don't match the loaded votes")]
+    StorageMismatch,
+    #[error("Failed to remove the self test file: {}", .0)]
+    Cleanup(std::io::Error),
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// Name of the blockchain file written during the self test.
+const SELF_TEST_FILE: &str = ".self-test.chain";
+
+/// Run the self test of an election hosted by the node.
+///
+/// # Arguments
+///
+/// - `config` - The config of the election. Its authority key is replaced by a throwaway one,
+///   since the keys of the real authority are not available to the node.
+/// - `data_paths` - The data directory in which the blockchain files of the node are kept.
+/// - `at_rest_secret` - The secret of the encryption at rest, if it is enabled.
+///
+/// # Errors
+///
+/// If the config is invalid or if the vote could not be cast, verified, stored or loaded.
+pub fn run(
+    config: &ElectionConfig,
+    data_paths: &DataPaths,
+    at_rest_secret: Option<&[u8]>,
+) -> Result<()> {
+    config.validate()?;
+    let choice = allowed_choice(config)?;
+
+    let authority = blind_sign::BlindSigner::new()?;
+    let context = ElectionContext::new(ElectionConfig {
+        authority_key: authority.get_public_key()?,
+        signature: None,
+        ..config.clone()
+    })?;
+    let voter = digital_sign::Signer::new()?;
+    let access_token = issue_access_token(&authority, &voter)?;
+    // The election doesn't have to be open yet, so the vote is cast at its start.
+    let vote = Vote::new(&voter, choice, config.start, &access_token)?;
+    context.verify_cast_vote(&vote)?;
+
+    let path = data_paths.root().join(SELF_TEST_FILE);
+    let result = check_storage(&path.to_string_lossy(), vote, at_rest_secret);
+    let cleanup = std::fs::remove_file(&path);
+    result?;
+    match cleanup {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Error::Cleanup(e)),
+        _ => Ok(()),
+    }
+}
+
+/// Pick a choice which the election allows on the ballot.
+fn allowed_choice(config: &ElectionConfig) -> Result<Choice> {
+    if let Some(candidate) = config.candidates.first() {
+        Ok(candidate.id.clone().into())
+    } else if config.allow_write_ins {
+        Ok(WriteIn::new("Self test")
+            .map_err(|_| Error::NoAllowedChoice)?
+            .into())
+    } else if config.allow_abstentions {
+        Ok(Choice::Abstain)
+    } else {
+        Err(Error::NoAllowedChoice)
+    }
+}
+
+/// Issue an access token to the voter, going through the same blinding as the real voters.
+fn issue_access_token(
+    authority: &blind_sign::BlindSigner,
+    voter: &digital_sign::Signer,
+) -> Result<blind_sign::Signature> {
+    let public_key = voter.get_public_key();
+    let (blinded, unblinder) =
+        blind_sign::Blinder::new(authority.get_public_key()?)?.blind(&public_key)?;
+    let blind_signature = authority.bling_sign(&blinded)?;
+
+    Ok(unblinder.unblind_signature(blind_signature, &public_key)?)
+}
+
+/// Store the vote into the file and check that it is loaded back intact.
+fn check_storage(path: &str, vote: Vote, at_rest_secret: Option<&[u8]>) -> Result<()> {
+    let mut voting_system = VotingSystem::new();
+    voting_system.add_votes(vec![vote])?;
+    match at_rest_secret {
+        Some(secret) => voting_system.save_to_encrypted_file(path, secret)?,
+        None => voting_system.save_to_file(path)?,
+    }
+    let loaded = VotingSystem::load(path, at_rest_secret)?;
+    loaded.validate()?;
+    if loaded.height() != voting_system.height() || loaded.tally() != voting_system.tally() {
+        return Err(Error::StorageMismatch);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use process_io::test_utils::TempDir;
+    use protocol::test_utils::make_config;
+
+    #[test]
+    fn test_self_test() {
+        let root = TempDir::new("self-test");
+        let data_paths = DataPaths::new(root.path()).unwrap();
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let config = make_config(authority.get_public_key().unwrap());
+
+        run(&config, &data_paths, None).unwrap();
+        run(&config, &data_paths, Some(b"secret")).unwrap();
+        assert!(!root.join(SELF_TEST_FILE).exists());
+
+        // The election doesn't have to be open for the self test to pass.
+        let minute = std::time::Duration::from_secs(60);
+        let future = ElectionConfig {
+            start: config.end + minute,
+            end: config.end + 2 * minute,
+            ..config.clone()
+        };
+        run(&future, &data_paths, None).unwrap();
+
+        let no_choice = ElectionConfig {
+            candidates: Vec::new(),
+            ..config.clone()
+        };
+        assert!(matches!(
+            run(&no_choice, &data_paths, None),
+            Err(Error::NoAllowedChoice)
+        ));
+        let inverted = ElectionConfig {
+            start: config.end,
+            end: config.start,
+            ..config
+        };
+        assert!(matches!(
+            run(&inverted, &data_paths, None),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+//! This file contains the client of the HTTP API of the nodes, so that every request is built
+//! from the base URL of the node in one place and fails in the same way.
+
+use std::time::Duration;
+
+use futures::future::{select, Either};
+use gloo_net::http::{Request, Response};
+use gloo_timers::future::TimeoutFuture;
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+/// Why a request to a node failed, so that the UI can react differently to each of them.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum FetchError {
+    /// The node didn't respond in time.
+    #[error("Request timed out")]
+    Timeout,
+    /// The node responded with an unsuccessful status.
+    #[error("Node responded with {status}: {body}")]
+    Http { status: u16, body: String },
+    /// The body of the request could not be serialized or the response could not be parsed.
+    #[error("Invalid JSON: {}", .0)]
+    Parse(String),
+    /// The node could not be reached.
+    #[error("Network error: {}", .0)]
+    Network(String),
+}
+type Result<T> = std::result::Result<T, FetchError>;
+
+impl From<gloo_net::Error> for FetchError {
+    fn from(e: gloo_net::Error) -> Self {
+        match e {
+            gloo_net::Error::SerdeError(e) => Self::Parse(e.to_string()),
+            e => Self::Network(e.to_string()),
+        }
+    }
+}
+
+impl FetchError {
+    /// Get the error of a response with the status, if the status is unsuccessful.
+    ///
+    /// # Arguments
+    ///
+    /// - `status` - The status of the response.
+    /// - `body` - The body of the response, which describes the error.
+    fn from_status(status: u16, body: &str) -> Option<Self> {
+        (!(200..300).contains(&status)).then(|| Self::Http {
+            status,
+            body: body.trim().to_owned(),
+        })
+    }
+}
+
+/// How long a request may take, before it's given up on.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client of the API of a single node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiClient {
+    /// The URL of the node, without a trailing slash.
+    base_url: String,
+    /// How long a request may take.
+    timeout: Duration,
+}
+
+impl ApiClient {
+    /// Create a client of the node at the URL.
+    /// The voters paste the URL of the node, so the URL is trimmed and is assumed to use HTTPS
+    /// if it has no scheme.
+    ///
+    /// # Arguments
+    ///
+    /// - `base_url` - The URL of the node.
+    pub fn new(base_url: &str) -> Self {
+        let base_url = base_url.trim().trim_end_matches('/');
+        let base_url = if base_url.contains("://") {
+            base_url.to_owned()
+        } else {
+            format!("https://{base_url}")
+        };
+
+        Self {
+            base_url,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Build the URL of an endpoint of the node.
+    ///
+    /// # Arguments
+    ///
+    /// - `segments` - The segments of the path of the endpoint, which are percent encoded, so
+    ///   that names of elections and ids can't change the path.
+    pub fn url(&self, segments: &[&str]) -> String {
+        let mut url = self.base_url.clone();
+        for segment in segments {
+            url.push('/');
+            url.push_str(&encode_segment(segment));
+        }
+        url
+    }
+
+    /// Get the JSON response of an endpoint.
+    ///
+    /// # Errors
+    ///
+    /// If the request fails, times out or doesn't succeed or if the response can't be parsed.
+    pub async fn get_json<T: DeserializeOwned>(&self, segments: &[&str]) -> Result<T> {
+        let response = self
+            .send(Request::get(&self.url(segments)).build()?)
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    /// Post the body as JSON to an endpoint and get the JSON response.
+    ///
+    /// # Arguments
+    ///
+    /// - `segments` - The segments of the path of the endpoint, see `url`.
+    /// - `body` - The body of the request.
+    /// - `headers` - Additional headers of the request.
+    ///
+    /// # Errors
+    ///
+    /// If the request fails, times out or doesn't succeed or if the response can't be parsed.
+    pub async fn post_json<B: Serialize, T: DeserializeOwned>(
+        &self,
+        segments: &[&str],
+        body: &B,
+        headers: &[(&str, &str)],
+    ) -> Result<T> {
+        let request = headers.iter().fold(
+            Request::post(&self.url(segments)),
+            |request, (name, value)| request.header(name, value),
+        );
+        let response = self.send(request.json(body)?).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Send the request, failing if it times out or doesn't succeed.
+    async fn send(&self, request: Request) -> Result<Response> {
+        let timeout =
+            TimeoutFuture::new(u32::try_from(self.timeout.as_millis()).unwrap_or(u32::MAX));
+        let response = match select(Box::pin(request.send()), timeout).await {
+            Either::Left((response, _)) => response?,
+            Either::Right(((), _)) => return Err(FetchError::Timeout),
+        };
+        if !response.ok() {
+            let body = response.text().await.unwrap_or_default();
+            if let Some(e) = FetchError::from_status(response.status(), &body) {
+                return Err(e);
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// Percent encode everything in the segment apart from the unreserved characters of URLs.
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url() {
+        let client = ApiClient::new(" https://node.example.com/ ");
+        assert_eq!(
+            client.url(&["challenge"]),
+            "https://node.example.com/challenge"
+        );
+        assert_eq!(
+            client.url(&["vote", "Local election 2024/25"]),
+            "https://node.example.com/vote/Local%20election%202024%2F25"
+        );
+        assert_eq!(
+            client.url(&["inclusion", "Jūrmala", "a-b_c"]),
+            "https://node.example.com/inclusion/J%C5%ABrmala/a-b_c"
+        );
+
+        // Pasted URLs without a scheme default to HTTPS.
+        assert_eq!(
+            ApiClient::new("www.blockchain.com").url(&["challenge"]),
+            "https://www.blockchain.com/challenge"
+        );
+        assert_eq!(
+            ApiClient::new("http://localhost:8080//").url(&[]),
+            "http://localhost:8080"
+        );
+    }
+
+    #[test]
+    fn test_fetch_error() {
+        assert_eq!(FetchError::from_status(200, "{}"), None);
+        assert_eq!(FetchError::from_status(204, ""), None);
+        assert_eq!(
+            FetchError::from_status(409, "Access token already used\n"),
+            Some(FetchError::Http {
+                status: 409,
+                body: "Access token already used".to_owned()
+            })
+        );
+        assert!(matches!(
+            FetchError::from_status(503, ""),
+            Some(FetchError::Http { status: 503, .. })
+        ));
+
+        let parse = serde_json::from_str::<u32>("not json").unwrap_err();
+        assert!(matches!(
+            FetchError::from(gloo_net::Error::SerdeError(parse)),
+            FetchError::Parse(_)
+        ));
+        assert_eq!(
+            FetchError::from(gloo_net::Error::GlooError("Failed to fetch".to_owned())),
+            FetchError::Network("Failed to fetch".to_owned())
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! This file contains the cache of the election config published by the node, so that the
+//! candidates can be shown by their names. The config may change while the client is open,
+//! so it can be refreshed, but only a config matching its published hash replaces the cached one.
+
+use anyhow::{bail, Result};
+use crypto::signature::digital_sign;
+use protocol::{
+    candidate_id::CandidateId,
+    config::{Candidate, ElectionConfig, HashedConfig},
+};
+
+/// The last valid config received from the node.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigCache(Option<HashedConfig>);
+
+impl ConfigCache {
+    pub fn config(&self) -> Option<&ElectionConfig> {
+        self.0.as_ref().map(|hashed_config| &hashed_config.config)
+    }
+
+    /// Get the candidates of the cached config, or none if no config was received yet.
+    pub fn candidates(&self) -> &[Candidate] {
+        self.config()
+            .map_or(&[], |config| config.candidates.as_slice())
+    }
+
+    /// Get the name under which the candidate is shown, falling back to the id if the
+    /// candidate is not in the cached config.
+    pub fn candidate_name(&self, id: &CandidateId) -> String {
+        self.config()
+            .and_then(|config| config.validate_candidate(id).ok())
+            .map_or_else(
+                || format!("Candidate {id}"),
+                |candidate| candidate.name.clone(),
+            )
+    }
+
+    /// Check that the candidate can be voted for in the cached config, so that the voter
+    /// doesn't sign a vote which the nodes would reject.
+    ///
+    /// # Errors
+    ///
+    /// If no config was received yet or if the candidate is not in the config.
+    pub fn validate_candidate(&self, id: &CandidateId) -> Result<()> {
+        let Some(config) = self.config() else {
+            bail!("Election config is not loaded, refresh the candidates first");
+        };
+        config.validate_candidate(id)?;
+        Ok(())
+    }
+
+    /// Replace the cached config with the one returned by the `/config` endpoint of the node.
+    /// The cached config is kept if the new one is invalid, doesn't match its hash or isn't
+    /// signed by the root key of the election.
+    ///
+    /// # Arguments
+    ///
+    /// - `json` - The response of the `/config` endpoint.
+    /// - `root_pk` - The root key of the election, if the voter obtained it, in which case the
+    ///   config must be signed with it, so that a node can't alter the config.
+    ///
+    /// # Returns
+    ///
+    /// Whether the config differs from the cached one.
+    ///
+    /// # Errors
+    ///
+    /// If the response can't be parsed, if the hash of the config doesn't match the published
+    /// hash, if the signature is missing or invalid or if the config is invalid.
+    pub fn refresh(
+        &mut self,
+        json: &str,
+        root_pk: Option<&digital_sign::PublicKey>,
+    ) -> Result<bool> {
+        let hashed_config: HashedConfig = serde_json::from_str(json)?;
+        let config_hash = hashed_config.config.hash()?;
+        if config_hash != hashed_config.config_hash {
+            bail!(
+                "Config hash mismatch: published {}, but the config hashes to {config_hash}",
+                hashed_config.config_hash
+            );
+        }
+        if let Some(root_pk) = root_pk {
+            hashed_config.config.verify_signature(root_pk)?;
+        }
+        hashed_config.config.validate()?;
+
+        let changed = self
+            .0
+            .as_ref()
+            .is_none_or(|cached| cached.config_hash != hashed_config.config_hash);
+        self.0 = Some(hashed_config);
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use crypto::signature::blind_sign;
+
+    fn sign(json: &str, root_signer: &digital_sign::Signer) -> String {
+        let HashedConfig { mut config, .. } = serde_json::from_str(json).unwrap();
+        config.sign(root_signer).unwrap();
+        serde_json::to_string(&HashedConfig::new(config).unwrap()).unwrap()
+    }
+
+    pub(crate) fn hashed_config_json(candidate_names: &[&str]) -> (HashedConfig, String) {
+        let now = chrono::Utc::now();
+        let config = ElectionConfig {
+            name: "Test election".to_owned(),
+            start: now,
+            end: now + std::time::Duration::from_secs(60),
+            authority_key: blind_sign::BlindSigner::new()
+                .unwrap()
+                .get_public_key()
+                .unwrap(),
+            candidates: candidate_names
+                .iter()
+                .zip(0..)
+                .map(|(name, id)| Candidate {
+                    name: (*name).to_owned(),
+                    id: CandidateId::new(id),
+                })
+                .collect(),
+            allow_write_ins: false,
+            max_clock_skew_secs: 0,
+            allow_abstentions: false,
+            allow_missing_nonces: false,
+            signature: None,
+        };
+        let hashed_config = HashedConfig::new(config).unwrap();
+        let json = serde_json::to_string(&hashed_config).unwrap();
+        (hashed_config, json)
+    }
+
+    #[test]
+    fn test_config_cache() {
+        let mut cache = ConfigCache::default();
+        assert!(cache.candidates().is_empty());
+        assert_eq!(cache.candidate_name(&CandidateId::new(0)), "Candidate 0");
+        assert!(cache.validate_candidate(&CandidateId::new(0)).is_err());
+
+        let (_, json) = hashed_config_json(&["Alice", "Bob"]);
+        assert!(cache.refresh(&json, None).unwrap());
+        assert!(!cache.refresh(&json, None).unwrap());
+        assert_eq!(cache.candidates().len(), 2);
+        assert_eq!(cache.candidate_name(&CandidateId::new(1)), "Bob");
+        cache.validate_candidate(&CandidateId::new(1)).unwrap();
+        assert!(cache.validate_candidate(&CandidateId::new(2)).is_err());
+
+        // The names changed mid-session.
+        let (_, json) = hashed_config_json(&["Alice", "Carol"]);
+        assert!(cache.refresh(&json, None).unwrap());
+        assert_eq!(cache.candidate_name(&CandidateId::new(1)), "Carol");
+
+        // A config altered after it was hashed is rejected and the cached one is kept.
+        let (mut hashed_config, _) = hashed_config_json(&["Alice", "Dave"]);
+        hashed_config.config.candidates[1].name = "Mallory".to_owned();
+        let json = serde_json::to_string(&hashed_config).unwrap();
+        assert!(cache
+            .refresh(&json, None)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Config hash mismatch"));
+        assert_eq!(cache.candidate_name(&CandidateId::new(1)), "Carol");
+
+        assert!(cache.refresh("garbage", None).is_err());
+        assert_eq!(cache.candidates().len(), 2);
+    }
+
+    #[test]
+    fn test_signed_config() {
+        let root_signer = digital_sign::Signer::new().unwrap();
+        let root_pk = root_signer.get_public_key();
+        let mut cache = ConfigCache::default();
+
+        // Unsigned configs are rejected once the voter has the root key.
+        let (_, json) = hashed_config_json(&["Alice", "Bob"]);
+        assert!(cache.refresh(&json, Some(&root_pk)).is_err());
+        assert!(cache.config().is_none());
+
+        let signed = sign(&json, &root_signer);
+        assert!(cache.refresh(&signed, Some(&root_pk)).unwrap());
+        assert_eq!(cache.candidate_name(&CandidateId::new(1)), "Bob");
+
+        // A node altering the config has to rehash it, but can't sign it.
+        let HashedConfig { mut config, .. } = serde_json::from_str(&signed).unwrap();
+        config.candidates[1].name = "Mallory".to_owned();
+        let tampered = serde_json::to_string(&HashedConfig::new(config).unwrap()).unwrap();
+        assert!(cache
+            .refresh(&tampered, Some(&root_pk))
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid config signature"));
+        assert_eq!(cache.candidate_name(&CandidateId::new(1)), "Bob");
+    }
+}
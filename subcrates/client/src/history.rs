@@ -0,0 +1,140 @@
+//! This file contains the local history of the voter's own activity, so that the voter can see
+//! what they have done on which blockchain. The history is stored encrypted together with the
+//! keys of the voter and never leaves the client.
+
+use leptos::{component, expect_context, view, CollectView, IntoView, SignalWith};
+use protocol::{candidate_id::CandidateId, timestamp::Timestamp};
+use serde::{Deserialize, Serialize};
+
+use crate::state::State;
+
+/// Something the voter has done.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum HistoryEvent {
+    /// The voter had registered in the client.
+    Registered,
+    /// The voter had received an access token from the election authority.
+    Validated,
+    /// The voter had voted for the candidate.
+    Voted(CandidateId),
+    /// The node had refused the vote for the reason.
+    VoteRejected(String),
+}
+
+/// A single entry of the history.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    /// When the event happened.
+    pub timestamp: Timestamp,
+    /// The blockchain to which the event relates, if any.
+    pub blockchain: Option<String>,
+    /// What happened.
+    pub event: HistoryEvent,
+}
+
+impl std::fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.timestamp.format("%Y-%m-%d %H:%M:%S UTC"))?;
+        match &self.event {
+            HistoryEvent::Registered => write!(f, "Registered")?,
+            HistoryEvent::Validated => write!(f, "Received access token")?,
+            HistoryEvent::Voted(candidate) => write!(f, "Voted for candidate {candidate}")?,
+            HistoryEvent::VoteRejected(reason) => write!(f, "Vote was rejected ({reason})")?,
+        }
+        if let Some(blockchain) = &self.blockchain {
+            write!(f, " on {blockchain}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The history of the voter, oldest entry first.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct History(Vec<HistoryEntry>);
+
+impl History {
+    /// Record an event which happened just now.
+    pub fn push(&mut self, event: HistoryEvent, blockchain: Option<String>) {
+        self.0.push(HistoryEntry {
+            timestamp: chrono::Utc::now(),
+            blockchain,
+            event,
+        });
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.0
+    }
+}
+
+#[must_use]
+#[component]
+pub fn HistoryView() -> impl IntoView {
+    let state = expect_context::<State>();
+
+    view! {
+        <h3>"History"</h3>
+        <ul>
+            {move || {
+                state
+                    .get_history()
+                    .with(|history| {
+                        history
+                            .entries()
+                            .iter()
+                            .rev()
+                            .map(|entry| view! { <li>{entry.to_string()}</li> })
+                            .collect_view()
+                    })
+            }}
+        </ul>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crypto::encryption::symmetric;
+
+    use crate::storage::KeyStore;
+
+    #[test]
+    fn test_history() {
+        let mut history = History::default();
+        history.push(HistoryEvent::Registered, None);
+        history.push(HistoryEvent::Validated, None);
+        history.push(
+            HistoryEvent::Voted(CandidateId::new(1)),
+            Some("www.blockchain.com".to_owned()),
+        );
+        history.push(
+            HistoryEvent::Voted(CandidateId::new(2)),
+            Some("www.other-blockchain.com".to_owned()),
+        );
+        assert_eq!(history.entries().len(), 4);
+        assert_eq!(history.entries()[0].event, HistoryEvent::Registered);
+        let entry = &history.entries()[2];
+        assert_eq!(entry.event, HistoryEvent::Voted(CandidateId::new(1)));
+        assert!(entry
+            .to_string()
+            .ends_with("Voted for candidate 1 on www.blockchain.com"));
+
+        // The history is stored encrypted together with the keys.
+        let encryption = symmetric::Encryption::new(b"Password").unwrap();
+        let storage = KeyStore {
+            signer_sk: None,
+            signer_pk: None,
+            authority_key: None,
+            unblinding_secret: None,
+            access_token: None,
+            candidate: None,
+            history: history.clone(),
+            outbox: crate::outbox::Outbox::default(),
+        }
+        .encrypt(&encryption)
+        .unwrap();
+        let encryption = symmetric::Encryption::load(b"Password", storage.get_metadata()).unwrap();
+        assert_eq!(storage.decrypt(&encryption).unwrap().history, history);
+    }
+}
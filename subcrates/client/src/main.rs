@@ -8,10 +8,15 @@ use leptos::{
     SignalWith,
 };
 
+mod api_client;
 mod authentication;
+mod config;
+mod history;
+mod outbox;
 mod settings;
 mod state;
 mod storage;
+mod submission;
 mod utils;
 mod validation;
 mod vote;
@@ -51,6 +56,8 @@ pub fn App() -> impl IntoView {
         </Show>
         <Show when=move || status.with(|status| { *status > Status::LoggedOut }) fallback=|| ()>
             <settings::SettingsPanel />
+            <history::HistoryView />
+            <outbox::OutboxView />
         </Show>
         <Show when=move || status.with(|status| matches!(status, Status::LoggedIn)) fallback=|| ()>
             <validation::ValidateVoter />
@@ -0,0 +1,230 @@
+//! This file contains the outbox of the signed votes which still have to be submitted to the
+//! blockchain, so that a vote isn't lost if the voter loses connectivity while submitting it.
+//! Queuing the votes is safe, since they are already signed and can't be altered, and the
+//! outbox is stored encrypted together with the keys of the voter.
+
+use leptos::{
+    component, ev, expect_context, spawn_local, view, window_event_listener, CollectView, IntoView,
+    Show, SignalWith,
+};
+use protocol::vote::{Nonce, Vote};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::state::State;
+use crate::submission::retry_outbox;
+
+/// Why submitting a pending vote failed.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum SubmitError {
+    /// The node could not be reached or could not accept the vote at the moment, so the vote
+    /// is kept to be submitted again later.
+    #[error("{}", .0)]
+    Unavailable(String),
+    /// The node refused the vote, so submitting it again would only get it refused again.
+    #[error("Vote rejected: {}", .0)]
+    Rejected(String),
+}
+
+/// A signed vote waiting to be submitted.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingVote {
+    /// The signed vote.
+    pub vote: Vote,
+    /// The blockchain to which the vote must be submitted.
+    pub blockchain: String,
+    /// The election in which the vote is cast.
+    pub election: String,
+    /// How many times submitting the vote had failed.
+    pub attempts: u32,
+    /// Why the last submission failed.
+    pub last_error: Option<String>,
+}
+
+impl std::fmt::Display for PendingVote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Vote for {} waiting to be sent to {}",
+            self.vote.get_choice(),
+            self.blockchain
+        )?;
+        if let Some(last_error) = &self.last_error {
+            write!(f, " ({} failed attempts: {last_error})", self.attempts)?;
+        }
+        Ok(())
+    }
+}
+
+/// The votes waiting to be submitted, oldest first.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Outbox(Vec<PendingVote>);
+
+impl Outbox {
+    /// Queue a signed vote for submission.
+    pub fn enqueue(&mut self, vote: Vote, blockchain: String, election: String) {
+        self.0.push(PendingVote {
+            vote,
+            blockchain,
+            election,
+            attempts: 0,
+            last_error: None,
+        });
+    }
+
+    pub fn pending(&self) -> &[PendingVote] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Record the outcome of submitting the pending vote with the id.
+    /// Accepted votes and votes refused by the node are removed, the votes which could not be
+    /// submitted stay in the outbox to be retried later.
+    ///
+    /// # Arguments
+    ///
+    /// - `vote_id` - The id of the submitted vote, see `Vote::id`.
+    /// - `outcome` - The outcome of the submission.
+    ///
+    /// # Returns
+    ///
+    /// The removed vote, if the vote was in the outbox and was removed.
+    pub fn record(
+        &mut self,
+        vote_id: &Nonce,
+        outcome: &Result<(), SubmitError>,
+    ) -> Option<PendingVote> {
+        let index = self
+            .0
+            .iter()
+            .position(|pending| pending.vote.id() == *vote_id)?;
+        match outcome {
+            Ok(()) | Err(SubmitError::Rejected(_)) => Some(self.0.remove(index)),
+            Err(e @ SubmitError::Unavailable(_)) => {
+                let pending = &mut self.0[index];
+                pending.attempts += 1;
+                pending.last_error = Some(e.to_string());
+                None
+            }
+        }
+    }
+}
+
+#[must_use]
+#[component]
+pub fn OutboxView() -> impl IntoView {
+    let state = expect_context::<State>();
+    let outbox = state.get_outbox();
+
+    // The handle is dropped without removing the listener, so that it lasts as long as the page.
+    let retry_state = state.clone();
+    let _ = window_event_listener(ev::online, move |_| {
+        spawn_local(retry_outbox(retry_state.clone()));
+    });
+    let on_retry = move |_| spawn_local(retry_outbox(state.clone()));
+
+    view! {
+        <Show when=move || outbox.with(|outbox| !outbox.is_empty()) fallback=|| ()>
+            <h3>"Outbox"</h3>
+            <button on:click=on_retry.clone()>"Retry now"</button>
+            <ul>
+                {move || {
+                    outbox
+                        .with(|outbox| {
+                            outbox
+                                .pending()
+                                .iter()
+                                .map(|pending| view! { <li>{pending.to_string()}</li> })
+                                .collect_view()
+                        })
+                }}
+            </ul>
+        </Show>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crypto::{encryption::symmetric, signature::blind_sign};
+    use protocol::{candidate_id::CandidateId, test_utils::make_valid_vote};
+
+    use crate::storage::KeyStore;
+
+    #[test]
+    fn test_outbox() {
+        let authority = blind_sign::BlindSigner::new().unwrap();
+        let mut outbox = Outbox::default();
+        outbox.enqueue(
+            make_valid_vote(&authority, CandidateId::new(1)),
+            "www.blockchain.com".to_owned(),
+            "Test election".to_owned(),
+        );
+        outbox.enqueue(
+            make_valid_vote(&authority, CandidateId::new(2)),
+            "www.other-blockchain.com".to_owned(),
+            "Test election".to_owned(),
+        );
+        outbox.enqueue(
+            make_valid_vote(&authority, CandidateId::new(3)),
+            "www.blockchain.com".to_owned(),
+            "Test election".to_owned(),
+        );
+        let ids: Vec<_> = outbox
+            .pending()
+            .iter()
+            .map(|pending| pending.vote.id())
+            .collect();
+
+        // Offline, so nothing gets submitted.
+        for id in &ids {
+            let offline = Err(SubmitError::Unavailable(
+                "Network is unreachable".to_owned(),
+            ));
+            assert!(outbox.record(id, &offline).is_none());
+        }
+        assert_eq!(outbox.pending().len(), 3);
+        assert_eq!(outbox.pending()[0].attempts, 1);
+        assert!(outbox.pending()[0]
+            .to_string()
+            .ends_with("(1 failed attempts: Network is unreachable)"));
+
+        // The outbox survives being stored encrypted together with the keys.
+        let encryption = symmetric::Encryption::new(b"Password").unwrap();
+        let storage = KeyStore {
+            signer_sk: None,
+            signer_pk: None,
+            authority_key: None,
+            unblinding_secret: None,
+            access_token: None,
+            candidate: None,
+            history: crate::history::History::default(),
+            outbox,
+        }
+        .encrypt(&encryption)
+        .unwrap();
+        let encryption = symmetric::Encryption::load(b"Password", storage.get_metadata()).unwrap();
+        let mut outbox = storage.decrypt(&encryption).unwrap().outbox;
+        assert_eq!(outbox.pending().len(), 3);
+
+        // After reconnecting, one vote is accepted, one is refused and one node is still down.
+        let accepted = outbox.record(&ids[0], &Ok(())).unwrap();
+        assert_eq!(*accepted.vote.get_choice(), CandidateId::new(1).into());
+        let rejected = Err(SubmitError::Rejected("Access token reused".to_owned()));
+        assert!(outbox.record(&ids[2], &rejected).is_some());
+        let refused = Err(SubmitError::Unavailable("Connection refused".to_owned()));
+        assert!(outbox.record(&ids[1], &refused).is_none());
+        assert_eq!(outbox.pending().len(), 1);
+        assert_eq!(outbox.pending()[0].blockchain, "www.other-blockchain.com");
+        assert_eq!(outbox.pending()[0].attempts, 2);
+
+        // Outcomes of votes which are no longer in the outbox are ignored.
+        assert!(outbox.record(&ids[0], &Ok(())).is_none());
+        assert!(outbox.record(&ids[1], &Ok(())).is_some());
+        assert!(outbox.is_empty());
+    }
+}
@@ -5,14 +5,21 @@
 //! Reactive leptos slices were'nt used because they clone struct members on each read and that is an
 //! issue since some State struct members are keys which can be relatively large.
 
+use crate::config::ConfigCache;
+use crate::history::{History, HistoryEvent};
+use crate::outbox::{Outbox, PendingVote, SubmitError};
 use crate::storage::{KeyStore, Storage};
 use anyhow::{anyhow, bail, Result};
 use crypto::{
     encryption::symmetric,
     signature::{blind_sign, digital_sign},
 };
-use leptos::{with, RwSignal, SignalSet, SignalWith};
-use protocol::{self, candidate_id::CandidateId, vote::Vote};
+use leptos::{with, RwSignal, SignalSet, SignalUpdate, SignalWith};
+use protocol::{
+    self,
+    candidate_id::CandidateId,
+    vote::{Nonce, Vote},
+};
 
 // TODO Add proper documentation when the client's logic is more stable.
 // TODO Figure out how to display user friendly errors.
@@ -43,12 +50,17 @@ pub enum Status {
 pub struct State {
     username: RwSignal<Option<String>>,
     encryption: RwSignal<Option<symmetric::Encryption>>,
-    signer: RwSignal<Option<digital_sign::Signer>>,
+    /// Sealed, so that the secret key is decrypted in the WASM heap only while signing.
+    signer: RwSignal<Option<digital_sign::SealedSigner>>,
     authority_key: RwSignal<Option<blind_sign::PublicKey>>,
     blinded_pk: RwSignal<Option<blind_sign::BlindedMessage>>,
     unblinder: RwSignal<Option<blind_sign::Unblinder>>,
     access_token: RwSignal<Option<blind_sign::Signature>>,
     candidate: RwSignal<Option<CandidateId>>,
+    history: RwSignal<History>,
+    outbox: RwSignal<Outbox>,
+    /// Not stored with the keys, since the config is public and is refreshed from the node.
+    config: RwSignal<ConfigCache>,
 }
 
 impl State {
@@ -62,20 +74,27 @@ impl State {
         }
         let encryption = symmetric::Encryption::new(password.as_bytes())?;
         let signer = digital_sign::Signer::new()?;
+        let mut history = History::default();
+        history.push(HistoryEvent::Registered, None);
 
         KeyStore {
             signer_sk: Some(signer.get_secret_key().to_owned()),
+            signer_pk: Some(signer.get_public_key()),
             authority_key: None,
             unblinding_secret: None,
             access_token: None,
             candidate: None,
+            history: history.clone(),
+            outbox: Outbox::default(),
         }
         .encrypt(&encryption)?
         .save(username);
 
         self.username.set(Some(username.to_owned()));
         self.encryption.set(Some(encryption));
-        self.signer.set(Some(signer));
+        self.signer
+            .set(Some(digital_sign::SealedSigner::new(signer)?));
+        self.history.set(history);
 
         Ok(())
     }
@@ -85,17 +104,26 @@ impl State {
         let encryption = symmetric::Encryption::load(password.as_bytes(), storage.get_metadata())?;
         let key_store = storage.decrypt(&encryption)?;
         let signer = if let Some(signer_sk) = key_store.signer_sk {
-            Some(digital_sign::Signer::from_secret_key(signer_sk)?)
+            let signer = match &key_store.signer_pk {
+                Some(signer_pk) => {
+                    digital_sign::Signer::from_secret_key_checked(signer_sk, signer_pk)?
+                }
+                None => digital_sign::Signer::from_secret_key(signer_sk)?,
+            };
+            Some(digital_sign::SealedSigner::new(signer)?)
         } else {
             None
         };
         let unblinder = if let (Some(authority_key), Some(unblinding_secret)) =
             (&key_store.authority_key, key_store.unblinding_secret)
         {
-            Some(blind_sign::Unblinder::from_pk_and_secret(
-                authority_key.to_owned(),
-                unblinding_secret,
-            )?)
+            Some(
+                blind_sign::StoredUnblinder {
+                    pk: authority_key.to_owned(),
+                    secret: unblinding_secret,
+                }
+                .to_unblinder()?,
+            )
         } else {
             None
         };
@@ -110,6 +138,8 @@ impl State {
         self.unblinder.set(unblinder);
         self.access_token.set(key_store.access_token);
         self.candidate.set(key_store.candidate);
+        self.history.set(key_store.history);
+        self.outbox.set(key_store.outbox);
 
         Ok(())
     }
@@ -123,6 +153,8 @@ impl State {
         self.unblinder.set(None);
         self.access_token.set(None);
         self.candidate.set(None);
+        self.history.set(History::default());
+        self.outbox.set(Outbox::default());
     }
 
     // TODO Ensure that keys cannot be read from garbage after user had logged out.
@@ -151,7 +183,11 @@ impl State {
     pub fn blind(&mut self, authority_key: blind_sign::PublicKey) -> Result<()> {
         let signer_pub_key = self
             .signer
-            .with(|signer| signer.as_ref().map(digital_sign::Signer::get_public_key))
+            .with(|signer| {
+                signer
+                    .as_ref()
+                    .map(|signer| signer.get_public_key().clone())
+            })
             .ok_or(anyhow!("User is not logged in"))?;
         let blinder = blind_sign::Blinder::new(authority_key.clone())?;
         let (blinded_pk, unblinder) = blinder.blind(&signer_pub_key)?;
@@ -167,6 +203,59 @@ impl State {
         self.blinded_pk
     }
 
+    pub fn get_history(&self) -> RwSignal<History> {
+        self.history
+    }
+
+    pub fn get_outbox(&self) -> RwSignal<Outbox> {
+        self.outbox
+    }
+
+    pub fn get_config(&self) -> RwSignal<ConfigCache> {
+        self.config
+    }
+
+    /// Replace the cached election config, see `ConfigCache::refresh`.
+    pub fn refresh_config(
+        &mut self,
+        json: &str,
+        root_pk: Option<&digital_sign::PublicKey>,
+    ) -> Result<bool> {
+        let mut refreshed = Ok(false);
+        self.config
+            .update(|config| refreshed = config.refresh(json, root_pk));
+
+        refreshed
+    }
+
+    /// The votes waiting in the outbox, oldest first.
+    pub fn pending_votes(&self) -> Vec<PendingVote> {
+        self.outbox.with(|outbox| outbox.pending().to_vec())
+    }
+
+    /// Record the outcome of submitting a vote from the outbox, see `Outbox::record`.
+    /// Votes refused by the node are recorded in the history with the reason.
+    pub fn record_submission(
+        &mut self,
+        vote_id: &Nonce,
+        outcome: &std::result::Result<(), SubmitError>,
+    ) -> Result<()> {
+        let mut removed = None;
+        self.outbox
+            .update(|outbox| removed = outbox.record(vote_id, outcome));
+        if let (Some(removed), Err(SubmitError::Rejected(reason))) = (removed, outcome) {
+            self.history.update(|history| {
+                history.push(
+                    HistoryEvent::VoteRejected(reason.clone()),
+                    Some(removed.blockchain),
+                );
+            });
+        }
+        self.save()?;
+
+        Ok(())
+    }
+
     pub fn unblind(&mut self, blind_signature: blind_sign::BlindSignature) -> Result<()> {
         let Self {
             signer, unblinder, ..
@@ -175,19 +264,23 @@ impl State {
         let access_token = with!(|signer, unblinder| {
             match (signer, unblinder) {
                 (Some(signer), Some(unblinder)) => unblinder
-                    .unblind_signature(blind_signature, &signer.get_public_key())
+                    .unblind_signature(blind_signature, signer.get_public_key())
                     .map_err(std::convert::Into::into),
                 _ => Err(anyhow!("State is corrupted")),
             }
         })?;
         self.access_token.set(Some(access_token));
+        self.history
+            .update(|history| history.push(HistoryEvent::Validated, None));
         self.save()?;
 
         Ok(())
     }
 
-    pub fn vote(&mut self, candidate: &str, _blockchain_addr: &str) -> Result<()> {
+    pub fn vote(&mut self, candidate: &str, blockchain_addr: &str) -> Result<()> {
         let candidate = CandidateId::new(candidate.parse()?);
+        self.config
+            .with(|config| config.validate_candidate(&candidate))?;
         let Self {
             signer,
             access_token,
@@ -195,17 +288,32 @@ impl State {
         } = self;
         apply_read_only!(signer, access_token);
 
-        let _vote = with!(|signer, access_token| {
+        let vote = with!(|signer, access_token| {
             match (signer, access_token) {
-                (Some(signer), Some(access_token)) => {
-                    Vote::new(signer, candidate.clone(), chrono::Utc::now(), access_token)
-                        .map_err(std::convert::Into::into)
-                }
+                (Some(signer), Some(access_token)) => signer
+                    .with_signer(|signer| {
+                        Vote::new(signer, candidate.clone(), chrono::Utc::now(), access_token)
+                    })
+                    .map_err(anyhow::Error::from)
+                    .and_then(|vote| vote.map_err(anyhow::Error::from)),
                 _ => Err(anyhow!("State is corrupted")),
             }
         })?;
 
+        let election = self
+            .config
+            .with(|config| config.config().map(|config| config.name.clone()))
+            .ok_or(anyhow!("Election config is not loaded"))?;
+
         self.candidate.set(Some(candidate.clone()));
+        self.outbox
+            .update(|outbox| outbox.enqueue(vote, blockchain_addr.to_owned(), election));
+        self.history.update(|history| {
+            history.push(
+                HistoryEvent::Voted(candidate),
+                Some(blockchain_addr.to_owned()),
+            );
+        });
         self.save()?;
 
         Ok(())
@@ -220,24 +328,47 @@ impl State {
             unblinder,
             access_token,
             candidate,
+            history,
+            outbox,
             ..
         } = self;
-        apply_read_only!(signer, authority_key, unblinder, access_token, candidate);
-        let key_store = with!(
-            |signer, authority_key, unblinder, access_token, candidate| {
-                KeyStore {
-                    signer_sk: signer
-                        .as_ref()
-                        .map(|signer| signer.get_secret_key().clone()),
-                    authority_key: authority_key.clone(),
-                    unblinding_secret: unblinder
-                        .as_ref()
-                        .map(|unblinder| unblinder.get_unblinding_secret().clone()),
-                    access_token: access_token.clone(),
-                    candidate: candidate.clone(),
-                }
-            }
+        // The secret key is in plain text only until the key store is encrypted below.
+        let signer_sk = signer.with(|signer| {
+            signer
+                .as_ref()
+                .map(digital_sign::SealedSigner::get_secret_key)
+                .transpose()
+        })?;
+        let signer_pk = signer.with(|signer| {
+            signer
+                .as_ref()
+                .map(|signer| signer.get_public_key().clone())
+        });
+        apply_read_only!(
+            authority_key,
+            unblinder,
+            access_token,
+            candidate,
+            history,
+            outbox
         );
+        let key_store =
+            with!(
+                |authority_key, unblinder, access_token, candidate, history, outbox| {
+                    KeyStore {
+                        signer_sk: signer_sk.clone(),
+                        signer_pk: signer_pk.clone(),
+                        authority_key: authority_key.clone(),
+                        unblinding_secret: unblinder
+                            .as_ref()
+                            .map(|unblinder| unblinder.get_unblinding_secret().clone()),
+                        access_token: access_token.clone(),
+                        candidate: candidate.clone(),
+                        history: history.clone(),
+                        outbox: outbox.clone(),
+                    }
+                }
+            );
         let storage = encryption
             .with(|encryption| {
                 encryption
@@ -259,6 +390,7 @@ impl State {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::tests::hashed_config_json;
     use leptos::SignalGet;
 
     fn logout_login(_state: State, username: &str, password: &str) -> State {
@@ -274,7 +406,7 @@ mod tests {
     fn test_state() {
         let username = "Admin";
         let password = "Password";
-        let candidate = "5";
+        let candidate = "1";
         let blockchain_addr = "www.blockchain.com";
 
         let authority_signer = blind_sign::BlindSigner::new().unwrap();
@@ -300,7 +432,56 @@ mod tests {
         let mut state = logout_login(state, username, password);
         assert!(matches!(state.get_status(), Status::Validated));
 
+        // Votes are only signed for the candidates of the cached config.
+        assert!(state.vote(candidate, blockchain_addr).is_err());
+        let (_, config) = hashed_config_json(&["Alice", "Bob"]);
+        state.refresh_config(&config, None).unwrap();
+        assert!(state.vote("2", blockchain_addr).is_err());
+        assert!(matches!(state.get_status(), Status::Validated));
         state.vote(candidate, blockchain_addr).unwrap();
         assert!(matches!(state.get_status(), Status::Voted));
+
+        let state = logout_login(state, username, password);
+        let history = state.get_history().get();
+        let events: Vec<_> = history
+            .entries()
+            .iter()
+            .map(|entry| entry.event.clone())
+            .collect();
+        assert_eq!(
+            events,
+            [
+                HistoryEvent::Registered,
+                HistoryEvent::Validated,
+                HistoryEvent::Voted(CandidateId::new(1))
+            ]
+        );
+        assert_eq!(
+            history.entries()[2].blockchain.as_deref(),
+            Some(blockchain_addr)
+        );
+
+        // The signed vote waits in the outbox until it's submitted, even across logins.
+        let mut state = state;
+        let pending = state.pending_votes();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].election, "Test election");
+        let vote_id = pending[0].vote.id();
+        let offline = Err(SubmitError::Unavailable(
+            "Network is unreachable".to_owned(),
+        ));
+        state.record_submission(&vote_id, &offline).unwrap();
+        let mut state = logout_login(state, username, password);
+        assert_eq!(state.pending_votes()[0].attempts, 1);
+
+        // A vote refused by the node is not retried, but the voter can see why it was refused.
+        let rejected = Err(SubmitError::Rejected("Access token reused".to_owned()));
+        state.record_submission(&vote_id, &rejected).unwrap();
+        let state = logout_login(state, username, password);
+        assert!(state.get_outbox().get().is_empty());
+        assert_eq!(
+            state.get_history().get().entries()[3].event,
+            HistoryEvent::VoteRejected("Access token reused".to_owned())
+        );
     }
 }
@@ -10,15 +10,28 @@ use leptos::{SignalGet, SignalSet};
 use leptos_use::storage::use_local_storage;
 use protocol::candidate_id::CandidateId;
 
+use crate::history::History;
+use crate::outbox::Outbox;
+
 // TODO Add documentation.
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct KeyStore {
     pub signer_sk: Option<digital_sign::SecretKey>,
+    /// The public key of `signer_sk`, to detect a corrupted or swapped secret key on login.
+    /// Defaulted, so that the key stores saved before it was added can be loaded.
+    #[serde(default)]
+    pub signer_pk: Option<digital_sign::PublicKey>,
     pub authority_key: Option<blind_sign::PublicKey>,
     pub unblinding_secret: Option<blind_sign::UnblindingSecret>,
     pub access_token: Option<blind_sign::Signature>,
     pub candidate: Option<CandidateId>,
+    /// Defaulted, so that the key stores saved before the history was added can be loaded.
+    #[serde(default)]
+    pub history: History,
+    /// Signed votes which still have to be submitted, defaulted for the same reason.
+    #[serde(default)]
+    pub outbox: Outbox,
 }
 
 impl KeyStore {
@@ -0,0 +1,154 @@
+//! This file contains the submission of the votes waiting in the outbox to the nodes.
+//! A vote is submitted by solving the proof of work challenge of the node and posting the vote
+//! in the API shape, see `protocol::vote::ApiVote`.
+
+use std::cell::Cell;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL, Engine};
+use protocol::{
+    proof_of_work::{Challenge, SOLUTION_HEADER},
+    vote::ApiVote,
+};
+use serde::de::IgnoredAny;
+
+use crate::api_client::{ApiClient, FetchError};
+use crate::outbox::{PendingVote, SubmitError};
+use crate::state::State;
+
+/// Status with which the nodes answer a failed proof of work, for example when the challenge
+/// expired while it was being solved.
+const PROOF_OF_WORK_FAILED: u16 = 429;
+
+/// How many challenges are solved for a vote, before it's left for the next retry.
+const PROOF_OF_WORK_ATTEMPTS: usize = 2;
+
+thread_local! {
+    /// Whether the outbox is being submitted, so that a vote isn't submitted twice at once.
+    static RETRYING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Try to submit every vote waiting in the outbox and record the outcomes in the state.
+/// Does nothing if the outbox is already being submitted.
+///
+/// # Arguments
+///
+/// - `state` - The state of the client holding the outbox.
+pub async fn retry_outbox(mut state: State) {
+    if RETRYING.replace(true) {
+        return;
+    }
+    for pending in state.pending_votes() {
+        let outcome = submit(&pending).await;
+        if let Err(e) = state.record_submission(&pending.vote.id(), &outcome) {
+            tracing::error!("Failed to record the submission of a vote: {e}");
+        }
+    }
+    RETRYING.set(false);
+}
+
+/// Submit the pending vote to its node.
+///
+/// # Errors
+///
+/// `SubmitError::Rejected` if the node refused the vote and `SubmitError::Unavailable` if the
+/// vote could not be submitted for any other reason.
+async fn submit(pending: &PendingVote) -> Result<(), SubmitError> {
+    let client = ApiClient::new(&pending.blockchain);
+    let mut attempts = 1;
+    let e = loop {
+        match post_vote(&client, pending).await {
+            Ok(()) => return Ok(()),
+            Err(FetchError::Http {
+                status: PROOF_OF_WORK_FAILED,
+                ..
+            }) if attempts < PROOF_OF_WORK_ATTEMPTS => attempts += 1,
+            Err(e) => break e,
+        }
+    };
+    match classify(e) {
+        // The vote may have been accepted by an earlier attempt whose response was lost, in
+        // which case the node refuses it as a replay.
+        SubmitError::Rejected(_) if is_included(&client, pending).await => Ok(()),
+        e => Err(e),
+    }
+}
+
+/// Solve a new challenge of the node and post the vote with the solution.
+async fn post_vote(client: &ApiClient, pending: &PendingVote) -> Result<(), FetchError> {
+    let challenge: Challenge = client.get_json(&["challenge"]).await?;
+    client
+        .post_json::<_, IgnoredAny>(
+            &["vote", &pending.election],
+            &ApiVote::from(&pending.vote),
+            &[(SOLUTION_HEADER, &challenge.solve())],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Tell apart the votes which the node refused, which would only be refused again, from the
+/// votes which could not be submitted at the moment.
+fn classify(e: FetchError) -> SubmitError {
+    match e {
+        FetchError::Http {
+            status: 400 | 403 | 404,
+            body,
+        } => SubmitError::Rejected(body),
+        e => SubmitError::Unavailable(e.to_string()),
+    }
+}
+
+/// Check whether the node can prove that the pending vote is included in its blockchain.
+async fn is_included(client: &ApiClient, pending: &PendingVote) -> bool {
+    let vote_id = BASE64_URL.encode(pending.vote.id());
+    client
+        .get_json::<IgnoredAny>(&["inclusion", &pending.election, &vote_id])
+        .await
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let http = |status: u16, body: &str| FetchError::Http {
+            status,
+            body: body.to_owned(),
+        };
+
+        assert_eq!(
+            classify(http(400, "Access token reused")),
+            SubmitError::Rejected("Access token reused".to_owned())
+        );
+        assert!(matches!(
+            classify(http(403, "Election ended")),
+            SubmitError::Rejected(_)
+        ));
+        assert!(matches!(
+            classify(http(404, "Unknown election")),
+            SubmitError::Rejected(_)
+        ));
+
+        // A failed proof of work, for example with an expired challenge, keeps the vote.
+        assert!(matches!(
+            classify(http(
+                PROOF_OF_WORK_FAILED,
+                "Proof of work challenge is unknown or expired"
+            )),
+            SubmitError::Unavailable(_)
+        ));
+        for e in [
+            http(425, "Election has not started"),
+            http(500, ""),
+            http(503, "Too many outstanding proof of work challenges"),
+            FetchError::Timeout,
+            FetchError::Network("Failed to fetch".to_owned()),
+            FetchError::Parse("expected value".to_owned()),
+        ] {
+            assert!(matches!(classify(e), SubmitError::Unavailable(_)));
+        }
+    }
+}
@@ -1,11 +1,16 @@
 //! This file contains the logic for casting an actual vote.
 
+use std::str::FromStr;
+
+use crypto::signature::digital_sign;
+
 use leptos::{
-    component, create_node_ref, create_signal, event_target_value, expect_context, html, view,
-    IntoView, NodeRef, Show, SignalGet, SignalSet,
+    component, create_node_ref, create_signal, event_target_value, expect_context, html,
+    spawn_local, view, CollectView, IntoView, NodeRef, Show, SignalGet, SignalSet, SignalWith,
 };
 
 use crate::state::State;
+use crate::submission::retry_outbox;
 
 #[component]
 pub fn Cast() -> impl IntoView {
@@ -30,16 +35,16 @@ pub fn Cast() -> impl IntoView {
         }
         let selected_candidate = candidate.get();
         match selected_candidate {
-            Some(selected_candidate) => {
-                if let Err(e) = state.vote(&selected_candidate, &blockchain_addr) {
-                    set_error.set(Some(format!("Failed to vote: {e}")));
-                }
-            }
+            Some(selected_candidate) => match state.vote(&selected_candidate, &blockchain_addr) {
+                Ok(()) => spawn_local(retry_outbox(state.clone())),
+                Err(e) => set_error.set(Some(format!("Failed to vote: {e}"))),
+            },
             None => set_error.set(Some("Candidate not selected".to_owned())),
         }
     };
 
     view! {
+        <ConfigRefresh />
         <label>
             <form on:submit=on_submit>
                 <label>
@@ -72,10 +77,91 @@ pub fn Cast() -> impl IntoView {
 
 #[component]
 fn Config() -> impl IntoView {
-    // TODO For now candidate config is hardcoded:
+    let state = expect_context::<State>();
+
+    move || {
+        state.get_config().with(|config| {
+            if config.candidates().is_empty() {
+                // Votes can only be cast for the candidates of the cached config.
+                return view! {
+                    <option value="" disabled=true>"Refresh the candidates first"</option>
+                }
+                .into_view();
+            }
+            config
+                .candidates()
+                .iter()
+                .map(|candidate| {
+                    view! {
+                        <option value=candidate.id.to_string()>
+                            {config.candidate_name(&candidate.id)}
+                        </option>
+                    }
+                })
+                .collect_view()
+        })
+    }
+}
+
+/// Control for refreshing the cached election config, so that the candidate names don't go stale.
+#[component]
+fn ConfigRefresh() -> impl IntoView {
+    let mut state = expect_context::<State>();
+    let (get_status, set_status) = create_signal(None);
+
+    let config_ref: NodeRef<html::Textarea> = create_node_ref();
+    let root_pk_ref: NodeRef<html::Input> = create_node_ref();
+    let on_submit = move |ev: leptos::ev::SubmitEvent| {
+        ev.prevent_default();
+        let config = config_ref
+            .get()
+            .expect("Election config input should be mounted")
+            .value();
+        let root_pk = root_pk_ref
+            .get()
+            .expect("Election root public key input should be mounted")
+            .value();
+        let root_pk = if root_pk.is_empty() {
+            None
+        } else {
+            match digital_sign::PublicKey::from_str(&root_pk) {
+                Ok(root_pk) => Some(root_pk),
+                Err(e) => {
+                    set_status.set(Some(format!("Invalid election root public key: {e}")));
+                    return;
+                }
+            }
+        };
+
+        // TODO Fetch the config from the node directly, once the client can talk to the nodes.
+        set_status.set(Some(
+            match state.refresh_config(&config, root_pk.as_ref()) {
+                Ok(true) => "Candidates updated".to_owned(),
+                Ok(false) => "Candidates are up to date".to_owned(),
+                Err(e) => format!("Failed to refresh the candidates: {e}"),
+            },
+        ));
+    };
+
     view! {
-        <option value="0">"First Candidate"</option>
-        <option value="1">"Second Candidate"</option>
-        <option value="2">"Third Candidate"</option>
+        <form on:submit=on_submit>
+            <label>
+                "Paste the response of the /config endpoint of the node:"
+                <textarea node_ref=config_ref name="election_config"></textarea>
+            </label>
+            <label>
+                "Enter the root public key of the election to check the signature of the config:"
+                <input
+                    type="text"
+                    node_ref=root_pk_ref
+                    name="election_root_pk"
+                    placeholder="Paste the root public key of the election here"
+                />
+                <button type="submit">"Refresh candidates"</button>
+            </label>
+        </form>
+        <Show when=move || get_status.get().is_some() fallback=|| ()>
+            <p>{get_status.get().expect("Status to be some")}</p>
+        </Show>
     }
 }
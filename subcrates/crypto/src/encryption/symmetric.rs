@@ -1,5 +1,7 @@
 //! This is a wrapper module for symmetric encryption using AEAD.
 
+use std::{collections::HashSet, sync::Mutex};
+
 use ring::{
     aead, pbkdf2,
     rand::{SecureRandom, SystemRandom},
@@ -29,12 +31,19 @@ pub enum Error {
     /// Decryption failed with the provided username and password.
     #[error("Decryption failed")]
     Decryption,
+    /// The nonce was already used to encrypt a message in strict mode, see
+    /// `Encryption::with_strict_nonces`.
+    #[error("Nonce was reused")]
+    NonceReuse,
 }
 type Result<T> = std::result::Result<T, Error>;
 
 /// Length of the salt segment in bytes. Chosen because this is the usual recommended byte cound.
 const SALT_LEN: usize = 32;
 
+/// Length of the metadata stored alongside each encrypted message in bytes.
+pub const METADATA_LEN: usize = SALT_LEN + aead::NONCE_LEN;
+
 /// Newtype for unique SALT generated for each user and used for deriving salt for encryption key.
 struct Salt([u8; SALT_LEN]);
 
@@ -96,9 +105,7 @@ impl AsRef<[u8]> for Nonce {
 /// Newtype for metadata which is stored alongside the encrypted message.
 #[serde_as]
 #[derive(Serialize, Deserialize, Clone, PartialEq)]
-pub struct MetaData(
-    #[serde_as(as = "serde_with::base64::Base64")] [u8; SALT_LEN + aead::NONCE_LEN],
-);
+pub struct MetaData(#[serde_as(as = "serde_with::base64::Base64")] [u8; METADATA_LEN]);
 
 impl MetaData {
     /// Create new metadata containing SALT and nonce.
@@ -112,7 +119,7 @@ impl MetaData {
     ///
     /// New metadata.
     fn new(salt: &Salt, nonce: &Nonce) -> Self {
-        let mut buffer = [0u8; SALT_LEN + aead::NONCE_LEN];
+        let mut buffer = [0u8; METADATA_LEN];
         buffer[0..SALT_LEN].copy_from_slice(&salt.0);
         buffer[SALT_LEN..].copy_from_slice(&nonce.0);
 
@@ -123,13 +130,13 @@ impl MetaData {
     ///
     /// # Arguments
     ///
-    /// `bytes` - The bytes of size `SALT_LEN` + `NONCE_LEN` containing SALT and nonce.
+    /// `bytes` - The bytes of size `METADATA_LEN` containing SALT and nonce.
     ///
     /// # Returns
     ///
     /// New metadata.
     #[must_use]
-    pub fn from_bytes(bytes: [u8; SALT_LEN + aead::NONCE_LEN]) -> Self {
+    pub fn from_bytes(bytes: [u8; METADATA_LEN]) -> Self {
         Self(bytes)
     }
 
@@ -171,6 +178,9 @@ pub struct Encryption {
     /// The SALT specific to the user and required to encrypt and decrypt messages.
     /// Will also be stored alongside the encrypted message.
     salt: Salt,
+    /// The nonces used to encrypt messages so far, only tracked in strict mode.
+    /// Reusing a nonce with ChaCha20-Poly1305 leaks the key stream and allows forging messages.
+    seen_nonces: Option<Mutex<HashSet<[u8; aead::NONCE_LEN]>>>,
 }
 
 impl Encryption {
@@ -238,7 +248,24 @@ impl Encryption {
             .map_err(|_| Error::KeyDerive)?;
         let key = aead::LessSafeKey::new(key);
 
-        Ok(Self { key, salt })
+        Ok(Self {
+            key,
+            salt,
+            seen_nonces: None,
+        })
+    }
+
+    /// Enable the strict mode, in which every nonce used to encrypt a message is remembered and
+    /// encrypting with a nonce which was already used fails with `Error::NonceReuse`.
+    /// Meant for development, since the memory used grows with every encrypted message.
+    ///
+    /// # Returns
+    ///
+    /// The encryption state in strict mode.
+    #[must_use]
+    pub fn with_strict_nonces(mut self) -> Self {
+        self.seen_nonces = Some(Mutex::new(HashSet::new()));
+        self
     }
 
     /// Encrypt a message.
@@ -257,8 +284,35 @@ impl Encryption {
     ///
     /// If encryption fails.
     pub fn encrypt(&self, to_encrypt: &mut Vec<u8>) -> Result<MetaData> {
-        let nonce = Nonce::new()?;
-        let metadata = MetaData::new(&self.salt, &nonce);
+        self.encrypt_with_nonce(to_encrypt, &Nonce::new()?)
+    }
+
+    /// Encrypt a message with the provided nonce.
+    ///
+    /// # Arguments
+    ///
+    /// `to_encrypt` - The message to be encrypted.
+    /// `nonce` - The nonce, which must never be reused with the same key.
+    ///
+    /// # Returns
+    ///
+    /// The metadata for the encrypted message, which should be stored alongside the encrypted message.
+    ///
+    /// # Errors
+    ///
+    /// If encryption fails.
+    /// If the nonce was already used in strict mode.
+    fn encrypt_with_nonce(&self, to_encrypt: &mut Vec<u8>, nonce: &Nonce) -> Result<MetaData> {
+        if let Some(seen_nonces) = &self.seen_nonces {
+            // A poisoned lock still holds a consistent set, since inserting can't panic midway.
+            let mut seen_nonces = seen_nonces
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            if !seen_nonces.insert(nonce.0) {
+                return Err(Error::NonceReuse);
+            }
+        }
+        let metadata = MetaData::new(&self.salt, nonce);
         self.key
             .seal_in_place_append_tag(
                 aead::Nonce::assume_unique_for_key(nonce.0),
@@ -358,4 +412,36 @@ mod tests {
         assert!(decryption.decrypt(&mut buffer, &metadata).is_err());
         assert_ne!(buffer[..plaintext.len()], *plaintext);
     }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_strict_nonces() {
+        let password = b"Password";
+        let nonce = [7u8; aead::NONCE_LEN];
+
+        // Without strict mode nothing stops the caller from reusing the nonce.
+        let encryption = Encryption::new(password).unwrap();
+        encryption
+            .encrypt_with_nonce(&mut b"First".to_vec(), &Nonce(nonce))
+            .unwrap();
+        encryption
+            .encrypt_with_nonce(&mut b"Second".to_vec(), &Nonce(nonce))
+            .unwrap();
+
+        let encryption = Encryption::new(password).unwrap().with_strict_nonces();
+        let mut buffer = b"First".to_vec();
+        let metadata = encryption
+            .encrypt_with_nonce(&mut buffer, &Nonce(nonce))
+            .unwrap();
+        assert!(matches!(
+            encryption.encrypt_with_nonce(&mut b"Second".to_vec(), &Nonce(nonce)),
+            Err(Error::NonceReuse)
+        ));
+        // Fresh nonces and decryption are unaffected.
+        encryption.encrypt(&mut b"Third".to_vec()).unwrap();
+        assert_eq!(
+            encryption.decrypt(&mut buffer, &metadata).unwrap(),
+            b"First"
+        );
+    }
 }
@@ -1,6 +1,7 @@
 //! This is a custom implementation of a Merkle Tree, used in set membership ZKPs.
 //! Other crates were too over bloated and not flexible enough.
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Error type for Merkle Tree operations.
@@ -16,6 +17,9 @@ pub enum Error {
 type Result<T> = std::result::Result<T, Error>;
 
 /// A struct containing all the info for Merkle Proof for a leaf in a Merkle Tree.
+/// The proof is serializable, so that it could be sent from where the tree lives to where
+/// the inclusion is verified.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[allow(clippy::module_name_repetitions)]
 pub struct MerkleProof<H> {
     /// The index of the leaf for which the proof is generated.
@@ -33,6 +37,51 @@ pub struct MerkleProof<H> {
     pub path: Vec<MerkleHashPath>,
 }
 
+impl<H: PartialEq> MerkleProof<H> {
+    /// Verify that the leaf is included in the Merkle Tree with the root of this proof.
+    ///
+    /// # Arguments
+    ///
+    /// - `leaf_hash` - The hash of the leaf, calculated with the leaf hash function of the tree.
+    /// - `node_hash_function` - The function with which the tree hashes two nodes together.
+    ///
+    /// # Returns
+    ///
+    /// Whether hashing the leaf with the proof results in the root.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crypto::merkle::MerkleTree;
+    ///
+    /// fn mock_hash(preimages: [u64; 2]) -> u64 {
+    ///     preimages[0] ^ preimages[1]
+    /// }
+    ///
+    /// let leaves = vec![1u64, 2u64, 3u64];
+    /// let tree = MerkleTree::new(
+    ///     &leaves,
+    ///     Box::new(|a, b| mock_hash([*a, *b])),
+    ///     Box::new(|x| mock_hash([*x, *x])),
+    /// ).unwrap();
+    /// let proof = tree.get_proof(1).unwrap();
+    /// assert!(proof.verify(mock_hash([2, 2]), |a, b| mock_hash([*a, *b])));
+    /// ```
+    pub fn verify(&self, leaf_hash: H, node_hash_function: impl Fn(&H, &H) -> H) -> bool {
+        if self.proof.len() != self.path.len() {
+            return false;
+        }
+        let digest = self.proof.iter().zip(&self.path).fold(
+            leaf_hash,
+            |digest, (sibling, path)| match path {
+                MerkleHashPath::Right => node_hash_function(&digest, sibling),
+                MerkleHashPath::Left => node_hash_function(sibling, &digest),
+            },
+        );
+        digest == self.root
+    }
+}
+
 /// Alias to abstract away some complexity from the type of `MerkleTree` struct.
 /// This type accepts a function which takes two hash values and hashes them together.
 type NodeHashFn<H> = Box<dyn Fn(&H, &H) -> H>;
@@ -73,7 +122,7 @@ pub struct MerkleTree<T, H> {
 
 // TODO make sure this is according to standard.
 /// Enum representing the path to a hash in a Merkle Tree.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 #[allow(clippy::module_name_repetitions)]
 pub enum MerkleHashPath {
     /// Right (or false, or zero) means that the proof element's hash value is on the right side of the current hashing operation.
@@ -189,7 +238,7 @@ where
     /// Precalculate the number of nodes in the Merkle Tree so that the nodes vector can be preallocated
     /// with the correct capacity to avoid reallocations.
     fn precalc_node_count(leaf_count: usize) -> usize {
-        if leaf_count % 2 != 0 {
+        if !leaf_count.is_multiple_of(2) {
             (leaf_count + 1) * 2 - 1
         } else {
             leaf_count * 2 - 1
@@ -202,9 +251,10 @@ where
     ///
     /// The root of the Merkle Tree.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// If the Merkle Tree is empty, which should not happen.
+    /// If the Merkle Tree is empty, which can't happen, since `new` refuses to build empty trees.
+    /// Returning an error instead of panicking, so that a broken invariant can't crash the caller.
     ///
     /// # Example
     ///
@@ -221,11 +271,10 @@ where
     ///     Box::new(|a, b| mock_hash([*a, *b])),
     ///     Box::new(|x| mock_hash([*x, *x])),
     /// ).unwrap();
-    /// let root = tree.get_root();
+    /// let root = tree.get_root().unwrap();
     /// ```
-    #[must_use]
-    pub fn get_root(&self) -> H {
-        self.nodes.last().unwrap().clone()
+    pub fn get_root(&self) -> Result<H> {
+        self.nodes.last().cloned().ok_or(Error::EmptyTree)
     }
 
     /// Get the Merkle Proof for a leaf in the Merkle Tree.
@@ -273,7 +322,7 @@ where
         let mut current_level = 0;
         let mut current_index = leaf_index;
         while cap > 1 {
-            let sibling_index = if current_index % 2 == 0 {
+            let sibling_index = if current_index.is_multiple_of(2) {
                 // If the sibling is on the RIGHT side of the hash.
                 proof.path.push(MerkleHashPath::Right);
                 current_index + 1
@@ -328,6 +377,23 @@ mod tests {
         assert!(tree.is_err());
     }
 
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_merkle_tree_single_leaf() {
+        let leaves = vec![7u64];
+        let tree = MerkleTree::new(
+            &leaves,
+            Box::new(|a, b| mock_hash([*a, *b])),
+            Box::new(|x| mock_hash([*x, 1])),
+        )
+        .unwrap();
+        // The hash of the only leaf is the root.
+        assert_eq!(tree.get_root().unwrap(), mock_hash([7, 1]));
+        let proof = tree.get_proof(0).unwrap();
+        assert!(proof.proof.is_empty());
+        assert!(proof.verify(mock_hash([7, 1]), |a, b| mock_hash([*a, *b])));
+    }
+
     #[wasm_bindgen_test]
     #[test]
     fn test_merkle_tree_proof_out_of_bounds() {
@@ -351,22 +417,22 @@ mod tests {
             Box::new(|x| mock_hash([*x, *x])),
         )
         .unwrap();
-        let root = tree.get_root();
+        let root = tree.get_root().unwrap();
 
         // Manually calculate all the hashes and the root.
-        let hash_0 = mock_hash([leaves[0].into(), leaves[0].into()]);
-        let hash_1 = mock_hash([leaves[1].into(), leaves[1].into()]);
-        let hash_2 = mock_hash([leaves[2].into(), leaves[2].into()]);
+        let hash_0 = mock_hash([leaves[0], leaves[0]]);
+        let hash_1 = mock_hash([leaves[1], leaves[1]]);
+        let hash_2 = mock_hash([leaves[2], leaves[2]]);
         let hash_01 = mock_hash([hash_0, hash_1]);
         let hash_22 = mock_hash([hash_2, hash_2]);
         let calc_root = mock_hash([hash_01, hash_22]);
 
-        let calc_proof = vec![
+        let calc_proof = [
             vec![hash_1, hash_22],
             vec![hash_0, hash_22],
             vec![hash_2, hash_01],
         ];
-        let calc_path = vec![
+        let calc_path = [
             vec![MerkleHashPath::Right, MerkleHashPath::Right],
             vec![MerkleHashPath::Left, MerkleHashPath::Right],
             vec![MerkleHashPath::Right, MerkleHashPath::Left],
@@ -384,4 +450,45 @@ mod tests {
             assert_eq!(leaf_index, proof.leaf_index);
         }
     }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_merkle_proof_verify() {
+        // XOR is commutative, so the mock hash can't tell the sides apart.
+        fn ordered_hash(a: &u64, b: &u64) -> u64 {
+            a.wrapping_mul(31).wrapping_add(*b)
+        }
+
+        let leaves = vec![1u64, 2u64, 3u64, 4u64, 5u64];
+        let tree = MerkleTree::new(&leaves, Box::new(ordered_hash), Box::new(|x| *x)).unwrap();
+
+        for (leaf_index, leaf) in leaves.iter().enumerate() {
+            let proof = tree.get_proof(leaf_index).unwrap();
+            assert!(proof.verify(*leaf, ordered_hash));
+            assert!(!proof.verify(*leaf + 10, ordered_hash));
+        }
+        let mut proof = tree.get_proof(0).unwrap();
+        proof.path[0] = MerkleHashPath::Left;
+        assert!(!proof.verify(leaves[0], ordered_hash));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_merkle_proof_serde() {
+        let leaves = vec![1u64, 2u64, 3u64];
+        let tree = MerkleTree::new(
+            &leaves,
+            Box::new(|a, b| mock_hash([*a, *b])),
+            Box::new(|x| mock_hash([*x, *x])),
+        )
+        .unwrap();
+
+        for leaf_index in 0..leaves.len() {
+            let proof = tree.get_proof(leaf_index).unwrap();
+            let bytes = bincode::serialize(&proof).unwrap();
+            let deserialized: MerkleProof<u64> = bincode::deserialize(&bytes).unwrap();
+            assert_eq!(proof, deserialized);
+        }
+        assert_ne!(tree.get_proof(0).unwrap(), tree.get_proof(1).unwrap());
+    }
 }
@@ -10,6 +10,7 @@
 // but should still be investigated if not using it opens us up to vulnerabilities.
 
 use blind_rsa_signatures::{self, KeyPair, Options};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Errors that can occur when working with blind signatures.
@@ -25,6 +26,12 @@ pub enum Error {
     /// Invalid base64 encoding found while parsing. Perhaps there's an issue with the public key input?
     #[error("Invalid base64 {:?}", .0)]
     InvalidBase64(#[from] base64::DecodeError),
+    /// The signature could not be verified under any of the provided public keys.
+    #[error("Signature does not verify under any of the {} provided keys", .0)]
+    NoMatchingKey(usize),
+    /// The parties of the blind signature scheme were configured with different options.
+    #[error("Blind signature options mismatch: expected {}, got {}", .0, .1)]
+    OptionsMismatch(OptionsFingerprint, OptionsFingerprint),
 }
 type Result<T> = std::result::Result<T, Error>;
 
@@ -101,6 +108,81 @@ impl From<UnblindingSecret> for blind_rsa_signatures::Secret {
     }
 }
 
+/// Description of the options of the blind signature scheme.
+/// The signer, the blinder, the unblinder and the verifier must all use the same options,
+/// otherwise the signatures fail to verify with an error which gives no hint about the cause.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionsFingerprint {
+    /// Name of the hash function used for the padding and for hashing the message.
+    hash: &'static str,
+    /// Whether the padding is deterministic instead of randomized with a salt.
+    deterministic: bool,
+    /// Length of the salt in bytes, which is zero for deterministic padding.
+    salt_len: usize,
+}
+
+impl OptionsFingerprint {
+    /// The fingerprint of the options with which all the parties of the protocol are configured.
+    #[must_use]
+    pub fn protocol() -> Self {
+        Self::from(&Options::default())
+    }
+}
+
+impl From<&Options> for OptionsFingerprint {
+    fn from(options: &Options) -> Self {
+        let (hash, deterministic, salt_len): (blind_rsa_signatures::Hash, bool, usize) =
+            options.clone().into();
+        let hash = match hash {
+            blind_rsa_signatures::Hash::Sha256 => "SHA-256",
+            blind_rsa_signatures::Hash::Sha384 => "SHA-384",
+            blind_rsa_signatures::Hash::Sha512 => "SHA-512",
+        };
+        Self {
+            hash,
+            deterministic,
+            // The salt length is ignored for deterministic padding.
+            salt_len: if deterministic { 0 } else { salt_len },
+        }
+    }
+}
+
+impl std::fmt::Display for OptionsFingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let padding = if self.deterministic {
+            "deterministic"
+        } else {
+            "randomized"
+        };
+        write!(
+            f,
+            "{} with {padding} padding and {} byte salt",
+            self.hash, self.salt_len
+        )
+    }
+}
+
+/// Check that two parties of the blind signature scheme use the same options.
+///
+/// # Arguments
+///
+/// * `expected` - The fingerprint of the options of one party, usually the signer.
+/// * `actual` - The fingerprint of the options of the other party.
+///
+/// # Errors
+///
+/// If the options differ.
+pub fn assert_options_match(
+    expected: &OptionsFingerprint,
+    actual: &OptionsFingerprint,
+) -> Result<()> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(Error::OptionsMismatch(expected.clone(), actual.clone()))
+    }
+}
+
 /// The signer for blindly signing messages.
 #[derive(Debug, Clone)]
 pub struct BlindSigner {
@@ -178,6 +260,12 @@ impl BlindSigner {
         Ok(SecretKey(self.sk.to_der()?))
     }
 
+    /// Get the fingerprint of the options of the blind signature scheme, see `assert_options_match`.
+    #[must_use]
+    pub fn options_fingerprint(&self) -> OptionsFingerprint {
+        OptionsFingerprint::from(&self.options)
+    }
+
     /// Blindly sign a message.
     ///
     /// # Arguments
@@ -231,6 +319,12 @@ impl Verifier {
         })
     }
 
+    /// Get the fingerprint of the options of the blind signature scheme, see `assert_options_match`.
+    #[must_use]
+    pub fn options_fingerprint(&self) -> OptionsFingerprint {
+        OptionsFingerprint::from(&self.options)
+    }
+
     /// Verify a signature.
     ///
     /// # Arguments
@@ -251,6 +345,40 @@ impl Verifier {
 
         Ok(())
     }
+
+    /// Verify a signature against a list of public keys, for example the current and the
+    /// archived keys of an authority which had rotated its keys.
+    /// The options of this verifier are used for all of the keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `signature` - The signature to verify.
+    /// * `msg` - The message which was signed.
+    /// * `keys` - The public keys under which the signature may have been issued.
+    ///
+    /// # Returns
+    ///
+    /// The index of the first key under which the signature is valid.
+    ///
+    /// # Errors
+    ///
+    /// If any of the public keys is invalid or if the signature is not valid under any of the keys.
+    pub fn verify_any(
+        &self,
+        signature: Signature,
+        msg: &[u8],
+        keys: &[PublicKey],
+    ) -> Result<usize> {
+        let sig = blind_rsa_signatures::Signature::from(signature);
+        for (index, pk) in keys.iter().enumerate() {
+            let pk: blind_rsa_signatures::PublicKey = pk.clone().try_into()?;
+            if sig.verify(&pk, None, msg, &self.options).is_ok() {
+                return Ok(index);
+            }
+        }
+
+        Err(Error::NoMatchingKey(keys.len()))
+    }
 }
 
 /// The blinder for blinding messages before sending them to the signer.
@@ -285,6 +413,12 @@ impl Blinder {
         })
     }
 
+    /// Get the fingerprint of the options of the blind signature scheme, see `assert_options_match`.
+    #[must_use]
+    pub fn options_fingerprint(&self) -> OptionsFingerprint {
+        OptionsFingerprint::from(&self.options)
+    }
+
     /// Blind a message.
     ///
     /// # Arguments
@@ -371,6 +505,12 @@ impl Unblinder {
         self.unblinding_secret.clone().into()
     }
 
+    /// Get the fingerprint of the options of the blind signature scheme, see `assert_options_match`.
+    #[must_use]
+    pub fn options_fingerprint(&self) -> OptionsFingerprint {
+        OptionsFingerprint::from(&self.options)
+    }
+
     /// Recreate an `Unblinder` from a `Signer` public key and an unblinding secret.
     ///
     /// # Arguments
@@ -392,6 +532,48 @@ impl Unblinder {
             unblinding_secret: unblinding_secret.into(),
         })
     }
+
+    /// Get everything needed to recreate this `Unblinder` in a serializable form.
+    ///
+    /// # Returns
+    ///
+    /// The public key and the unblinding secret of this unblinder.
+    ///
+    /// # Errors
+    ///
+    /// If the public key cannot be serialized to DER format.
+    pub fn to_stored(&self) -> Result<StoredUnblinder> {
+        Ok(StoredUnblinder {
+            pk: PublicKey(self.pk.to_der()?),
+            secret: self.get_unblinding_secret(),
+        })
+    }
+}
+
+/// Serializable form of an `Unblinder`, so that it can be persisted while the user waits for the
+/// signer to sign the blinded message.
+/// NOTE: Contains the unblinding secret, so it must never leave the user.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct StoredUnblinder {
+    /// The public key of the `Blinder`.
+    pub pk: PublicKey,
+    /// The unblinding secret of the `Unblinder`.
+    pub secret: UnblindingSecret,
+}
+
+impl StoredUnblinder {
+    /// Recreate the stored `Unblinder`.
+    ///
+    /// # Returns
+    ///
+    /// The recreated `Unblinder`.
+    ///
+    /// # Errors
+    ///
+    /// If public key conversion fails.
+    pub fn to_unblinder(&self) -> Result<Unblinder> {
+        Unblinder::from_pk_and_secret(self.pk.clone(), self.secret.clone())
+    }
 }
 
 #[cfg(test)]
@@ -440,4 +622,98 @@ mod tests {
         // Same for the blind message and the unblinded signature
         assert!(verifier.verify_signature(signature, &blind_msg.0).is_err());
     }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_verify_any() {
+        let old_signer = BlindSigner::new().unwrap();
+        let new_signer = BlindSigner::new().unwrap();
+        let keys = vec![
+            old_signer.get_public_key().unwrap(),
+            new_signer.get_public_key().unwrap(),
+        ];
+
+        let msg = b"secret_message";
+        let blinder = Blinder::new(new_signer.get_public_key().unwrap()).unwrap();
+        let (blind_msg, unblinder) = blinder.blind(msg).unwrap();
+        let blind_signature = new_signer.bling_sign(&blind_msg).unwrap();
+        let signature = unblinder.unblind_signature(blind_signature, msg).unwrap();
+
+        let verifier = Verifier::new(old_signer.get_public_key().unwrap()).unwrap();
+        assert_eq!(
+            verifier.verify_any(signature.clone(), msg, &keys).unwrap(),
+            1
+        );
+        assert!(matches!(
+            verifier.verify_any(signature, msg, &keys[..1]),
+            Err(Error::NoMatchingKey(1))
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_stored_unblinder() {
+        let blind_signer = BlindSigner::new().unwrap();
+        let msg = b"secret_message";
+        let blinder = Blinder::new(blind_signer.get_public_key().unwrap()).unwrap();
+        let (blind_msg, unblinder) = blinder.blind(msg).unwrap();
+
+        let stored = unblinder.to_stored().unwrap();
+        assert_eq!(stored.pk, blind_signer.get_public_key().unwrap());
+        let bytes = bincode::serialize(&stored).unwrap();
+        let loaded: StoredUnblinder = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(loaded, stored);
+
+        let blind_signature = blind_signer.bling_sign(&blind_msg).unwrap();
+        let signature = loaded
+            .to_unblinder()
+            .unwrap()
+            .unblind_signature(blind_signature, msg)
+            .unwrap();
+        let verifier = Verifier::new(blind_signer.get_public_key().unwrap()).unwrap();
+        verifier.verify_signature(signature, msg).unwrap();
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_options_mismatch() {
+        let blind_signer = BlindSigner::new().unwrap();
+        let pk = blind_signer.get_public_key().unwrap();
+        let blinder = Blinder::new(pk.clone()).unwrap();
+        let (_, unblinder) = blinder.blind(b"message").unwrap();
+        let verifier = Verifier::new(pk.clone()).unwrap();
+        for fingerprint in [
+            blinder.options_fingerprint(),
+            unblinder.options_fingerprint(),
+            verifier.options_fingerprint(),
+        ] {
+            assert_options_match(&blind_signer.options_fingerprint(), &fingerprint).unwrap();
+        }
+
+        let misconfigured = Verifier {
+            pk: pk.try_into().unwrap(),
+            options: Options::new(blind_rsa_signatures::Hash::Sha256, false, 32),
+        };
+        let err = assert_options_match(
+            &blind_signer.options_fingerprint(),
+            &misconfigured.options_fingerprint(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::OptionsMismatch(..)));
+        assert_eq!(
+            err.to_string(),
+            "Blind signature options mismatch: expected SHA-384 with randomized padding and 48 \
+             byte salt, got SHA-256 with randomized padding and 32 byte salt"
+        );
+        assert_eq!(
+            blind_signer.options_fingerprint(),
+            OptionsFingerprint::protocol()
+        );
+
+        // The salt length doesn't matter for deterministic padding.
+        assert_eq!(
+            OptionsFingerprint::from(&Options::new(blind_rsa_signatures::Hash::Sha384, true, 0)),
+            OptionsFingerprint::from(&Options::new(blind_rsa_signatures::Hash::Sha384, true, 48))
+        );
+    }
 }
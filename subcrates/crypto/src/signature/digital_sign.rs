@@ -1,7 +1,11 @@
 //! A simple digital signature based on ed25519.
 
 // TODO add examples when API is more stable.
-use ring::signature::{self, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+use ring::{
+    aead, digest,
+    rand::{SecureRandom, SystemRandom},
+    signature::{self, Ed25519KeyPair, KeyPair, UnparsedPublicKey},
+};
 use thiserror::Error;
 
 /// Errors that can occur when working with digital signatures.
@@ -20,6 +24,12 @@ pub enum Error {
     /// Base64 conversion error.
     #[error("Invalid base64 {:?}", .0)]
     InvalidBase64(#[from] base64::DecodeError),
+    /// The secret key could not be encrypted or decrypted in memory.
+    #[error("Failed to seal the secret key in memory")]
+    Sealing,
+    /// The secret key doesn't belong to the expected public key, so it was corrupted or swapped.
+    #[error("Secret key doesn't match the expected public key")]
+    PublicKeyMismatch,
 }
 type Result<T> = std::result::Result<T, Error>;
 
@@ -57,6 +67,65 @@ pub fn verify(message: &[u8], signature: &Signature, peer_public_key: &PublicKey
     Ok(())
 }
 
+/// Domain separation prefix of prehashed messages, so that a prehashed signature can never be
+/// mistaken for a plain signature of some other message.
+const PREHASH_DOMAIN: &[u8] = b"digital-voting/ed25519/sha512-prehashed";
+
+/// Incremental hasher of large messages to be signed with `Signer::sign_prehashed`.
+/// The message can be fed in chunks, so that it never has to be buffered as a whole.
+///
+/// Ring does not implement Ed25519ph from RFC 8032, so instead the SHA-512 digest of the
+/// message is prefixed with a domain separation tag and signed with plain Ed25519.
+/// This means that the signatures are not compatible with other Ed25519ph implementations.
+pub struct Prehasher(digest::Context);
+
+impl Prehasher {
+    /// Create a new prehasher for a single message.
+    #[must_use]
+    pub fn new() -> Self {
+        Self(digest::Context::new(&digest::SHA512))
+    }
+
+    /// Feed the next chunk of the message into the hasher.
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.0.update(chunk);
+    }
+
+    /// Get the domain separated digest which is actually signed.
+    fn finish(self) -> Vec<u8> {
+        let digest = self.0.finish();
+        let mut prehashed = Vec::with_capacity(PREHASH_DOMAIN.len() + digest.as_ref().len());
+        prehashed.extend_from_slice(PREHASH_DOMAIN);
+        prehashed.extend_from_slice(digest.as_ref());
+        prehashed
+    }
+}
+
+impl Default for Prehasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Associated function for verifying a signature created with `Signer::sign_prehashed`.
+///
+/// # Arguments
+///
+/// * `prehasher` - The hasher into which the whole signed message had been fed.
+/// * `signature` - The signature to verify.
+/// * `peer_public_key` - The public key of the peer that signed the message.
+///
+/// # Errors
+///
+/// If the signature is invalid, including if it's a plain signature of the message.
+pub fn verify_prehashed(
+    prehasher: Prehasher,
+    signature: &Signature,
+    peer_public_key: &PublicKey,
+) -> Result<()> {
+    verify(&prehasher.finish(), signature, peer_public_key)
+}
+
 /// The signature struct containing the key pair.
 /// Constructed only for signing, verification is done with an associated function.
 pub struct Signer {
@@ -106,6 +175,33 @@ impl Signer {
         })
     }
 
+    /// Same as `from_secret_key`, but also checks that the key pair belongs to the expected
+    /// public key, so that a stored secret key which was corrupted or swapped is detected.
+    ///
+    /// # Arguments
+    ///
+    /// * `secret_key` - The stored secret key.
+    /// * `expected_public_key` - The public key stored together with the secret key.
+    ///
+    /// # Returns
+    ///
+    /// The signer struct containing the keypair.
+    ///
+    /// # Errors
+    ///
+    /// If deriving the key pair from Pkcs8 fails or if its public key is not the expected one.
+    pub fn from_secret_key_checked(
+        secret_key: SecretKey,
+        expected_public_key: &PublicKey,
+    ) -> Result<Self> {
+        let signer = Self::from_secret_key(secret_key)?;
+        if signer.get_public_key() != *expected_public_key {
+            return Err(Error::PublicKeyMismatch);
+        }
+
+        Ok(signer)
+    }
+
     /// Get secret key encoded as pkcs8 document for storing the key.
     ///
     /// # Returns
@@ -130,6 +226,21 @@ impl Signer {
         Signature(self.key_pair.sign(message).as_ref().to_vec())
     }
 
+    /// Sign a large message which had been fed into a prehasher.
+    /// Such signatures must be verified with `verify_prehashed`.
+    ///
+    /// # Arguments
+    ///
+    /// * `prehasher` - The hasher into which the whole message had been fed.
+    ///
+    /// # Returns
+    ///
+    /// The signature.
+    #[must_use]
+    pub fn sign_prehashed(&self, prehasher: Prehasher) -> Signature {
+        self.sign(&prehasher.finish())
+    }
+
     /// Get the public key.
     ///
     /// # Returns
@@ -142,6 +253,145 @@ impl Signer {
     }
 }
 
+/// Overwrite secret bytes before their memory is freed.
+/// Passing the bytes through `black_box`, so that the compiler doesn't skip writing to memory
+/// which is never read again.
+fn wipe(bytes: &mut [u8]) {
+    bytes.fill(0);
+    std::hint::black_box(bytes);
+}
+
+/// A signer which keeps its secret key encrypted in memory with a random key, so that the
+/// secret key is in plain text only while a message is being signed.
+/// This narrows the exposure of the secret key, but doesn't eliminate it, since the random key
+/// is kept in memory as well and ring doesn't wipe the key pair which is created for signing.
+pub struct SealedSigner {
+    /// The public key, which doesn't need to be protected.
+    public_key: PublicKey,
+    /// The random key with which the secret key is sealed.
+    key: aead::LessSafeKey,
+    /// The pkcs8 encoded secret key followed by the authentication tag.
+    sealed_secret_key: Vec<u8>,
+}
+
+impl SealedSigner {
+    /// Every sealing key is random and seals a single secret key, so the nonce can be fixed.
+    const NONCE: [u8; aead::NONCE_LEN] = [0; aead::NONCE_LEN];
+
+    /// Seal the secret key of the signer, wiping the plain text copy held by the signer.
+    ///
+    /// # Errors
+    ///
+    /// If the random key could not be generated or if the sealing fails.
+    pub fn new(signer: Signer) -> Result<Self> {
+        let public_key = signer.get_public_key();
+        let Signer {
+            secret_key: SecretKey(mut secret_key),
+            ..
+        } = signer;
+
+        let mut key_bytes = [0; 32];
+        let key = SystemRandom::new()
+            .fill(&mut key_bytes)
+            .ok()
+            .and_then(|()| aead::UnboundKey::new(&aead::CHACHA20_POLY1305, &key_bytes).ok())
+            .map(aead::LessSafeKey::new);
+        wipe(&mut key_bytes);
+        // Copying into a buffer with room for the tag, so that appending the tag doesn't leave
+        // a plain text copy behind in a reallocated buffer.
+        let mut sealed_secret_key =
+            Vec::with_capacity(secret_key.len() + aead::CHACHA20_POLY1305.tag_len());
+        sealed_secret_key.extend_from_slice(&secret_key);
+        wipe(&mut secret_key);
+        let key = key.ok_or(Error::Sealing)?;
+        key.seal_in_place_append_tag(
+            aead::Nonce::assume_unique_for_key(Self::NONCE),
+            aead::Aad::empty(),
+            &mut sealed_secret_key,
+        )
+        .map_err(|_| Error::Sealing)?;
+
+        Ok(Self {
+            public_key,
+            key,
+            sealed_secret_key,
+        })
+    }
+
+    /// Decrypt the secret key into a signer for the duration of the closure.
+    /// The decrypted secret key is wiped once the closure returns.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The closure using the signer.
+    ///
+    /// # Returns
+    ///
+    /// The return value of the closure.
+    ///
+    /// # Errors
+    ///
+    /// If the secret key could not be decrypted.
+    pub fn with_signer<R>(&self, f: impl FnOnce(&Signer) -> R) -> Result<R> {
+        let mut secret_key = self.sealed_secret_key.clone();
+        let opened = self.key.open_in_place(
+            aead::Nonce::assume_unique_for_key(Self::NONCE),
+            aead::Aad::empty(),
+            &mut secret_key,
+        );
+        let key_pair = match opened {
+            Ok(opened) => signature::Ed25519KeyPair::from_pkcs8(opened)
+                .map_err(|_| Error::KeyPairGenerationFailed),
+            Err(_) => Err(Error::Sealing),
+        };
+        let key_pair = match key_pair {
+            Ok(key_pair) => key_pair,
+            Err(e) => {
+                wipe(&mut secret_key);
+                return Err(e);
+            }
+        };
+        secret_key.truncate(self.sealed_secret_key.len() - aead::CHACHA20_POLY1305.tag_len());
+
+        let mut signer = Signer {
+            key_pair,
+            secret_key: SecretKey(secret_key),
+        };
+        let result = f(&signer);
+        wipe(&mut signer.secret_key.0);
+
+        Ok(result)
+    }
+
+    /// Sign a message, see `Signer::sign`.
+    ///
+    /// # Errors
+    ///
+    /// If the secret key could not be decrypted.
+    pub fn sign(&self, message: &[u8]) -> Result<Signature> {
+        self.with_signer(|signer| signer.sign(message))
+    }
+
+    /// Get a plain text copy of the secret key for storing it, see `Signer::get_secret_key`.
+    ///
+    /// # Errors
+    ///
+    /// If the secret key could not be decrypted.
+    pub fn get_secret_key(&self) -> Result<SecretKey> {
+        self.with_signer(|signer| signer.get_secret_key().clone())
+    }
+
+    /// Get the public key, which is kept in plain text.
+    ///
+    /// # Returns
+    ///
+    /// The public key.
+    #[must_use]
+    pub fn get_public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -162,4 +412,82 @@ mod tests {
         let public_key = signer.get_public_key();
         verify(message, &signature_bytes, &public_key).unwrap();
     }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_checked_secret_key() {
+        let signer = Signer::new().unwrap();
+        let secret_key = signer.get_secret_key().clone();
+        let public_key = signer.get_public_key();
+
+        let loaded = Signer::from_secret_key_checked(secret_key.clone(), &public_key).unwrap();
+        assert_eq!(loaded.get_public_key(), public_key);
+
+        // A secret key swapped for another valid one is caught.
+        let other_public_key = Signer::new().unwrap().get_public_key();
+        assert!(matches!(
+            Signer::from_secret_key_checked(secret_key, &other_public_key),
+            Err(Error::PublicKeyMismatch)
+        ));
+        assert!(matches!(
+            Signer::from_secret_key_checked(SecretKey::from_pkcs8(vec![1, 2, 3]), &public_key),
+            Err(Error::KeyPairGenerationFailed)
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_prehashed_signature() {
+        let message = vec![7_u8; 10_000];
+        let signer = Signer::new().unwrap();
+        let public_key = signer.get_public_key();
+        let prehash = |message: &[u8]| {
+            let mut prehasher = Prehasher::new();
+            for chunk in message.chunks(999) {
+                prehasher.update(chunk);
+            }
+            prehasher
+        };
+
+        let prehashed_signature = signer.sign_prehashed(prehash(&message));
+        verify_prehashed(prehash(&message), &prehashed_signature, &public_key).unwrap();
+        assert!(verify_prehashed(prehash(b"other"), &prehashed_signature, &public_key).is_err());
+
+        // Plain and prehashed signatures must not be interchangeable.
+        let plain_signature = signer.sign(&message);
+        assert!(verify(&message, &prehashed_signature, &public_key).is_err());
+        assert!(verify_prehashed(prehash(&message), &plain_signature, &public_key).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_sealed_signer() {
+        let message = b"hello world";
+        let signer = Signer::new().unwrap();
+        let secret_key = signer.get_secret_key().clone();
+        let public_key = signer.get_public_key();
+
+        let sealed_signer = SealedSigner::new(signer).unwrap();
+        let sealed_secret_key = sealed_signer.sealed_secret_key.clone();
+        let is_plain_text = |bytes: &[u8]| {
+            secret_key
+                .as_ref()
+                .windows(8)
+                .any(|secret| bytes.windows(8).any(|window| window == secret))
+        };
+        assert!(!is_plain_text(&sealed_signer.sealed_secret_key));
+
+        for _ in 0..2 {
+            let signature = sealed_signer.sign(message).unwrap();
+            verify(message, &signature, &public_key).unwrap();
+            // The secret key stays sealed between signs.
+            assert_eq!(sealed_signer.sealed_secret_key, sealed_secret_key);
+        }
+        assert_eq!(*sealed_signer.get_public_key(), public_key);
+        assert_eq!(sealed_signer.get_secret_key().unwrap(), secret_key);
+
+        let mut tampered = sealed_signer;
+        tampered.sealed_secret_key[0] ^= 1;
+        assert!(matches!(tampered.sign(message), Err(Error::Sealing)));
+    }
 }
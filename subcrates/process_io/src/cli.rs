@@ -10,6 +10,7 @@ use rustyline::{
     Completer, CompletionType, Config, EditMode, Editor, Helper, Hinter, Validator,
 };
 use std::borrow::Cow::{self, Borrowed, Owned};
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 // Error type of the CLI module.
@@ -27,6 +28,11 @@ pub enum Error {
 }
 type Result<T> = std::result::Result<T, Error>;
 
+/// The file in which the command history is kept by default.
+pub const HISTORY_FILE: &str = "node-cmd-history.txt";
+/// The default maximum amount of commands kept in the history, same as the default of rustyline.
+pub const DEFAULT_MAX_HISTORY_ENTRIES: usize = 100;
+
 /// `StdioReader` reads lines from stdio.
 /// It also manages the command history so should only be dropped
 /// when the application exits.
@@ -37,10 +43,12 @@ pub struct StdioReader {
     /// Used for adding to the input command, so that `clap` can parse it.
     /// Storing this here to avoid the extra operations needed to retrieve it.
     exec_name: String,
+    /// The file to which the command history is saved.
+    history_path: PathBuf,
 }
 
 impl StdioReader {
-    /// Create a new `StdioReader`.
+    /// Create a new `StdioReader` keeping the default amount of commands in `HISTORY_FILE`.
     ///
     /// # Returns
     ///
@@ -50,9 +58,33 @@ impl StdioReader {
     ///
     /// If there was an error creating the Editor for Rustyline.
     pub fn new() -> Result<Self> {
+        Self::with_history(HISTORY_FILE, DEFAULT_MAX_HISTORY_ENTRIES)
+    }
+
+    /// Create a new `StdioReader` with the command history kept in the file.
+    /// Only the most recent commands are loaded from the file and saved back to it,
+    /// so that the history file doesn't grow forever.
+    ///
+    /// # Arguments
+    ///
+    /// - `history_path` - The file from which the history is loaded and to which it is saved.
+    /// - `max_history_entries` - Maximum amount of commands kept in the history.
+    ///
+    /// # Returns
+    ///
+    /// A new `StdioReader`.
+    ///
+    /// # Errors
+    ///
+    /// If there was an error creating the Editor for Rustyline.
+    pub fn with_history(
+        history_path: impl AsRef<Path>,
+        max_history_entries: usize,
+    ) -> Result<Self> {
         let config = Config::builder()
             .completion_type(CompletionType::List)
             .auto_add_history(true)
+            .max_history_size(max_history_entries)?
             .edit_mode(EditMode::Emacs)
             .build();
         let h = MyHelper {
@@ -66,9 +98,14 @@ impl StdioReader {
         let exec_name = exec_name.to_string_lossy().to_string();
         let mut rl = Editor::with_config(config)?;
         rl.set_helper(Some(h));
-        let _ = rl.load_history("node-cmd-history.txt");
+        let history_path = history_path.as_ref().to_path_buf();
+        let _ = rl.load_history(&history_path);
 
-        Ok(Self { rl, exec_name })
+        Ok(Self {
+            rl,
+            exec_name,
+            history_path,
+        })
     }
 
     /// Read a line from stdio. This function blocks until a line is read.
@@ -101,7 +138,7 @@ impl Drop for StdioReader {
     /// The command history is saved to a file when the `StdioReader` is dropped.
     /// So `StdioReader` should only really be dropped when the program is exiting.
     fn drop(&mut self) {
-        let _ = self.rl.save_history("node-cmd-history.txt");
+        let _ = self.rl.save_history(&self.history_path);
     }
 }
 
@@ -143,3 +180,37 @@ impl Highlighter for MyHelper {
         self.highlighter.highlight_char(line, pos, forced)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils::TempDir;
+
+    fn history(reader: &StdioReader) -> Vec<&str> {
+        reader.rl.history().iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn test_history_cap() {
+        let dir = TempDir::new("history");
+        let path = dir.join("history.txt");
+
+        let mut reader = StdioReader::with_history(&path, 3).unwrap();
+        for i in 0..5 {
+            reader.rl.add_history_entry(format!("cmd {i}")).unwrap();
+        }
+        drop(reader);
+        let reader = StdioReader::with_history(&path, 3).unwrap();
+        assert_eq!(history(&reader), ["cmd 2", "cmd 3", "cmd 4"]);
+        drop(reader);
+
+        // Lowering the cap trims the history already in the file once it's saved again.
+        let mut reader = StdioReader::with_history(&path, 2).unwrap();
+        assert_eq!(history(&reader), ["cmd 3", "cmd 4"]);
+        reader.rl.add_history_entry("cmd 5").unwrap();
+        drop(reader);
+        let reader = StdioReader::with_history(&path, 3).unwrap();
+        assert_eq!(history(&reader), ["cmd 4", "cmd 5"]);
+    }
+}
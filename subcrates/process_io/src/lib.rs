@@ -1 +1,4 @@
 pub mod cli;
+pub mod output;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
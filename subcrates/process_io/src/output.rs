@@ -0,0 +1,106 @@
+//! Module for the output of the CLI commands.
+//! The results of the commands can be written to a file instead of stdout,
+//! so that they don't get interleaved with the logs printed to the same terminal.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+/// Destination of the results of the CLI commands.
+pub struct Output {
+    /// The writer to which the results are written.
+    writer: Box<dyn Write + Send>,
+}
+
+impl Output {
+    /// Create an output which writes to stdout.
+    #[must_use]
+    pub fn stdout() -> Self {
+        Self::from_writer(std::io::stdout())
+    }
+
+    /// Create an output which writes to the given writer.
+    #[must_use]
+    pub fn from_writer(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Box::new(writer),
+        }
+    }
+
+    /// Open the output configured on the command line.
+    ///
+    /// # Arguments
+    ///
+    /// - `path` - The file to which the results are appended. Stdout is used if it's `None`.
+    ///
+    /// # Errors
+    ///
+    /// If the file could not be opened.
+    pub fn open(path: Option<&Path>) -> std::io::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::stdout());
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self::from_writer(file))
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    /// Results are flushed after every line, so that they show up while the process is running.
+    fn write_fmt(&mut self, args: std::fmt::Arguments<'_>) -> std::io::Result<()> {
+        self.writer.write_fmt(args)?;
+        self.writer.flush()
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    use crate::test_utils::TempDir;
+
+    /// Writer which can still be read after it's moved into the output.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_output_writer() {
+        let buffer = SharedBuffer::default();
+        let mut output = Output::from_writer(buffer.clone());
+        writeln!(output, "result {}", 1).unwrap();
+        writeln!(output, "result {}", 2).unwrap();
+        assert_eq!(*buffer.0.lock().unwrap(), b"result 1\nresult 2\n");
+    }
+
+    #[test]
+    fn test_output_file() {
+        let dir = TempDir::new("output");
+        let path = dir.join("output");
+
+        writeln!(Output::open(Some(&path)).unwrap(), "first").unwrap();
+        // Results of previous runs are kept.
+        writeln!(Output::open(Some(&path)).unwrap(), "second").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+    }
+}
@@ -0,0 +1,82 @@
+//! Helpers for the tests which need files on disk.
+//! Available in this crate's own tests and, behind the `test-utils` feature, in downstream crates,
+//! so that every test doesn't have to come up with its own temporary paths and cleanup.
+//! The helpers panic on failure, since they are only meant to be used in tests.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Directory under the system temporary directory, which is removed together with its contents
+/// when dropped, so that it's cleaned up even if the test panics.
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Create an empty directory whose path is unique across processes and across the tests
+    /// running in parallel in the same process.
+    ///
+    /// # Arguments
+    ///
+    /// - `name` - Name of the test, to tell the directories apart when debugging.
+    ///
+    /// # Panics
+    ///
+    /// If the directory could not be created.
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "digital-voting-{}-{}-{name}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        // Left over from a previous process with the same id.
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+
+        Self { path }
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the path of a file in the directory.
+    #[must_use]
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+
+    /// Same as `join`, but as a string for the functions which take file names as `&str`.
+    #[must_use]
+    pub fn file(&self, name: &str) -> String {
+        self.join(name).to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_dir() {
+        let first = TempDir::new("temp-dir");
+        let second = TempDir::new("temp-dir");
+        assert_ne!(first.path(), second.path());
+        assert!(first.path().is_dir());
+
+        std::fs::write(first.join("file"), "contents").unwrap();
+        let path = first.path().to_owned();
+        drop(first);
+        assert!(!path.exists());
+    }
+}
@@ -17,9 +17,15 @@ pub enum Error {
 /// This configurably defines what underlying primitive type will be used to describe the candidate.
 type UnderlyingType = u8;
 
+/// The maximum amount of candidates in an election, since every candidate needs a distinct id.
+pub const MAX_CANDIDATES: usize = 1 << (8 * std::mem::size_of::<UnderlyingType>());
+
+/// Width of the canonical encoding of a candidate id in bytes, see `CandidateId::to_canonical_bytes`.
+pub const CANONICAL_LEN: usize = std::mem::size_of::<UnderlyingType>();
+
 /// The wrapper struct for primitive types to represent election candidates.
 #[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Clone)]
-pub struct CandidateId([u8; std::mem::size_of::<UnderlyingType>()]);
+pub struct CandidateId([u8; CANONICAL_LEN]);
 
 impl CandidateId {
     /// Function used to create new primitive candidate from underlying primitive type.
@@ -33,6 +39,14 @@ impl CandidateId {
     pub fn get(&self) -> UnderlyingType {
         UnderlyingType::from_le_bytes(self.0)
     }
+
+    /// Encode the candidate id into fixed width little endian bytes, which are the same on every
+    /// platform, so that the signatures covering the candidate ids of votes stay portable.
+    /// Both signing and verification must use this encoding.
+    #[must_use]
+    pub fn to_canonical_bytes(&self) -> [u8; CANONICAL_LEN] {
+        self.get().to_le_bytes()
+    }
 }
 
 impl AsRef<[u8]> for CandidateId {
@@ -57,12 +71,25 @@ impl std::str::FromStr for CandidateId {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     use wasm_bindgen_test::wasm_bindgen_test;
 
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_canonical_bytes() {
+        // Changing these bytes breaks the signatures of all the existing votes.
+        assert_eq!(CandidateId::new(0).to_canonical_bytes(), [0x00]);
+        assert_eq!(CandidateId::new(7).to_canonical_bytes(), [0x07]);
+        assert_eq!(CandidateId::new(255).to_canonical_bytes(), [0xff]);
+        let candidate: CandidateId = "42".parse().unwrap();
+        assert_eq!(candidate.to_canonical_bytes(), [42]);
+    }
+
     #[wasm_bindgen_test]
     #[test]
     fn test_vote() {
-        let primitive_candidate_original = 1 as usize;
+        let primitive_candidate_original = 1_usize;
 
         let primitive_candidate = primitive_candidate_original.to_string();
         let primitive_candidate: usize = primitive_candidate.parse().unwrap();
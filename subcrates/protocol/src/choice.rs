@@ -0,0 +1,187 @@
+//! Module describing what a voter has chosen on their ballot.
+//! A voter may either pick one of the configured candidates or, if the election allows it,
+//! write in the name of a candidate who is not present in the election config or abstain.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::candidate_id::CandidateId;
+
+/// Errors that can occur when working with ballot choices.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// The write-in name is empty after removing surrounding whitespace.
+    #[error("Write-in name cannot be empty")]
+    EmptyWriteIn,
+    /// The write-in name is longer than `MAX_WRITE_IN_LEN` characters.
+    #[error("Write-in name is {} characters long, but at most {} are allowed", .0, MAX_WRITE_IN_LEN)]
+    WriteInTooLong(usize),
+    /// The write-in name contains control characters.
+    #[error("Write-in name cannot contain control characters")]
+    WriteInControlCharacters,
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// Maximum length of a write-in name in characters.
+pub const MAX_WRITE_IN_LEN: usize = 64;
+
+/// Free text name of a candidate who is not present in the election config.
+/// The name is sanitized upon construction by trimming it and collapsing all internal
+/// whitespace into single spaces, so that the same name is always spelled the same way.
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct WriteIn(String);
+
+impl WriteIn {
+    /// Create a new sanitized and validated write-in name.
+    ///
+    /// # Arguments
+    ///
+    /// - `name` - The name of the written in candidate.
+    ///
+    /// # Returns
+    ///
+    /// The write-in name.
+    ///
+    /// # Errors
+    ///
+    /// If the name is empty, too long or contains control characters.
+    pub fn new(name: &str) -> Result<Self> {
+        // Whitespace control characters are fine, since they are collapsed below anyway.
+        if name.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+            return Err(Error::WriteInControlCharacters);
+        }
+        let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        if name.is_empty() {
+            return Err(Error::EmptyWriteIn);
+        }
+        let len = name.chars().count();
+        if len > MAX_WRITE_IN_LEN {
+            return Err(Error::WriteInTooLong(len));
+        }
+
+        Ok(Self(name))
+    }
+}
+
+impl AsRef<str> for WriteIn {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for WriteIn {
+    type Error = Error;
+
+    fn try_from(name: String) -> Result<Self> {
+        Self::new(&name)
+    }
+}
+
+impl From<WriteIn> for String {
+    fn from(write_in: WriteIn) -> Self {
+        write_in.0
+    }
+}
+
+impl std::fmt::Display for WriteIn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// What the voter had chosen on the ballot.
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Clone)]
+pub enum Choice {
+    /// One of the candidates from the election config.
+    Candidate(CandidateId),
+    /// A candidate who is not present in the election config.
+    WriteIn(WriteIn),
+    /// A deliberate abstention, which counts towards the turnout, but not towards any candidate.
+    Abstain,
+}
+
+impl Choice {
+    /// Encode the choice into bytes for signing.
+    /// Each variant is prefixed with a tag byte and write-ins are also length prefixed,
+    /// so that no two different choices can ever produce the same bytes.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Candidate(candidate) => {
+                let mut bytes = vec![0];
+                bytes.extend_from_slice(&candidate.to_canonical_bytes());
+                bytes
+            }
+            Self::WriteIn(write_in) => {
+                let name = write_in.as_ref().as_bytes();
+                let mut bytes = vec![1];
+                // Write-in length is bounded by `MAX_WRITE_IN_LEN` characters, so it fits into u32.
+                #[allow(clippy::cast_possible_truncation)]
+                bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(name);
+                bytes
+            }
+            Self::Abstain => vec![2],
+        }
+    }
+}
+
+impl From<CandidateId> for Choice {
+    fn from(candidate: CandidateId) -> Self {
+        Self::Candidate(candidate)
+    }
+}
+
+impl From<WriteIn> for Choice {
+    fn from(write_in: WriteIn) -> Self {
+        Self::WriteIn(write_in)
+    }
+}
+
+impl std::fmt::Display for Choice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Candidate(candidate) => write!(f, "candidate {candidate}"),
+            Self::WriteIn(write_in) => write!(f, "write-in \"{write_in}\""),
+            Self::Abstain => write!(f, "abstention"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_write_in_sanitization() {
+        let write_in = WriteIn::new("  Jane \t  Doe ").unwrap();
+        assert_eq!(write_in.as_ref(), "Jane Doe");
+
+        assert!(matches!(WriteIn::new("   "), Err(Error::EmptyWriteIn)));
+        assert!(matches!(
+            WriteIn::new("Jane\u{0}Doe"),
+            Err(Error::WriteInControlCharacters)
+        ));
+        assert!(WriteIn::new(&"a".repeat(MAX_WRITE_IN_LEN)).is_ok());
+        assert!(matches!(
+            WriteIn::new(&"a".repeat(MAX_WRITE_IN_LEN + 1)),
+            Err(Error::WriteInTooLong(_))
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_choice_bytes_unambiguous() {
+        let candidate = Choice::from(CandidateId::new(1));
+        let write_in = Choice::from(WriteIn::new("1").unwrap());
+        assert_ne!(candidate.to_bytes(), write_in.to_bytes());
+        assert_ne!(Choice::Abstain.to_bytes(), candidate.to_bytes());
+        assert_ne!(Choice::Abstain.to_bytes(), write_in.to_bytes());
+        // The tag byte followed by the canonical bytes of the candidate id.
+        assert_eq!(candidate.to_bytes(), [0, 1]);
+    }
+}
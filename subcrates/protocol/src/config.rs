@@ -0,0 +1,710 @@
+//! Module for the configuration of an election, which is shared by the node, the election
+//! authority and the client, so that all of them agree on the rules of the election.
+
+use crypto::signature::{blind_sign, digital_sign};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::candidate_id::{CandidateId, MAX_CANDIDATES};
+use crate::choice::Choice;
+use crate::timestamp::{self, Limits as TimestampLimits, Timestamp};
+use crate::vote::{self, VerificationPolicy, Vote};
+
+/// Errors that can occur when working with election configs.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// The election ends before it starts.
+    #[error("Invalid election period: {}", .0)]
+    InvalidPeriod(#[from] timestamp::Error),
+    /// There are more candidates than there are distinct candidate ids.
+    #[error("Election has {} candidates, but at most {} are supported", .0, MAX_CANDIDATES)]
+    TooManyCandidates(usize),
+    /// The name of the candidate is empty or only whitespace.
+    #[error("Candidate {} has an empty name", .0)]
+    EmptyCandidateName(CandidateId),
+    /// The name of the candidate is longer than `MAX_CANDIDATE_NAME_LEN` characters.
+    #[error("Name of candidate {} is {} characters long, but at most {} are allowed", .0, .1, MAX_CANDIDATE_NAME_LEN)]
+    CandidateNameTooLong(CandidateId, usize),
+    /// The name of the candidate contains control characters, which would break its display.
+    #[error("Name of candidate {} cannot contain control characters", .0)]
+    CandidateNameControlCharacters(CandidateId),
+    /// Two candidates share the same id.
+    #[error("Candidate id {} is used more than once", .0)]
+    DuplicateCandidate(CandidateId),
+    /// The voter chose a candidate which is not running in the election.
+    #[error("Candidate id {} is not running in this election", .0)]
+    UnknownCandidate(CandidateId),
+    /// The voter wrote in a candidate, but the election does not allow write-ins.
+    #[error("Write-in candidates are not allowed in this election")]
+    WriteInsNotAllowed,
+    /// The voter abstained, but the election does not record abstentions.
+    #[error("Abstentions are not allowed in this election")]
+    AbstentionsNotAllowed,
+    /// The config could not be serialized for hashing.
+    #[error("Failed to serialize config: {}", .0)]
+    Serialization(#[from] serde_json::Error),
+    /// The config is not signed, so its origin can't be verified.
+    #[error("Config is not signed")]
+    MissingSignature,
+    /// The signature of the config doesn't match the config or the root key.
+    #[error("Invalid config signature: {}", .0)]
+    InvalidSignature(#[from] digital_sign::Error),
+    /// The public key of the election authority could not be parsed.
+    #[error("Invalid authority public key: {}", .0)]
+    InvalidAuthorityKey(blind_sign::Error),
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// Reasons for which `ElectionConfig::verify_vote` rejects a vote.
+#[derive(Error, Debug)]
+pub enum VoteRejection {
+    /// The election does not accept votes yet.
+    #[error("the election starts at {}", .0)]
+    NotStarted(Timestamp),
+    /// The election no longer accepts votes.
+    #[error("the election ended at {}", .0)]
+    Ended(Timestamp),
+    /// The choice of the vote is not allowed in the election.
+    #[error(transparent)]
+    Choice(Error),
+    /// The vote itself is invalid.
+    #[error(transparent)]
+    Vote(#[from] vote::Error),
+    /// The vote could not be verified, because the config is invalid.
+    #[error("Invalid election config: {}", .0)]
+    InvalidConfig(Error),
+    /// The vote could not be verified, because the authority key of the config is invalid.
+    #[error("Invalid authority public key: {}", .0)]
+    InvalidAuthorityKey(blind_sign::Error),
+}
+
+/// Maximum length of a candidate name in characters.
+pub const MAX_CANDIDATE_NAME_LEN: usize = 128;
+
+/// A candidate that can be voted for in the election.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Candidate {
+    /// Human readable name of the candidate.
+    pub name: String,
+    /// Id of the candidate which is used in the votes.
+    pub id: CandidateId,
+}
+
+impl Candidate {
+    /// Validate that the name of the candidate can be safely displayed.
+    ///
+    /// # Errors
+    ///
+    /// If the name is empty, too long or contains control characters.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(Error::EmptyCandidateName(self.id.clone()));
+        }
+        let len = self.name.chars().count();
+        if len > MAX_CANDIDATE_NAME_LEN {
+            return Err(Error::CandidateNameTooLong(self.id.clone(), len));
+        }
+        if self.name.chars().any(char::is_control) {
+            return Err(Error::CandidateNameControlCharacters(self.id.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+/// The configuration of a single election.
+/// Unknown fields are rejected, so that a typo in an optional field doesn't silently fall back
+/// to its default.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+#[allow(clippy::module_name_repetitions)]
+pub struct ElectionConfig {
+    /// Name of the election.
+    pub name: String,
+    /// The time from which votes are accepted.
+    pub start: Timestamp,
+    /// The time until which votes are accepted.
+    pub end: Timestamp,
+    /// The public key of the election authority used to verify that the voters are eligible.
+    pub authority_key: blind_sign::PublicKey,
+    /// The candidates that can be voted for.
+    pub candidates: Vec<Candidate>,
+    /// Whether voters may vote for candidates which are not present in `candidates`.
+    #[serde(default)]
+    pub allow_write_ins: bool,
+    /// How many seconds a vote timestamp may be outside of the election period, to tolerate
+    /// the drift of the voters' clocks.
+    #[serde(default)]
+    pub max_clock_skew_secs: u64,
+    /// Whether voters may deliberately abstain, see `Choice::Abstain`.
+    /// Omitted when false, so that the hashes of existing configs are not affected.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_abstentions: bool,
+    /// Whether votes without a nonce are accepted, for clients which don't generate nonces.
+    /// Omitted when false, so that the hashes of existing configs are not affected.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_missing_nonces: bool,
+    /// Signature of the config by the root key of the election, see `ElectionConfig::sign`.
+    /// Omitted when absent, so that the hashes of unsigned configs are not affected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<digital_sign::Signature>,
+}
+
+impl ElectionConfig {
+    /// Validate that the config describes a sensible election.
+    ///
+    /// # Errors
+    ///
+    /// If the election period is inverted, if there are more than `MAX_CANDIDATES` candidates,
+    /// if candidate ids are not unique or if any candidate name is invalid.
+    pub fn validate(&self) -> Result<()> {
+        self.timestamp_limits()?;
+        if self.candidates.len() > MAX_CANDIDATES {
+            return Err(Error::TooManyCandidates(self.candidates.len()));
+        }
+        let mut ids = std::collections::HashSet::new();
+        for candidate in &self.candidates {
+            candidate.validate()?;
+            if !ids.insert(&candidate.id) {
+                return Err(Error::DuplicateCandidate(candidate.id.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the limits for the timestamps of the votes cast in this election.
+    ///
+    /// # Errors
+    ///
+    /// If the election ends before it starts.
+    pub fn timestamp_limits(&self) -> Result<TimestampLimits> {
+        Ok(TimestampLimits::builder()
+            .start(self.start)
+            .end(self.end)
+            .skew(std::time::Duration::from_secs(self.max_clock_skew_secs))
+            .build()?)
+    }
+
+    /// Get the policy by which the votes cast in this election are verified.
+    ///
+    /// # Errors
+    ///
+    /// If the election ends before it starts.
+    pub fn verification_policy(&self) -> Result<VerificationPolicy> {
+        Ok(VerificationPolicy {
+            timestamp_limits: self.timestamp_limits()?,
+            require_nonce: !self.allow_missing_nonces,
+        })
+    }
+
+    /// Check that the election is accepting votes at the time.
+    /// Unlike the timestamps of the votes, the period is enforced without any clock skew,
+    /// since the clock of the node decides when the election closes.
+    ///
+    /// # Errors
+    ///
+    /// If the election has not started yet or has already ended.
+    pub fn check_open(&self, now: Timestamp) -> std::result::Result<(), VoteRejection> {
+        if now < self.start {
+            return Err(VoteRejection::NotStarted(self.start));
+        }
+        if now > self.end {
+            return Err(VoteRejection::Ended(self.end));
+        }
+
+        Ok(())
+    }
+
+    /// Run the full verification of a vote submitted to this election: the election must be
+    /// open, the choice must be allowed and the vote must satisfy the verification policy.
+    /// Replays can't be detected from a single vote, so they are left to the caller.
+    /// The config is turned into an `ElectionContext` on every call, which should be used
+    /// directly to verify more than one vote.
+    ///
+    /// # Arguments
+    ///
+    /// - `vote` - The submitted vote.
+    /// - `now` - The time at which the vote was received.
+    ///
+    /// # Errors
+    ///
+    /// If the vote is rejected or if the config is invalid.
+    pub fn verify_vote(
+        &self,
+        vote: &Vote,
+        now: Timestamp,
+    ) -> std::result::Result<(), VoteRejection> {
+        self.context()?.verify_vote(vote, now)
+    }
+
+    /// Same as `verify_vote`, but without checking that the election is open at the moment,
+    /// like when the votes are verified after the election.
+    ///
+    /// # Errors
+    ///
+    /// If the vote is rejected or if the config is invalid.
+    pub fn verify_cast_vote(&self, vote: &Vote) -> std::result::Result<(), VoteRejection> {
+        self.context()?.verify_cast_vote(vote)
+    }
+
+    /// Derive the context of a single vote verification from the config.
+    fn context(&self) -> std::result::Result<ElectionContext, VoteRejection> {
+        ElectionContext::new(self.clone()).map_err(|e| match e {
+            Error::InvalidAuthorityKey(e) => VoteRejection::InvalidAuthorityKey(e),
+            e => VoteRejection::InvalidConfig(e),
+        })
+    }
+
+    /// Hash the config, so that the parties of the election can check that they all use the
+    /// same one. Votes cast under a different config can't be verified by the nodes.
+    ///
+    /// # Returns
+    ///
+    /// The hex encoded BLAKE3 hash of the JSON serialized config.
+    ///
+    /// # Errors
+    ///
+    /// If the config could not be serialized.
+    pub fn hash(&self) -> Result<String> {
+        // The fields are always serialized in the order of declaration, so the bytes are stable.
+        let bytes = serde_json::to_vec(self)?;
+        Ok(blake3::hash(&bytes).to_hex().to_string())
+    }
+
+    /// The bytes which are signed by the root key of the election, which are the JSON serialized
+    /// config without the signature.
+    fn signed_bytes(&self) -> Result<Vec<u8>> {
+        let unsigned = Self {
+            signature: None,
+            ..self.clone()
+        };
+        Ok(serde_json::to_vec(&unsigned)?)
+    }
+
+    /// Sign the config with the root key of the election, so that the clients can check that
+    /// the config served by a node had not been altered.
+    ///
+    /// # Errors
+    ///
+    /// If the config could not be serialized.
+    pub fn sign(&mut self, root_signer: &digital_sign::Signer) -> Result<()> {
+        self.signature = Some(root_signer.sign(&self.signed_bytes()?));
+        Ok(())
+    }
+
+    /// Verify that the config was signed with the root key of the election.
+    ///
+    /// # Arguments
+    ///
+    /// - `root_pk` - The public key of the root key of the election, obtained out of band.
+    ///
+    /// # Errors
+    ///
+    /// If the config is not signed or if the signature doesn't match the config or the key.
+    pub fn verify_signature(&self, root_pk: &digital_sign::PublicKey) -> Result<()> {
+        let signature = self.signature.as_ref().ok_or(Error::MissingSignature)?;
+        digital_sign::verify(&self.signed_bytes()?, signature, root_pk)?;
+
+        Ok(())
+    }
+
+    /// Look up a candidate running in this election.
+    /// Shared by the client and the node, so that both accept the same candidates.
+    ///
+    /// # Arguments
+    ///
+    /// - `id` - Id of the candidate chosen by the voter.
+    ///
+    /// # Returns
+    ///
+    /// The candidate with the id.
+    ///
+    /// # Errors
+    ///
+    /// If no candidate in the config has the id.
+    pub fn validate_candidate(&self, id: &CandidateId) -> Result<&Candidate> {
+        self.candidates
+            .iter()
+            .find(|candidate| candidate.id == *id)
+            .ok_or_else(|| Error::UnknownCandidate(id.clone()))
+    }
+
+    /// Check that a ballot choice is permitted in this election.
+    ///
+    /// # Errors
+    ///
+    /// If the chosen candidate is unknown or if the choice is a write-in or an abstention, but
+    /// those are not allowed.
+    pub fn validate_choice(&self, choice: &Choice) -> Result<()> {
+        match choice {
+            Choice::Candidate(id) => self.validate_candidate(id).map(|_| ()),
+            Choice::WriteIn(_) if self.allow_write_ins => Ok(()),
+            Choice::WriteIn(_) => Err(Error::WriteInsNotAllowed),
+            Choice::Abstain if self.allow_abstentions => Ok(()),
+            Choice::Abstain => Err(Error::AbstentionsNotAllowed),
+        }
+    }
+}
+
+/// An election config together with everything derived from it which is needed to verify the
+/// votes, so that the authority key is parsed only once instead of for every vote.
+pub struct ElectionContext {
+    /// The config from which the context is derived.
+    config: ElectionConfig,
+    /// Verifier of the access tokens, parsed from the authority key of the config.
+    access_token_verifier: blind_sign::Verifier,
+    /// The policy by which the votes are verified.
+    policy: VerificationPolicy,
+}
+
+impl ElectionContext {
+    /// Validate the config and derive the context from it.
+    ///
+    /// # Arguments
+    ///
+    /// - `config` - The config of the election.
+    ///
+    /// # Errors
+    ///
+    /// If the config is invalid or contains an invalid authority public key.
+    pub fn new(config: ElectionConfig) -> Result<Self> {
+        config.validate()?;
+        // Parsing an RSA key is expensive, so it's done once here instead of for every vote.
+        let access_token_verifier = blind_sign::Verifier::new(config.authority_key.clone())
+            .map_err(Error::InvalidAuthorityKey)?;
+        blind_sign::assert_options_match(
+            &blind_sign::OptionsFingerprint::protocol(),
+            &access_token_verifier.options_fingerprint(),
+        )
+        .map_err(Error::InvalidAuthorityKey)?;
+        let policy = config.verification_policy()?;
+
+        Ok(Self {
+            config,
+            access_token_verifier,
+            policy,
+        })
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &ElectionConfig {
+        &self.config
+    }
+
+    /// Run the full verification of a vote submitted to this election, see
+    /// `ElectionConfig::verify_vote`.
+    ///
+    /// # Arguments
+    ///
+    /// - `vote` - The submitted vote.
+    /// - `now` - The time at which the vote was received.
+    ///
+    /// # Errors
+    ///
+    /// If the vote is rejected.
+    pub fn verify_vote(
+        &self,
+        vote: &Vote,
+        now: Timestamp,
+    ) -> std::result::Result<(), VoteRejection> {
+        self.config.check_open(now)?;
+        self.verify_cast_vote(vote)
+    }
+
+    /// Same as `verify_vote`, but without checking that the election is open at the moment,
+    /// like when the votes are verified after the election.
+    ///
+    /// # Errors
+    ///
+    /// If the vote is rejected.
+    pub fn verify_cast_vote(&self, vote: &Vote) -> std::result::Result<(), VoteRejection> {
+        self.config
+            .validate_choice(vote.get_choice())
+            .map_err(VoteRejection::Choice)?;
+
+        Ok(vote.verify(&self.access_token_verifier, &self.policy)?)
+    }
+}
+
+/// An election config published by a node, together with its hash.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HashedConfig {
+    /// The config of the election.
+    pub config: ElectionConfig,
+    /// The hash of the config, see `ElectionConfig::hash`.
+    pub config_hash: String,
+}
+
+impl HashedConfig {
+    /// Hash the config.
+    ///
+    /// # Errors
+    ///
+    /// If the config could not be serialized.
+    pub fn new(config: ElectionConfig) -> Result<Self> {
+        Ok(Self {
+            config_hash: config.hash()?,
+            config,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::choice::WriteIn;
+    use crate::test_utils;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_validate() {
+        let mut config = test_utils::make_config("".parse().unwrap());
+        config.validate().unwrap();
+
+        config.candidates.push(config.candidates[0].clone());
+        assert!(matches!(
+            config.validate(),
+            Err(Error::DuplicateCandidate(_))
+        ));
+
+        let mut config = test_utils::make_config("".parse().unwrap());
+        std::mem::swap(&mut config.start, &mut config.end);
+        assert!(matches!(config.validate(), Err(Error::InvalidPeriod(_))));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_candidate_names() {
+        let mut config = test_utils::make_config("".parse().unwrap());
+        config.candidates[1].name = "Jüri Ratas".to_owned();
+        config.validate().unwrap();
+
+        config.candidates[1].name = " \t".to_owned();
+        assert!(matches!(
+            config.validate(),
+            Err(Error::EmptyCandidateName(id)) if id == CandidateId::new(1)
+        ));
+
+        config.candidates[1].name = "a".repeat(MAX_CANDIDATE_NAME_LEN);
+        config.validate().unwrap();
+        config.candidates[1].name.push('a');
+        assert!(matches!(
+            config.validate(),
+            Err(Error::CandidateNameTooLong(_, 129))
+        ));
+
+        for name in ["Jane\nDoe", "Jane\u{1b}[31mDoe"] {
+            config.candidates[1].name = name.to_owned();
+            assert!(matches!(
+                config.validate(),
+                Err(Error::CandidateNameControlCharacters(_))
+            ));
+        }
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_max_candidates() {
+        let mut config = test_utils::make_config("".parse().unwrap());
+        config.candidates = (0..=u8::MAX)
+            .map(|id| Candidate {
+                name: format!("Candidate {id}"),
+                id: CandidateId::new(id),
+            })
+            .collect();
+        assert_eq!(config.candidates.len(), MAX_CANDIDATES);
+        config.validate().unwrap();
+
+        config.candidates.push(Candidate {
+            name: "One too many".to_owned(),
+            id: CandidateId::new(0),
+        });
+        let err = config.validate().unwrap_err();
+        assert!(matches!(err, Error::TooManyCandidates(257)));
+        assert_eq!(
+            err.to_string(),
+            "Election has 257 candidates, but at most 256 are supported"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_write_ins() {
+        let mut config = test_utils::make_config("".parse().unwrap());
+        let write_in = Choice::from(WriteIn::new("Jane Doe").unwrap());
+
+        config.validate_choice(&CandidateId::new(1).into()).unwrap();
+        assert!(matches!(
+            config.validate_choice(&CandidateId::new(3).into()),
+            Err(Error::UnknownCandidate(_))
+        ));
+        assert!(matches!(
+            config.validate_choice(&write_in),
+            Err(Error::WriteInsNotAllowed)
+        ));
+
+        config.allow_write_ins = true;
+        config.validate_choice(&write_in).unwrap();
+
+        assert!(matches!(
+            config.validate_choice(&Choice::Abstain),
+            Err(Error::AbstentionsNotAllowed)
+        ));
+        config.allow_abstentions = true;
+        config.validate_choice(&Choice::Abstain).unwrap();
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_validate_candidate() {
+        let config = test_utils::make_config("".parse().unwrap());
+
+        let candidate = config.validate_candidate(&CandidateId::new(2)).unwrap();
+        assert_eq!(candidate.name, "Candidate 2");
+        assert!(matches!(
+            config.validate_candidate(&CandidateId::new(3)),
+            Err(Error::UnknownCandidate(id)) if id == CandidateId::new(3)
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_hash() {
+        let config = test_utils::make_config("".parse().unwrap());
+        let hash = config.hash().unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(config.clone().hash().unwrap(), hash);
+        let json = serde_json::to_string(&config).unwrap();
+        let parsed: ElectionConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.hash().unwrap(), hash);
+
+        let mut changed = config.clone();
+        changed.allow_write_ins = true;
+        assert_ne!(changed.hash().unwrap(), hash);
+        let mut changed = config;
+        changed.candidates[0].name = "Candidate 3".to_owned();
+        assert_ne!(changed.hash().unwrap(), hash);
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_signature() {
+        let root_signer = digital_sign::Signer::new().unwrap();
+        let root_pk = root_signer.get_public_key();
+        let mut config = test_utils::make_config("".parse().unwrap());
+        let unsigned_hash = config.hash().unwrap();
+        assert!(matches!(
+            config.verify_signature(&root_pk),
+            Err(Error::MissingSignature)
+        ));
+
+        config.sign(&root_signer).unwrap();
+        config.verify_signature(&root_pk).unwrap();
+        assert_ne!(config.hash().unwrap(), unsigned_hash);
+        let json = serde_json::to_string(&config).unwrap();
+        let parsed: ElectionConfig = serde_json::from_str(&json).unwrap();
+        parsed.verify_signature(&root_pk).unwrap();
+
+        // A node altering the candidates can't produce a valid signature.
+        let mut tampered = config.clone();
+        tampered.candidates[0].name = "Mallory".to_owned();
+        assert!(matches!(
+            tampered.verify_signature(&root_pk),
+            Err(Error::InvalidSignature(_))
+        ));
+        let other_pk = digital_sign::Signer::new().unwrap().get_public_key();
+        assert!(config.verify_signature(&other_pk).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_unknown_fields() {
+        let config = test_utils::make_config("".parse().unwrap());
+        let mut json = serde_json::to_value(&config).unwrap();
+        json["allow_writeins"] = true.into();
+        let err = serde_json::from_value::<ElectionConfig>(json)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("unknown field `allow_writeins`"), "{err}");
+
+        let mut json = serde_json::to_value(&config).unwrap();
+        json["candidates"][0]["nmae"] = "Candidate 1".into();
+        let err = serde_json::from_value::<ElectionConfig>(json)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("unknown field `nmae`"), "{err}");
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_verify_vote() {
+        let authority = test_utils::make_authorities(1).remove(0);
+        let config = test_utils::make_config(authority.get_public_key().unwrap());
+        let now = chrono::Utc::now();
+        let minute = std::time::Duration::from_secs(60);
+
+        let vote = test_utils::make_vote_at(&authority, now, CandidateId::new(1));
+        config.verify_vote(&vote, now).unwrap();
+
+        let expired = test_utils::make_vote_at(&authority, now - 2 * minute, CandidateId::new(1));
+        assert!(matches!(
+            config.verify_vote(&expired, now),
+            Err(VoteRejection::Vote(vote::Error::InvalidTimestmap(_)))
+        ));
+        assert!(matches!(
+            config.verify_vote(&vote, now + 2 * minute),
+            Err(VoteRejection::Ended(end)) if end == config.end
+        ));
+        let unknown = test_utils::make_vote_at(&authority, now, CandidateId::new(3));
+        assert!(matches!(
+            config.verify_vote(&unknown, now),
+            Err(VoteRejection::Choice(Error::UnknownCandidate(_)))
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_election_context() {
+        let authority = test_utils::make_authorities(1).remove(0);
+        let config = test_utils::make_config(authority.get_public_key().unwrap());
+        let now = chrono::Utc::now();
+        // A single context, and so a single parsed authority key, verifies all the votes.
+        let context = ElectionContext::new(config.clone()).unwrap();
+        assert_eq!(context.config(), &config);
+        for candidate in 0..3 {
+            let vote = test_utils::make_vote_at(&authority, now, CandidateId::new(candidate));
+            context.verify_vote(&vote, now).unwrap();
+            context.verify_cast_vote(&vote).unwrap();
+            config.verify_vote(&vote, now).unwrap();
+        }
+        // The context rejects the same votes as the config.
+        let unknown = test_utils::make_vote_at(&authority, now, CandidateId::new(3));
+        assert!(matches!(
+            context.verify_vote(&unknown, now),
+            Err(VoteRejection::Choice(Error::UnknownCandidate(_)))
+        ));
+        let vote = test_utils::make_vote_at(&authority, now, CandidateId::new(1));
+        assert!(matches!(
+            context.verify_vote(&vote, config.end + std::time::Duration::from_secs(1)),
+            Err(VoteRejection::Ended(_))
+        ));
+        let other_authority = test_utils::make_authorities(1).remove(0);
+        let foreign = test_utils::make_vote_at(&other_authority, now, CandidateId::new(1));
+        assert!(matches!(
+            context.verify_vote(&foreign, now),
+            Err(VoteRejection::Vote(vote::Error::AccessTokenVerification(_)))
+        ));
+
+        // Invalid configs are refused upfront instead of on every vote.
+        let mut invalid = config;
+        std::mem::swap(&mut invalid.start, &mut invalid.end);
+        assert!(matches!(
+            ElectionContext::new(invalid),
+            Err(Error::InvalidPeriod(_))
+        ));
+    }
+}
@@ -0,0 +1,254 @@
+//! Proofs that a vote is included in a block of the blockchain.
+//! The votes of every block are the leaves of a Merkle Tree, so a voter who kept their vote can
+//! check that it's included in the block from the root and the Merkle path alone, without
+//! downloading the whole block or the rest of the blockchain.
+
+use crypto::merkle::{self, MerkleProof, MerkleTree};
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::config::{ElectionConfig, VoteRejection};
+use crate::vote::Vote;
+
+/// Errors that can occur when proving or verifying the inclusion of a vote.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// The Merkle Tree of the votes could not be built or queried.
+    #[error(transparent)]
+    Merkle(#[from] merkle::Error),
+    /// The vote could not be serialized for hashing.
+    #[error("Binary serialization error: {}", .0)]
+    BinSerialization(#[from] bincode::Error),
+    /// The proof is for a block with a different root.
+    #[error("Inclusion proof is for a different block")]
+    RootMismatch,
+    /// The proof doesn't show that the vote is included in the block.
+    #[error("Vote is not included in the block")]
+    NotIncluded,
+    /// The vote itself is not valid in the election.
+    #[error("Vote rejected: {}", .0)]
+    VoteRejected(#[from] VoteRejection),
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// The hashes stored in the Merkle Tree of the votes of a block.
+pub type Hash = [u8; digest::SHA256_OUTPUT_LEN];
+
+/// Prefix of the hashed leaves, so that a leaf can't be passed off as a node and vice versa.
+const LEAF_PREFIX: u8 = 0;
+/// Prefix of the hashed nodes.
+const NODE_PREFIX: u8 = 1;
+
+fn hash(prefix: u8, parts: &[&[u8]]) -> Hash {
+    let mut context = digest::Context::new(&digest::SHA256);
+    context.update(&[prefix]);
+    for part in parts {
+        context.update(part);
+    }
+    let mut hash = [0; digest::SHA256_OUTPUT_LEN];
+    hash.copy_from_slice(context.finish().as_ref());
+    hash
+}
+
+/// Hash the vote the same way the leaves of the tree are hashed.
+/// The votes are serialized with bincode, same as when the blocks are hashed.
+fn hash_vote(vote: &Vote) -> Result<Hash> {
+    Ok(hash(LEAF_PREFIX, &[&bincode::serialize(vote)?]))
+}
+
+fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    hash(NODE_PREFIX, &[left, right])
+}
+
+/// Build the Merkle Tree of the votes of a block.
+fn vote_tree(votes: &[Vote]) -> Result<MerkleTree<Hash, Hash>> {
+    let leaves = votes.iter().map(hash_vote).collect::<Result<Vec<_>>>()?;
+
+    Ok(MerkleTree::new(
+        &leaves,
+        Box::new(hash_node),
+        Box::new(|leaf| *leaf),
+    )?)
+}
+
+/// Proof that a vote is included in the block at a height.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InclusionProof {
+    /// Height of the block containing the vote.
+    pub height: usize,
+    /// Root of the Merkle Tree of the votes of the block.
+    pub merkle_root: Hash,
+    /// The Merkle path from the vote to the root.
+    pub merkle_proof: MerkleProof<Hash>,
+}
+
+impl InclusionProof {
+    /// Prove that the vote at the index is included in the block.
+    ///
+    /// # Arguments
+    ///
+    /// - `height` - Height of the block.
+    /// - `votes` - The votes of the block.
+    /// - `index` - Index of the vote among the votes of the block.
+    ///
+    /// # Errors
+    ///
+    /// If the index is out of bounds or if the votes could not be serialized.
+    pub fn new(height: usize, votes: &[Vote], index: usize) -> Result<Self> {
+        let tree = vote_tree(votes)?;
+
+        Ok(Self {
+            height,
+            merkle_root: tree.get_root()?,
+            merkle_proof: tree.get_proof(index)?,
+        })
+    }
+
+    /// Verify that the proof shows that the vote is included in the block with the root of
+    /// this proof. The root itself must be checked against the block by the caller.
+    ///
+    /// # Errors
+    ///
+    /// If the vote could not be serialized.
+    pub fn verify(&self, vote: &Vote) -> Result<bool> {
+        Ok(self.merkle_proof.root == self.merkle_root
+            && self.merkle_proof.verify(hash_vote(vote)?, hash_node))
+    }
+}
+
+/// Verify a single vote without the blockchain, like a light client would: the vote must be
+/// valid in the election and the proof must show that it's included in the block.
+/// The election doesn't have to be open anymore, since votes are usually checked afterwards.
+///
+/// # Arguments
+///
+/// - `vote` - The vote to verify.
+/// - `inclusion_proof` - The proof of the inclusion of the vote in the block.
+/// - `block_root` - The root of the Merkle Tree of the votes of the block, obtained from a
+///   trusted source instead of from the proof.
+/// - `config` - The config of the election.
+///
+/// # Errors
+///
+/// If the proof is for another block, if the vote is not included in the block or if the vote
+/// is invalid.
+pub fn light_verify(
+    vote: &Vote,
+    inclusion_proof: &InclusionProof,
+    block_root: &Hash,
+    config: &ElectionConfig,
+) -> Result<()> {
+    if inclusion_proof.merkle_root != *block_root {
+        return Err(Error::RootMismatch);
+    }
+    if !inclusion_proof.verify(vote)? {
+        return Err(Error::NotIncluded);
+    }
+
+    Ok(config.verify_cast_vote(vote)?)
+}
+
+/// Get the root of the Merkle Tree of the votes of a block.
+///
+/// # Errors
+///
+/// If the block has no votes or if the votes could not be serialized.
+pub fn merkle_root(votes: &[Vote]) -> Result<Hash> {
+    Ok(vote_tree(votes)?.get_root()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{candidate_id::CandidateId, test_utils};
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_inclusion_proof() {
+        let authorities = test_utils::make_authorities(1);
+        let votes: Vec<_> = (0..5)
+            .map(|candidate| {
+                test_utils::make_valid_vote(&authorities[0], CandidateId::new(candidate))
+            })
+            .collect();
+
+        for (index, vote) in votes.iter().enumerate() {
+            let proof = InclusionProof::new(3, &votes, index).unwrap();
+            assert_eq!(proof.height, 3);
+            assert_eq!(proof.merkle_root, merkle_root(&votes).unwrap());
+            assert!(proof.verify(vote).unwrap());
+        }
+
+        // The proof doesn't verify for other votes or roots.
+        let mut proof = InclusionProof::new(3, &votes, 0).unwrap();
+        assert!(!proof.verify(&votes[1]).unwrap());
+        proof.merkle_root = [0; digest::SHA256_OUTPUT_LEN];
+        assert!(!proof.verify(&votes[0]).unwrap());
+
+        assert!(InclusionProof::new(3, &votes, 5).is_err());
+        assert!(merkle_root(&[]).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_light_verify() {
+        let authorities = test_utils::make_authorities(2);
+        let config = test_utils::make_config(authorities[0].get_public_key().unwrap());
+        let votes: Vec<_> = (0..3)
+            .map(|candidate| {
+                test_utils::make_valid_vote(&authorities[0], CandidateId::new(candidate))
+            })
+            .collect();
+        let root = merkle_root(&votes).unwrap();
+        let proof = InclusionProof::new(7, &votes, 1).unwrap();
+        light_verify(&votes[1], &proof, &root, &config).unwrap();
+
+        // A vote which isn't the one the proof is for.
+        assert!(matches!(
+            light_verify(&votes[2], &proof, &root, &config),
+            Err(Error::NotIncluded)
+        ));
+
+        // A tampered path.
+        let mut tampered = proof.clone();
+        tampered.merkle_proof.proof[0][0] ^= 1;
+        assert!(matches!(
+            light_verify(&votes[1], &tampered, &root, &config),
+            Err(Error::NotIncluded)
+        ));
+
+        // A root of another block, even with a proof matching it.
+        let other_votes = &votes[..2];
+        let other_proof = InclusionProof::new(8, other_votes, 1).unwrap();
+        assert!(matches!(
+            light_verify(&votes[1], &other_proof, &root, &config),
+            Err(Error::RootMismatch)
+        ));
+        assert!(matches!(
+            light_verify(
+                &votes[1],
+                &proof,
+                &merkle_root(other_votes).unwrap(),
+                &config
+            ),
+            Err(Error::RootMismatch)
+        ));
+
+        // An included vote which isn't valid in the election.
+        let forged = test_utils::make_valid_vote(&authorities[1], CandidateId::new(1));
+        let forged_votes = [votes[0].clone(), forged];
+        let forged_proof = InclusionProof::new(9, &forged_votes, 1).unwrap();
+        assert!(matches!(
+            light_verify(
+                &forged_votes[1],
+                &forged_proof,
+                &merkle_root(&forged_votes).unwrap(),
+                &config
+            ),
+            Err(Error::VoteRejected(VoteRejection::Vote(_)))
+        ));
+    }
+}
@@ -1,8 +1,16 @@
 //! Crate which describes the protocol and fundamental operation of the blockchain.
 
 pub mod candidate_id;
+pub mod choice;
+pub mod config;
+pub mod inclusion;
+pub mod proof_of_work;
+pub mod signature_scheme;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 pub mod timestamp;
 pub mod vote;
+pub mod voter_registry;
 
 // Configuration for wasm-bindgen-test to run tests in browser.
 #[cfg(test)]
@@ -0,0 +1,83 @@
+//! Hashcash style client puzzles, which the nodes can require before accepting a vote, so that
+//! flooding them with spam is expensive. A node issues a random challenge and the client has to
+//! find a nonce, such that the SHA-256 hash of the challenge followed by the nonce starts with
+//! the required amount of zero bits.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+
+/// Length of a challenge in bytes.
+pub const CHALLENGE_LEN: usize = 16;
+
+/// The header in which the solution is submitted, formatted as `<base64 challenge>:<nonce>`.
+pub const SOLUTION_HEADER: &str = "X-Pow-Solution";
+
+/// A challenge issued to a client.
+#[serde_as]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Challenge {
+    /// The random bytes which must be hashed together with the nonce.
+    #[serde_as(as = "Base64")]
+    pub challenge: [u8; CHALLENGE_LEN],
+    /// Amount of leading zero bits which the hash must have.
+    pub difficulty: u8,
+}
+
+impl Challenge {
+    /// Find a nonce which solves the challenge.
+    ///
+    /// # Returns
+    ///
+    /// The value of the solution header.
+    #[must_use]
+    pub fn solve(&self) -> String {
+        let nonce = (0..=u64::MAX)
+            .find(|nonce| meets_difficulty(&self.challenge, *nonce, self.difficulty))
+            .unwrap_or_default();
+        format!("{}:{nonce}", BASE64.encode(self.challenge))
+    }
+}
+
+/// Check whether the hash of the challenge and the nonce starts with enough zero bits.
+#[must_use]
+pub fn meets_difficulty(challenge: &[u8], nonce: u64, difficulty: u8) -> bool {
+    let mut context = digest::Context::new(&digest::SHA256);
+    context.update(challenge);
+    context.update(&nonce.to_le_bytes());
+    let hash = context.finish();
+
+    let mut zero_bits = 0;
+    for byte in hash.as_ref() {
+        zero_bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    zero_bits >= u32::from(difficulty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        let challenge = Challenge {
+            challenge: [7; CHALLENGE_LEN],
+            difficulty: 8,
+        };
+        let solution = challenge.solve();
+        let (encoded, nonce) = solution.split_once(':').unwrap();
+        assert_eq!(BASE64.decode(encoded).unwrap(), challenge.challenge);
+        assert!(meets_difficulty(
+            &challenge.challenge,
+            nonce.parse().unwrap(),
+            8
+        ));
+
+        let json = serde_json::to_string(&challenge).unwrap();
+        assert_eq!(serde_json::from_str::<Challenge>(&json).unwrap(), challenge);
+    }
+}
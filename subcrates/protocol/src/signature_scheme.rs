@@ -0,0 +1,122 @@
+//! The signature schemes with which the voters sign their votes.
+//! Every vote records the scheme it was signed with, so that new schemes, like post-quantum
+//! ones, can be introduced without breaking the verification of the votes signed with Ed25519.
+
+use crypto::signature::digital_sign::{self, PublicKey, SealedSigner, Signature, Signer};
+use serde::{Deserialize, Serialize};
+
+/// The signature schemes with which votes can be signed.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureScheme {
+    /// Ed25519 from `digital_sign`.
+    #[default]
+    Ed25519,
+}
+
+impl SignatureScheme {
+    /// Get the id of the scheme, which is covered by the signature of the vote, so that the
+    /// scheme can't be swapped without invalidating the vote.
+    #[must_use]
+    pub fn id(self) -> u8 {
+        match self {
+            Self::Ed25519 => 0,
+        }
+    }
+
+    /// Get the verifier of the signatures made with the scheme.
+    #[must_use]
+    pub fn verifier(self) -> &'static dyn VoteVerifier {
+        match self {
+            Self::Ed25519 => &Ed25519Verifier,
+        }
+    }
+}
+
+impl std::fmt::Display for SignatureScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ed25519 => write!(f, "Ed25519"),
+        }
+    }
+}
+
+/// Signer of the votes of a voter.
+pub trait VoteSigner {
+    /// The scheme with which the votes are signed.
+    fn scheme(&self) -> SignatureScheme;
+
+    /// The public key of the voter, with which the votes are verified.
+    fn public_key(&self) -> PublicKey;
+
+    /// Sign the signed fields of a vote.
+    ///
+    /// # Errors
+    ///
+    /// If the secret key of the voter could not be used.
+    fn sign(&self, message: &[u8]) -> Result<Signature, digital_sign::Error>;
+}
+
+impl VoteSigner for Signer {
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Ed25519
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.get_public_key()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Signature, digital_sign::Error> {
+        Ok(Signer::sign(self, message))
+    }
+}
+
+impl VoteSigner for SealedSigner {
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Ed25519
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.get_public_key().clone()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Signature, digital_sign::Error> {
+        SealedSigner::sign(self, message)
+    }
+}
+
+/// Verifier of the signatures of the votes made with a single scheme.
+pub trait VoteVerifier {
+    /// The scheme of the signatures which can be verified.
+    fn scheme(&self) -> SignatureScheme;
+
+    /// Verify the signature of the signed fields of a vote.
+    ///
+    /// # Errors
+    ///
+    /// If the signature is invalid.
+    fn verify(
+        &self,
+        message: &[u8],
+        signature: &Signature,
+        public_key: &PublicKey,
+    ) -> Result<(), digital_sign::Error>;
+}
+
+/// Verifier of the votes signed with `SignatureScheme::Ed25519`.
+pub struct Ed25519Verifier;
+
+impl VoteVerifier for Ed25519Verifier {
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Ed25519
+    }
+
+    fn verify(
+        &self,
+        message: &[u8],
+        signature: &Signature,
+        public_key: &PublicKey,
+    ) -> Result<(), digital_sign::Error> {
+        digital_sign::verify(message, signature, public_key)
+    }
+}
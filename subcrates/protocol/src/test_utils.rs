@@ -0,0 +1,218 @@
+//! Helpers for generating election authorities and votes in tests.
+//! Available in this crate's own tests and, behind the `test-utils` feature, in downstream crates,
+//! so that they don't have to duplicate the blind signing flow required to produce a valid vote.
+//! The helpers panic on failure, since they are only meant to be used in tests.
+//!
+//! # Example
+//!
+//! ```
+//! use crypto::signature::blind_sign;
+//! use protocol::{candidate_id::CandidateId, test_utils, timestamp::Limits, vote::VerificationPolicy};
+//!
+//! let authorities = test_utils::make_authorities(1);
+//! let vote = test_utils::make_valid_vote(&authorities[0], CandidateId::new(1));
+//!
+//! let verifier = blind_sign::Verifier::new(authorities[0].get_public_key().unwrap()).unwrap();
+//! let now = chrono::Utc::now();
+//! let limits = Limits::new(now - std::time::Duration::from_secs(60), now).unwrap();
+//! vote.verify(&verifier, &VerificationPolicy::new(limits)).unwrap();
+//! ```
+
+use crypto::signature::{blind_sign, digital_sign};
+
+use crate::candidate_id::CandidateId;
+use crate::choice::Choice;
+use crate::config::{Candidate, ElectionConfig};
+use crate::timestamp::Timestamp;
+use crate::vote::Vote;
+
+/// Name of the election created by `make_config`.
+pub const TEST_ELECTION: &str = "test";
+
+/// Create the config of an election which is ongoing at the time of the call.
+///
+/// # Arguments
+///
+/// - `authority_key` - The public key of the authority of the election.
+///
+/// # Returns
+///
+/// The unsigned config of an election named `TEST_ELECTION` with three candidates, which started a
+/// minute ago and ends in a minute.
+#[must_use]
+pub fn make_config(authority_key: blind_sign::PublicKey) -> ElectionConfig {
+    let now = chrono::Utc::now();
+    ElectionConfig {
+        name: TEST_ELECTION.to_owned(),
+        start: now - std::time::Duration::from_secs(60),
+        end: now + std::time::Duration::from_secs(60),
+        authority_key,
+        candidates: (0..3)
+            .map(|id| Candidate {
+                name: format!("Candidate {id}"),
+                id: CandidateId::new(id),
+            })
+            .collect(),
+        allow_write_ins: false,
+        max_clock_skew_secs: 0,
+        allow_abstentions: false,
+        allow_missing_nonces: false,
+        signature: None,
+    }
+}
+
+/// Create election authorities, each with a freshly generated key pair.
+///
+/// # Arguments
+///
+/// - `n` - The amount of authorities to create.
+///
+/// # Returns
+///
+/// The blind signers of the authorities.
+///
+/// # Panics
+///
+/// If key generation fails.
+#[must_use]
+pub fn make_authorities(n: usize) -> Vec<blind_sign::BlindSigner> {
+    (0..n)
+        .map(|_| blind_sign::BlindSigner::new().expect("Failed to generate authority keys"))
+        .collect()
+}
+
+/// Get an access token for the voter, blind signed by the authority.
+///
+/// # Arguments
+///
+/// - `authority` - The authority which blind signs the access token of the voter.
+/// - `voter` - The digital signer of the voter, whose public key is signed.
+///
+/// # Returns
+///
+/// The unblinded access token.
+///
+/// # Panics
+///
+/// If any of the cryptographic operations fail.
+#[must_use]
+pub fn make_access_token(
+    authority: &blind_sign::BlindSigner,
+    voter: &digital_sign::Signer,
+) -> blind_sign::Signature {
+    let msg = voter.get_public_key();
+    let blinder = blind_sign::Blinder::new(
+        authority
+            .get_public_key()
+            .expect("Failed to get authority public key"),
+    )
+    .expect("Failed to create blinder");
+    let (blind_msg, unblinder) = blinder.blind(&msg).expect("Failed to blind message");
+    let blind_signature = authority
+        .bling_sign(&blind_msg)
+        .expect("Failed to blind sign message");
+    unblinder
+        .unblind_signature(blind_signature, &msg)
+        .expect("Failed to unblind signature")
+}
+
+/// Create a vote with an access token issued by the authority and a specific timestamp.
+///
+/// # Arguments
+///
+/// - `authority` - The authority which blind signs the access token of the voter.
+/// - `timestamp` - The timestamp of the vote.
+/// - `choice` - The choice on the ballot.
+///
+/// # Returns
+///
+/// The signed vote of a freshly generated voter.
+///
+/// # Panics
+///
+/// If any of the cryptographic operations fail.
+#[must_use]
+pub fn make_vote_at(
+    authority: &blind_sign::BlindSigner,
+    timestamp: Timestamp,
+    choice: impl Into<Choice>,
+) -> Vote {
+    let digital_signer = digital_sign::Signer::new().expect("Failed to generate voter keys");
+    let access_token = make_access_token(authority, &digital_signer);
+
+    Vote::new(&digital_signer, choice, timestamp, &access_token).expect("Failed to create vote")
+}
+
+/// Create a vote which is valid at the time of the call.
+///
+/// # Arguments
+///
+/// - `authority` - The authority which blind signs the access token of the voter.
+/// - `choice` - The choice on the ballot.
+///
+/// # Returns
+///
+/// The signed vote, timestamped with the current time.
+///
+/// # Panics
+///
+/// If any of the cryptographic operations fail.
+#[must_use]
+pub fn make_valid_vote(authority: &blind_sign::BlindSigner, choice: impl Into<Choice>) -> Vote {
+    make_vote_at(authority, chrono::Utc::now(), choice)
+}
+
+/// Create a vote which is otherwise valid, but was cast after the election had ended.
+///
+/// # Arguments
+///
+/// - `authority` - The authority which blind signs the access token of the voter.
+/// - `end` - The end of the election period.
+/// - `choice` - The choice on the ballot.
+///
+/// # Returns
+///
+/// The signed vote, timestamped one second after the end of the election.
+///
+/// # Panics
+///
+/// If any of the cryptographic operations fail.
+#[must_use]
+pub fn make_expired_vote(
+    authority: &blind_sign::BlindSigner,
+    end: Timestamp,
+    choice: impl Into<Choice>,
+) -> Vote {
+    make_vote_at(authority, end + std::time::Duration::from_secs(1), choice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::candidate_id::CandidateId;
+    use crate::timestamp::Limits;
+    use crate::vote::VerificationPolicy;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_expired_vote() {
+        let authorities = make_authorities(2);
+        let verifier = blind_sign::Verifier::new(authorities[0].get_public_key().unwrap()).unwrap();
+        let now = chrono::Utc::now();
+        let policy = VerificationPolicy::new(
+            Limits::new(now - std::time::Duration::from_secs(60), now).unwrap(),
+        );
+
+        make_vote_at(&authorities[0], now, CandidateId::new(1))
+            .verify(&verifier, &policy)
+            .unwrap();
+        assert!(make_expired_vote(&authorities[0], now, CandidateId::new(1))
+            .verify(&verifier, &policy)
+            .is_err());
+        assert!(make_vote_at(&authorities[1], now, CandidateId::new(1))
+            .verify(&verifier, &policy)
+            .is_err());
+    }
+}
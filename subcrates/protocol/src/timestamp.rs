@@ -6,8 +6,14 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum Error {
     /// The timestamp lower limit cannot be larger than the upper limit.
-    #[error("Timestamp lower limit cannot be larger than upper limit")]
-    InvalidLimits,
+    #[error("Timestamp lower limit {start} is larger than upper limit {end}")]
+    InvertedRange { start: Timestamp, end: Timestamp },
+    /// The limits were built without the lower limit.
+    #[error("Timestamp lower limit is not set")]
+    MissingStart,
+    /// The limits were built without the upper limit.
+    #[error("Timestamp upper limit is not set")]
+    MissingEnd,
 }
 type Result<T> = std::result::Result<T, Error>;
 
@@ -20,6 +26,8 @@ pub struct Limits {
     timestamp_lower_limit: Timestamp,
     /// Upper limit for an acceptable timestamp.
     timestamp_upper_limit: Timestamp,
+    /// Tolerated clock skew, by which both limits are widened.
+    skew: std::time::Duration,
 }
 
 impl Limits {
@@ -36,15 +44,37 @@ impl Limits {
     /// If lower limit is larger than upper limit.
     pub fn new(timestamp_lower_limit: Timestamp, timestamp_upper_limit: Timestamp) -> Result<Self> {
         if timestamp_lower_limit > timestamp_upper_limit {
-            return Err(Error::InvalidLimits);
+            return Err(Error::InvertedRange {
+                start: timestamp_lower_limit,
+                end: timestamp_upper_limit,
+            });
         }
 
         Ok(Self {
             timestamp_lower_limit,
             timestamp_upper_limit,
+            skew: std::time::Duration::ZERO,
         })
     }
 
+    /// Start building limits, both of which must be set before building.
+    #[must_use]
+    pub fn builder() -> LimitsBuilder {
+        LimitsBuilder::default()
+    }
+
+    /// Tolerate timestamps which are off by up to the skew, since the clocks of the voters drift.
+    /// Both limits are widened by the skew, which is zero by default.
+    ///
+    /// # Arguments
+    ///
+    /// `skew` - The maximum tolerated clock skew.
+    #[must_use]
+    pub fn with_skew(mut self, skew: std::time::Duration) -> Self {
+        self.skew = skew;
+        self
+    }
+
     /// Verify the input timestamp against the upper and lower limits.
     /// Note that limits are inclusive.
     ///
@@ -57,7 +87,60 @@ impl Limits {
     /// `true`, if the timestmap is valid and `false` if it is not.
     #[must_use]
     pub fn verify(&self, timestamp: Timestamp) -> bool {
-        timestamp >= self.timestamp_lower_limit && timestamp <= self.timestamp_upper_limit
+        // A skew too large to be represented leaves the limit unbounded.
+        let skew = chrono::TimeDelta::from_std(self.skew).ok();
+        let lower_limit = skew
+            .and_then(|skew| self.timestamp_lower_limit.checked_sub_signed(skew))
+            .unwrap_or(Timestamp::MIN_UTC);
+        let upper_limit = skew
+            .and_then(|skew| self.timestamp_upper_limit.checked_add_signed(skew))
+            .unwrap_or(Timestamp::MAX_UTC);
+
+        timestamp >= lower_limit && timestamp <= upper_limit
+    }
+}
+
+/// Builder of `Limits`, so that the limits can be set by name.
+#[derive(Default)]
+pub struct LimitsBuilder {
+    start: Option<Timestamp>,
+    end: Option<Timestamp>,
+    skew: std::time::Duration,
+}
+
+impl LimitsBuilder {
+    /// Set the inclusive lower limit.
+    #[must_use]
+    pub fn start(mut self, start: Timestamp) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Set the inclusive upper limit.
+    #[must_use]
+    pub fn end(mut self, end: Timestamp) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    /// Set the tolerated clock skew, see `Limits::with_skew`.
+    #[must_use]
+    pub fn skew(mut self, skew: std::time::Duration) -> Self {
+        self.skew = skew;
+        self
+    }
+
+    /// Build the limits.
+    ///
+    /// # Errors
+    ///
+    /// If the start or the end is not set or if the start is later than the end.
+    pub fn build(self) -> Result<Limits> {
+        Ok(Limits::new(
+            self.start.ok_or(Error::MissingStart)?,
+            self.end.ok_or(Error::MissingEnd)?,
+        )?
+        .with_skew(self.skew))
     }
 }
 
@@ -78,6 +161,62 @@ mod tests {
         assert!(!timestamp_limits.verify(timestamp + std::time::Duration::from_nanos(1)));
         assert!(!timestamp_limits.verify(timestamp - std::time::Duration::from_nanos(1)));
 
-        assert!(Limits::new(timestamp + std::time::Duration::from_nanos(1), timestamp).is_err());
+        let later = timestamp + std::time::Duration::from_nanos(1);
+        assert!(matches!(
+            Limits::new(later, timestamp),
+            Err(Error::InvertedRange { start, end }) if start == later && end == timestamp
+        ));
+        assert!(matches!(
+            Limits::builder().start(later).end(timestamp).build(),
+            Err(Error::InvertedRange { .. })
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_limits_builder() {
+        let start = chrono::Utc::now();
+        let end = start + std::time::Duration::from_secs(60);
+        let skew = std::time::Duration::from_secs(5);
+
+        let timestamp_limits = Limits::builder()
+            .start(start)
+            .end(end)
+            .skew(skew)
+            .build()
+            .unwrap();
+        assert!(timestamp_limits.verify(start - skew));
+        assert!(timestamp_limits.verify(end + skew));
+        assert!(!timestamp_limits.verify(end + skew + std::time::Duration::from_nanos(1)));
+
+        assert!(matches!(
+            Limits::builder().start(start).build(),
+            Err(Error::MissingEnd)
+        ));
+        assert!(matches!(
+            Limits::builder().end(end).skew(skew).build(),
+            Err(Error::MissingStart)
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_timestamp_skew() {
+        let start = chrono::Utc::now();
+        let end = start + std::time::Duration::from_secs(60);
+        let skew = std::time::Duration::from_secs(5);
+        let nanosecond = std::time::Duration::from_nanos(1);
+
+        let timestamp_limits = Limits::new(start, end).unwrap().with_skew(skew);
+        assert!(timestamp_limits.verify(start - skew));
+        assert!(timestamp_limits.verify(end + skew));
+        assert!(!timestamp_limits.verify(start - skew - nanosecond));
+        assert!(!timestamp_limits.verify(end + skew + nanosecond));
+
+        let timestamp_limits = Limits::new(start, end)
+            .unwrap()
+            .with_skew(std::time::Duration::MAX);
+        assert!(timestamp_limits.verify(Timestamp::MIN_UTC));
+        assert!(timestamp_limits.verify(Timestamp::MAX_UTC));
     }
 }
@@ -5,10 +5,14 @@ use crypto::{
     self,
     signature::{blind_sign, digital_sign},
 };
+use ring::rand::{SecureRandom, SystemRandom};
 use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
 use thiserror::Error;
 
 use crate::candidate_id::CandidateId;
+use crate::choice::{self, Choice, WriteIn};
+use crate::signature_scheme::{SignatureScheme, VoteSigner, VoteVerifier};
 use crate::timestamp::{Limits as TimestampLimits, Timestamp};
 
 /// Errors that can occur when working with election votes.
@@ -26,22 +30,84 @@ pub enum Error {
     /// The timestamp is invalid.
     #[error("Timestamp is invalid: {}", .0)]
     InvalidTimestmap(Timestamp),
+    /// Failed to generate a random nonce for the vote.
+    #[error("Failed to generate vote nonce")]
+    NonceGeneration,
+    /// The JSON of the vote could not be serialized or deserialized.
+    #[error("Invalid vote JSON: {}", .0)]
+    ApiJson(#[from] serde_json::Error),
+    /// The JSON of the vote is in a version of the API shape which is not supported.
+    #[error("Unsupported vote API version: {}", .0)]
+    UnsupportedApiVersion(u32),
+    /// The write-in name in the JSON of the vote is invalid.
+    #[error("Invalid write-in: {}", .0)]
+    InvalidWriteIn(#[from] choice::Error),
+    /// The vote has no nonce, which the verification policy requires.
+    #[error("Vote has no nonce")]
+    MissingNonce,
+    /// The signer failed to sign the vote.
+    #[error("Failed to sign vote: {}", .0)]
+    Signing(digital_sign::Error),
+    /// The vote was signed with a different scheme than the verifier verifies.
+    #[error("Vote is signed with {}, but the verifier verifies {}", .actual, .expected)]
+    SchemeMismatch {
+        expected: SignatureScheme,
+        actual: SignatureScheme,
+    },
 }
 type Result<T> = std::result::Result<T, Error>;
 
+/// Length of the vote nonce in bytes.
+pub const NONCE_LEN: usize = 16;
+
+/// Random value making every vote unique, so that nodes can detect a replayed vote.
+pub type Nonce = [u8; NONCE_LEN];
+
+/// The nonce of votes cast without one, which can't be told apart from replays by the nonce.
+pub const MISSING_NONCE: Nonce = [0; NONCE_LEN];
+
+/// How strictly the votes of an election are verified, see `Vote::verify`.
+/// Assembled from the election config with `ElectionConfig::verification_policy`, so that all
+/// the parties of the election verify the votes the same way.
+pub struct VerificationPolicy {
+    /// The time window in which the votes must be cast, including the tolerated clock skew.
+    pub timestamp_limits: TimestampLimits,
+    /// Whether the votes must carry a nonce, see `MISSING_NONCE`. Replays of votes without a
+    /// nonce can only be detected by their access tokens.
+    pub require_nonce: bool,
+}
+
+impl VerificationPolicy {
+    /// Create the strictest policy for the time window.
+    #[must_use]
+    pub fn new(timestamp_limits: TimestampLimits) -> Self {
+        Self {
+            timestamp_limits,
+            require_nonce: true,
+        }
+    }
+}
+
 /// Structure of a vote in the blockchain.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Vote {
     /// Digital signature public key of the blockchain user who cast the vote.
     public_key: digital_sign::PublicKey,
     /// The candidate for whom the vote is being cast.
-    candidate: CandidateId,
+    choice: Choice,
+    /// The time at which the vote was cast.
+    timestamp: Timestamp,
     /// Access token issued by the election authority.
     /// It is a blind signature used to sign the `public_key` of the user.
     /// The public key of this signature is the public key of the election authority.
     /// Each access token on the blockchain must be unique.
-    timestamp: Timestamp,
     access_token: blind_sign::Signature,
+    /// Random nonce generated for every vote.
+    /// Nodes track the nonces which they have seen to reject replays of the exact same vote,
+    /// independently of tracking the access tokens.
+    nonce: Nonce,
+    /// The scheme with which the vote is signed.
+    scheme: SignatureScheme,
     /// Digital signature corresponding to the `public_key`.
     /// It signs all previous fields.
     signature: digital_sign::Signature,
@@ -52,8 +118,8 @@ impl Vote {
     ///
     /// # Arguments
     ///
-    /// - `signer` - Digital signer used to sign messages with the blockchain user's public key.
-    /// - `candidate` - The candidate for whom the vote is being cast.
+    /// - `signer` - Signer of the blockchain user, whose scheme is recorded in the vote.
+    /// - `choice` - The candidate for whom the vote is being cast, either from the config or a write-in.
     /// - `access_token` - Access token issued by the election authority, needed to write to the blockchain.
     ///
     /// # Returns
@@ -62,55 +128,123 @@ impl Vote {
     ///
     /// # Errors
     ///
-    /// If serializing the struct to bytes for signing fails.
+    /// If generating the nonce, serializing the struct to bytes for signing or signing fails.
     pub fn new(
-        signer: &digital_sign::Signer,
-        candidate: CandidateId,
+        signer: &impl VoteSigner,
+        choice: impl Into<Choice>,
         timestamp: Timestamp,
         access_token: &blind_sign::Signature,
     ) -> Result<Self> {
-        let public_key = signer.get_public_key();
-        let to_sign = Self::signed_bytes(&public_key, &candidate, &timestamp, access_token)?;
+        let mut nonce = [0; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| Error::NonceGeneration)?;
 
-        Ok(Self {
-            public_key,
-            candidate,
-            timestamp,
-            access_token: access_token.clone(),
-            signature: signer.sign(&to_sign),
-        })
-    }
-
-    #[must_use]
-    pub fn get_candidate(&self) -> &CandidateId {
-        &self.candidate
+        Self::new_with_nonce(signer, choice, timestamp, access_token, nonce)
     }
 
-    /// Create new Vote to be sent to the blockchain.
+    /// Same as `new`, but with a nonce provided by the caller instead of a random one.
+    /// The nonce must never be reused, otherwise the vote would be rejected as a replay.
     ///
     /// # Arguments
     ///
-    /// - `signer` - Digital signer used to sign messages with the blockchain user's public key.
-    /// - `candidate` - The candidate for whom the vote is being cast.
+    /// - `signer` - Signer of the blockchain user, whose scheme is recorded in the vote.
+    /// - `choice` - The candidate for whom the vote is being cast, either from the config or a write-in.
     /// - `access_token` - Access token issued by the election authority, needed to write to the blockchain.
+    /// - `nonce` - The unique nonce of the vote.
     ///
     /// # Returns
     ///
     /// A new Vote instance.
-    fn signed_bytes(
-        public_key: &digital_sign::PublicKey,
-        candidate: &CandidateId,
-        timestamp: &Timestamp,
+    ///
+    /// # Errors
+    ///
+    /// If serializing the struct to bytes for signing or signing fails.
+    pub fn new_with_nonce(
+        signer: &impl VoteSigner,
+        choice: impl Into<Choice>,
+        timestamp: Timestamp,
         access_token: &blind_sign::Signature,
-    ) -> Result<Vec<u8>> {
-        let mut to_sign =
-            Vec::with_capacity(public_key.len() + candidate.as_ref().len() + access_token.len());
-        to_sign.extend_from_slice(public_key.as_ref());
-        to_sign.extend_from_slice(candidate.as_ref());
-        to_sign.extend_from_slice(access_token.as_ref());
-        to_sign.append(&mut bincode::serialize(&timestamp)?);
+        nonce: Nonce,
+    ) -> Result<Self> {
+        let choice = choice.into();
+        let public_key = signer.public_key();
+        let scheme = signer.scheme();
+        let to_sign = SignedFields {
+            public_key: &public_key,
+            choice: &choice,
+            timestamp: &timestamp,
+            access_token,
+            nonce: &nonce,
+            scheme,
+        }
+        .to_bytes()?;
+        let signature = signer.sign(&to_sign).map_err(Error::Signing)?;
 
-        Ok(to_sign)
+        Ok(Self {
+            public_key,
+            choice,
+            timestamp,
+            access_token: access_token.clone(),
+            nonce,
+            scheme,
+            signature,
+        })
+    }
+
+    #[must_use]
+    pub fn get_public_key(&self) -> &digital_sign::PublicKey {
+        &self.public_key
+    }
+
+    #[must_use]
+    pub fn get_choice(&self) -> &Choice {
+        &self.choice
+    }
+
+    #[must_use]
+    pub fn get_timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    #[must_use]
+    pub fn get_access_token(&self) -> &blind_sign::Signature {
+        &self.access_token
+    }
+
+    #[must_use]
+    pub fn get_nonce(&self) -> &Nonce {
+        &self.nonce
+    }
+
+    #[must_use]
+    pub fn get_scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+
+    /// Get the id by which the vote is referred to in receipts and inclusion proofs.
+    /// The id is the nonce of the vote, or for a vote without a nonce the hash of its access
+    /// token, since the access tokens are what tells such votes apart in an election.
+    #[must_use]
+    pub fn id(&self) -> Nonce {
+        if self.nonce != MISSING_NONCE {
+            return self.nonce;
+        }
+        let mut id = [0; NONCE_LEN];
+        id.copy_from_slice(&blake3::hash(self.access_token.as_ref()).as_bytes()[..NONCE_LEN]);
+        id
+    }
+
+    /// Get references to all the fields of the vote which are covered by the signature.
+    fn signed_fields(&self) -> SignedFields<'_> {
+        SignedFields {
+            public_key: &self.public_key,
+            choice: &self.choice,
+            timestamp: &self.timestamp,
+            access_token: &self.access_token,
+            nonce: &self.nonce,
+            scheme: self.scheme,
+        }
     }
 
     /// Verify an isntance of a vote.
@@ -118,30 +252,243 @@ impl Vote {
     /// # Arguments
     ///
     /// - `access_token_verifyer` - Verifyer of the blind signature of the election authority.
+    /// - `policy` - How strictly the vote is verified.
     ///
     /// # Errors
     ///
-    /// If the vote is invalid or corrupted.
+    /// If the vote is invalid or corrupted or doesn't satisfy the policy.
     pub fn verify(
         &self,
         access_token_verifyer: &blind_sign::Verifier,
-        timestamp_limits: &TimestampLimits,
+        policy: &VerificationPolicy,
     ) -> Result<()> {
-        if !timestamp_limits.verify(self.timestamp) {
+        if !policy.timestamp_limits.verify(self.timestamp) {
             return Err(Error::InvalidTimestmap(self.timestamp));
         }
+        if policy.require_nonce && self.nonce == MISSING_NONCE {
+            return Err(Error::MissingNonce);
+        }
         access_token_verifyer.verify_signature(self.access_token.clone(), &self.public_key)?;
-        let signed_bytes = Self::signed_bytes(
-            &self.public_key,
-            &self.candidate,
-            &self.timestamp,
-            &self.access_token,
-        )?;
-        Ok(digital_sign::verify(
-            &signed_bytes,
-            &self.signature,
-            &self.public_key,
-        )?)
+        self.verify_signature_with(self.scheme.verifier())
+    }
+
+    /// Verify only the signature of the voter over the vote, with a verifier of the scheme the
+    /// vote is signed with.
+    ///
+    /// # Arguments
+    ///
+    /// - `verifier` - Verifier of the signatures of the scheme.
+    ///
+    /// # Errors
+    ///
+    /// If the vote is signed with another scheme or if the signature is invalid.
+    pub fn verify_signature_with(&self, verifier: &dyn VoteVerifier) -> Result<()> {
+        if verifier.scheme() != self.scheme {
+            return Err(Error::SchemeMismatch {
+                expected: verifier.scheme(),
+                actual: self.scheme,
+            });
+        }
+        let signed_bytes = self.signed_fields().to_bytes()?;
+        Ok(verifier.verify(&signed_bytes, &self.signature, &self.public_key)?)
+    }
+}
+
+/// The fields of a vote which are covered by the vote's signature.
+/// Both signing and verification must go through this struct, so that the fields are always
+/// encoded in the same order.
+struct SignedFields<'a> {
+    public_key: &'a digital_sign::PublicKey,
+    choice: &'a Choice,
+    timestamp: &'a Timestamp,
+    access_token: &'a blind_sign::Signature,
+    nonce: &'a Nonce,
+    scheme: SignatureScheme,
+}
+
+impl SignedFields<'_> {
+    /// Canonically encode the signed fields into bytes for signing or verification.
+    /// The fields are encoded in the same order as they are declared in the `Vote` struct.
+    ///
+    /// # Returns
+    ///
+    /// The bytes to be signed.
+    ///
+    /// # Errors
+    ///
+    /// If serializing the timestamp fails.
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let choice = self.choice.to_bytes();
+        let timestamp = bincode::serialize(self.timestamp)?;
+        let mut to_sign = Vec::with_capacity(
+            self.public_key.len()
+                + choice.len()
+                + timestamp.len()
+                + self.access_token.len()
+                + self.nonce.len()
+                + 1,
+        );
+        to_sign.extend_from_slice(self.public_key.as_ref());
+        to_sign.extend_from_slice(&choice);
+        to_sign.extend_from_slice(&timestamp);
+        to_sign.extend_from_slice(self.access_token.as_ref());
+        to_sign.extend_from_slice(self.nonce);
+        to_sign.push(self.scheme.id());
+
+        Ok(to_sign)
+    }
+}
+
+impl Vote {
+    /// Serialize the vote into the external JSON shape used by the API, see `ApiVote`.
+    ///
+    /// # Returns
+    ///
+    /// The JSON string of the vote.
+    ///
+    /// # Errors
+    ///
+    /// If serialization fails.
+    pub fn to_api_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&ApiVote::from(self))?)
+    }
+
+    /// Deserialize the vote from the external JSON shape used by the API, see `ApiVote`.
+    /// Note that the vote is not verified.
+    ///
+    /// # Arguments
+    ///
+    /// - `json` - The JSON string of the vote.
+    ///
+    /// # Returns
+    ///
+    /// The deserialized vote.
+    ///
+    /// # Errors
+    ///
+    /// If the JSON is malformed, if its version is not supported or if the choice is invalid.
+    pub fn from_api_json(json: &str) -> Result<Self> {
+        serde_json::from_str::<ApiVote>(json)?.try_into()
+    }
+}
+
+/// Version of the external JSON shape of votes, which must be bumped on any breaking change.
+pub const API_VERSION: u32 = 1;
+
+/// Check whether votes in the version of the API shape are supported, so that clients can
+/// find out before building a vote which would be rejected.
+///
+/// # Arguments
+///
+/// - `version` - The version of the API shape which the client produces.
+///
+/// # Errors
+///
+/// If the version is not supported.
+pub fn check_api_version(version: u32) -> Result<()> {
+    if version != API_VERSION {
+        return Err(Error::UnsupportedApiVersion(version));
+    }
+
+    Ok(())
+}
+
+/// The external JSON shape of a vote, in which votes are exchanged between the node and clients.
+/// It is decoupled from the layout of `Vote`, so that the internals can be refactored without
+/// breaking the clients. Binary fields are encoded in base64 and the timestamp in RFC 3339:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "voter_public_key": "<base64>",
+///   "choice": { "candidate": 1 } | { "write_in": "Jane Doe" } | "abstain",
+///   "cast_at": "2024-11-05T12:00:00Z",
+///   "access_token": "<base64>",
+///   "nonce": "<base64>",
+///   "signature_scheme": "ed25519",
+///   "signature": "<base64>"
+/// }
+/// ```
+#[serde_as]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ApiVote {
+    /// Version of the shape, always `API_VERSION` when produced by this crate.
+    pub version: u32,
+    /// Digital signature public key of the voter.
+    pub voter_public_key: digital_sign::PublicKey,
+    /// The choice on the ballot.
+    pub choice: ApiChoice,
+    /// The time at which the vote was cast.
+    pub cast_at: Timestamp,
+    /// Access token issued by the election authority.
+    pub access_token: blind_sign::Signature,
+    /// The unique nonce of the vote.
+    #[serde_as(as = "Base64")]
+    pub nonce: Nonce,
+    /// The scheme with which the vote is signed, Ed25519 if omitted by older clients.
+    #[serde(default)]
+    pub signature_scheme: SignatureScheme,
+    /// Digital signature of the voter over the vote.
+    pub signature: digital_sign::Signature,
+}
+
+/// The external JSON shape of a choice.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiChoice {
+    /// Id of a candidate from the election config.
+    Candidate(u8),
+    /// Name of a write-in candidate.
+    WriteIn(String),
+    /// A deliberate abstention.
+    Abstain,
+}
+
+impl From<&Choice> for ApiChoice {
+    fn from(choice: &Choice) -> Self {
+        match choice {
+            Choice::Candidate(candidate) => Self::Candidate(candidate.get()),
+            Choice::WriteIn(write_in) => Self::WriteIn(write_in.to_string()),
+            Choice::Abstain => Self::Abstain,
+        }
+    }
+}
+
+impl From<&Vote> for ApiVote {
+    fn from(vote: &Vote) -> Self {
+        Self {
+            version: API_VERSION,
+            voter_public_key: vote.public_key.clone(),
+            choice: ApiChoice::from(&vote.choice),
+            cast_at: vote.timestamp,
+            access_token: vote.access_token.clone(),
+            nonce: vote.nonce,
+            signature_scheme: vote.scheme,
+            signature: vote.signature.clone(),
+        }
+    }
+}
+
+impl TryFrom<ApiVote> for Vote {
+    type Error = Error;
+
+    fn try_from(vote: ApiVote) -> Result<Self> {
+        check_api_version(vote.version)?;
+
+        Ok(Self {
+            public_key: vote.voter_public_key,
+            choice: match vote.choice {
+                ApiChoice::Candidate(candidate) => CandidateId::new(candidate).into(),
+                ApiChoice::WriteIn(name) => WriteIn::new(&name)?.into(),
+                ApiChoice::Abstain => Choice::Abstain,
+            },
+            timestamp: vote.cast_at,
+            access_token: vote.access_token,
+            nonce: vote.nonce,
+            scheme: vote.signature_scheme,
+            signature: vote.signature,
+        })
     }
 }
 
@@ -149,9 +496,9 @@ impl std::fmt::Display for Vote {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{} voted for candidate {} on {}",
+            "{} voted for {} on {}",
             self.public_key,
-            self.candidate,
+            self.choice,
             self.timestamp.format("%Y-%m-%d %H:%M:%S")
         )
     }
@@ -161,25 +508,19 @@ impl std::fmt::Display for Vote {
 mod tests {
     use super::*;
 
+    use crate::signature_scheme::Ed25519Verifier;
+    use crate::test_utils;
+    use crypto::signature::digital_sign::SealedSigner;
     use wasm_bindgen_test::wasm_bindgen_test;
 
     fn generate_vote_for_testing(
         timestamp: Timestamp,
-        candidate: CandidateId,
+        candidate: impl Into<Choice>,
     ) -> (Vote, blind_sign::PublicKey) {
-        let blind_signer = blind_sign::BlindSigner::new().unwrap();
-        let authority_pubkey = blind_signer.get_public_key().unwrap();
-        let digital_signer = digital_sign::Signer::new().unwrap();
-        let msg = digital_signer.get_public_key();
-        let blinder = blind_sign::Blinder::new(blind_signer.get_public_key().unwrap()).unwrap();
-        let (blind_msg, unblinder) = blinder.blind(&msg).unwrap();
-        let blind_signature = blind_signer.bling_sign(&blind_msg).unwrap();
-        let access_token = unblinder
-            .unblind_signature(blind_signature.clone(), &msg)
-            .unwrap();
-        let vote = Vote::new(&digital_signer, candidate, timestamp, &access_token).unwrap();
+        let authority = test_utils::make_authorities(1).remove(0);
+        let vote = test_utils::make_vote_at(&authority, timestamp, candidate);
 
-        (vote, authority_pubkey)
+        (vote, authority.get_public_key().unwrap())
     }
 
     // TODO Not sure if it's a good idea to couple this test to crypto subcrate.
@@ -195,6 +536,233 @@ mod tests {
             timestamp + std::time::Duration::from_secs(1),
         )
         .unwrap();
-        vote.verify(&verifier, &timestamp_limits).unwrap();
+        let policy = VerificationPolicy::new(timestamp_limits);
+        vote.verify(&verifier, &policy).unwrap();
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_signed_fields_order() {
+        let now = chrono::Utc::now();
+        // Fresh keys are generated for every vote, so the signed bytes differ on each iteration.
+        for candidate in 0..4 {
+            let timestamp = now + std::time::Duration::from_secs(u64::from(candidate) * 3600);
+            let (vote, authority_pubkey) =
+                generate_vote_for_testing(timestamp, CandidateId::new(candidate));
+
+            // Encoding the fields by hand in the declared order must produce the signed bytes.
+            let mut expected = Vec::new();
+            expected.extend_from_slice(vote.public_key.as_ref());
+            expected.append(&mut vote.choice.to_bytes());
+            expected.append(&mut bincode::serialize(&vote.timestamp).unwrap());
+            expected.extend_from_slice(vote.access_token.as_ref());
+            expected.extend_from_slice(&vote.nonce);
+            expected.push(vote.scheme.id());
+            assert_eq!(vote.signed_fields().to_bytes().unwrap(), expected);
+            digital_sign::verify(&expected, &vote.signature, &vote.public_key).unwrap();
+
+            let verifier = blind_sign::Verifier::new(authority_pubkey).unwrap();
+            let timestamp_limits = TimestampLimits::new(timestamp, timestamp).unwrap();
+            let policy = VerificationPolicy::new(timestamp_limits);
+            vote.verify(&verifier, &policy).unwrap();
+        }
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_write_in_vote() {
+        let timestamp = chrono::Utc::now();
+        let write_in = WriteIn::new("Jane Doe").unwrap();
+        let (vote, authority_pubkey) = generate_vote_for_testing(timestamp, write_in.clone());
+        assert_eq!(vote.get_choice(), &Choice::WriteIn(write_in));
+
+        let verifier = blind_sign::Verifier::new(authority_pubkey).unwrap();
+        let timestamp_limits = TimestampLimits::new(timestamp, timestamp).unwrap();
+        let policy = VerificationPolicy::new(timestamp_limits);
+        vote.verify(&verifier, &policy).unwrap();
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_nonce() {
+        let authority = test_utils::make_authorities(1).remove(0);
+        let signer = digital_sign::Signer::new().unwrap();
+        let access_token = test_utils::make_access_token(&authority, &signer);
+        let timestamp = chrono::Utc::now();
+        let first = Vote::new_with_nonce(
+            &signer,
+            CandidateId::new(1),
+            timestamp,
+            &access_token,
+            [1; NONCE_LEN],
+        )
+        .unwrap();
+        let second = Vote::new_with_nonce(
+            &signer,
+            CandidateId::new(1),
+            timestamp,
+            &access_token,
+            [2; NONCE_LEN],
+        )
+        .unwrap();
+
+        assert_ne!(first.signature, second.signature);
+        let verifier = blind_sign::Verifier::new(authority.get_public_key().unwrap()).unwrap();
+        let timestamp_limits = TimestampLimits::new(timestamp, timestamp).unwrap();
+        let policy = VerificationPolicy::new(timestamp_limits);
+        first.verify(&verifier, &policy).unwrap();
+        second.verify(&verifier, &policy).unwrap();
+
+        // Swapping the nonce must invalidate the signature.
+        let mut replayed = first.clone();
+        replayed.nonce = *second.get_nonce();
+        assert!(replayed.verify(&verifier, &policy).is_err());
+
+        let random = Vote::new(&signer, CandidateId::new(1), timestamp, &access_token).unwrap();
+        assert_ne!(random.get_nonce(), first.get_nonce());
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_api_json() {
+        let authority = test_utils::make_authorities(1).remove(0);
+        let vote = test_utils::make_valid_vote(&authority, WriteIn::new("Jane Doe").unwrap());
+
+        let json = vote.to_api_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        // Pinning the keys, since changing them breaks the clients of the API.
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            [
+                "access_token",
+                "cast_at",
+                "choice",
+                "nonce",
+                "signature",
+                "signature_scheme",
+                "version",
+                "voter_public_key"
+            ]
+        );
+        assert_eq!(value["version"], 1);
+        assert_eq!(value["signature_scheme"], "ed25519");
+        assert_eq!(
+            value["choice"],
+            serde_json::json!({ "write_in": "Jane Doe" })
+        );
+        let candidate_vote = test_utils::make_valid_vote(&authority, CandidateId::new(3));
+        let value: serde_json::Value =
+            serde_json::from_str(&candidate_vote.to_api_json().unwrap()).unwrap();
+        assert_eq!(value["choice"], serde_json::json!({ "candidate": 3 }));
+
+        let parsed = Vote::from_api_json(&json).unwrap();
+        assert_eq!(parsed.to_api_json().unwrap(), json);
+        let verifier = blind_sign::Verifier::new(authority.get_public_key().unwrap()).unwrap();
+        let now = chrono::Utc::now();
+        let timestamp_limits =
+            TimestampLimits::new(now - std::time::Duration::from_secs(60), now).unwrap();
+        let policy = VerificationPolicy::new(timestamp_limits);
+        parsed.verify(&verifier, &policy).unwrap();
+
+        let future = json.replace("\"version\":1", "\"version\":2");
+        assert!(matches!(
+            Vote::from_api_json(&future),
+            Err(Error::UnsupportedApiVersion(2))
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_verification_policy() {
+        let authority = test_utils::make_authorities(1).remove(0);
+        let signer = digital_sign::Signer::new().unwrap();
+        let access_token = test_utils::make_access_token(&authority, &signer);
+        let timestamp = chrono::Utc::now();
+        let vote = Vote::new_with_nonce(
+            &signer,
+            CandidateId::new(1),
+            timestamp,
+            &access_token,
+            MISSING_NONCE,
+        )
+        .unwrap();
+        let verifier = blind_sign::Verifier::new(authority.get_public_key().unwrap()).unwrap();
+
+        let strict = VerificationPolicy::new(TimestampLimits::new(timestamp, timestamp).unwrap());
+        assert!(matches!(
+            vote.verify(&verifier, &strict),
+            Err(Error::MissingNonce)
+        ));
+
+        let lenient = VerificationPolicy {
+            require_nonce: false,
+            ..VerificationPolicy::new(TimestampLimits::new(timestamp, timestamp).unwrap())
+        };
+        vote.verify(&verifier, &lenient).unwrap();
+
+        // The policy doesn't loosen the other checks.
+        let late = VerificationPolicy {
+            require_nonce: false,
+            ..VerificationPolicy::new(
+                TimestampLimits::new(
+                    timestamp - std::time::Duration::from_secs(60),
+                    timestamp - std::time::Duration::from_secs(1),
+                )
+                .unwrap(),
+            )
+        };
+        assert!(matches!(
+            vote.verify(&verifier, &late),
+            Err(Error::InvalidTimestmap(_))
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_signature_scheme() {
+        let authority = test_utils::make_authorities(1).remove(0);
+        let signer = digital_sign::Signer::new().unwrap();
+        let access_token = test_utils::make_access_token(&authority, &signer);
+        let vote = Vote::new(
+            &signer,
+            CandidateId::new(1),
+            chrono::Utc::now(),
+            &access_token,
+        )
+        .unwrap();
+        assert_eq!(vote.get_scheme(), SignatureScheme::Ed25519);
+        vote.verify_signature_with(&Ed25519Verifier).unwrap();
+        vote.verify_signature_with(vote.get_scheme().verifier())
+            .unwrap();
+
+        // A sealed signer signs with the same scheme.
+        let sealed = SealedSigner::new(digital_sign::Signer::new().unwrap()).unwrap();
+        let access_token = sealed
+            .with_signer(|signer| test_utils::make_access_token(&authority, signer))
+            .unwrap();
+        let vote = Vote::new(
+            &sealed,
+            CandidateId::new(1),
+            chrono::Utc::now(),
+            &access_token,
+        )
+        .unwrap();
+        assert_eq!(vote.get_public_key(), sealed.get_public_key());
+        vote.verify_signature_with(&Ed25519Verifier).unwrap();
+
+        // Older clients don't send the scheme, which defaults to Ed25519.
+        let mut value: serde_json::Value =
+            serde_json::from_str(&vote.to_api_json().unwrap()).unwrap();
+        value.as_object_mut().unwrap().remove("signature_scheme");
+        let parsed = Vote::from_api_json(&value.to_string()).unwrap();
+        assert_eq!(parsed.get_scheme(), SignatureScheme::Ed25519);
+        parsed.verify_signature_with(&Ed25519Verifier).unwrap();
     }
 }
@@ -0,0 +1,401 @@
+//! The registry of the voters eligible to vote in an election.
+//! The election authority publishes the root of the Merkle Tree built from the ids of the
+//! registered voters and hands every voter a proof of their membership, so that the voters can
+//! prove their eligibility without revealing which registered voter they are.
+
+use std::collections::HashMap;
+
+use crypto::merkle::{self, MerkleProof, MerkleTree};
+use crypto::signature::digital_sign;
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+use thiserror::Error;
+
+/// Errors that can occur when working with the voter registry.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// The Merkle Tree of the registry could not be built or queried.
+    #[error(transparent)]
+    Merkle(#[from] merkle::Error),
+    /// The same voter id was registered more than once.
+    #[error("Voter {} is registered more than once", .0)]
+    DuplicateVoter(u64),
+    /// The voter id is not in the registry.
+    #[error("Voter {} is not registered", .0)]
+    UnknownVoter(u64),
+    /// Proofs can only be generated from a registry built with the hash of the published root.
+    #[error("Membership proofs can't be generated from a {} registry", .0)]
+    UnprovableHash(HashFunction),
+    /// The signature of the manifest doesn't match the manifest or the authority key.
+    #[error("Invalid manifest signature: {}", .0)]
+    InvalidSignature(#[from] digital_sign::Error),
+}
+type Result<T> = std::result::Result<T, Error>;
+
+/// The hashes stored in the Merkle Tree of the registry.
+pub type Hash = [u8; digest::SHA256_OUTPUT_LEN];
+
+/// Prefix of the hashed leaves, so that a leaf can't be passed off as a node and vice versa.
+const LEAF_PREFIX: u8 = 0;
+/// Prefix of the hashed nodes.
+const NODE_PREFIX: u8 = 1;
+
+/// The hash function the Merkle Tree of the registry is built with.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HashFunction {
+    /// SHA-256, which the published root and the proofs handed to the voters are built with.
+    // TODO Switch to a ZKP friendly hash, like Poseidon, once the membership ZKP is implemented.
+    #[default]
+    Sha256,
+    /// BLAKE3, which is faster for building the registry only to check who is registered,
+    /// but its root doesn't match the published one, so no proofs can be generated from it.
+    Blake3,
+}
+
+impl std::fmt::Display for HashFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sha256 => write!(f, "SHA-256"),
+            Self::Blake3 => write!(f, "BLAKE3"),
+        }
+    }
+}
+
+impl HashFunction {
+    /// Get the id of the hash function, which is covered by the signature of the manifest.
+    #[must_use]
+    pub fn id(self) -> u8 {
+        match self {
+            Self::Sha256 => 0,
+            Self::Blake3 => 1,
+        }
+    }
+
+    fn hash(self, prefix: u8, parts: &[&[u8]]) -> Hash {
+        match self {
+            Self::Sha256 => {
+                let mut context = digest::Context::new(&digest::SHA256);
+                context.update(&[prefix]);
+                for part in parts {
+                    context.update(part);
+                }
+                let mut hash = [0; digest::SHA256_OUTPUT_LEN];
+                hash.copy_from_slice(context.finish().as_ref());
+                hash
+            }
+            Self::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                hasher.update(&[prefix]);
+                for part in parts {
+                    hasher.update(part);
+                }
+                hasher.finalize().into()
+            }
+        }
+    }
+
+    fn hash_leaf(self, id: u64) -> Hash {
+        self.hash(LEAF_PREFIX, &[&id.to_le_bytes()])
+    }
+
+    fn hash_node(self, left: &Hash, right: &Hash) -> Hash {
+        self.hash(NODE_PREFIX, &[left, right])
+    }
+}
+
+/// The registered voters of an election.
+pub struct VoterRegistry {
+    /// The Merkle Tree built from the voter ids in the order in which they were registered.
+    tree: MerkleTree<u64, Hash>,
+    /// Positions of the voter ids among the leaves of the tree.
+    leaf_indices: HashMap<u64, usize>,
+    /// The hash function the tree is built with.
+    hash_function: HashFunction,
+}
+
+impl VoterRegistry {
+    /// Build the registry from the ids of the registered voters.
+    ///
+    /// # Arguments
+    ///
+    /// - `ids` - The ids of the registered voters.
+    ///
+    /// # Errors
+    ///
+    /// If there are no voters or if any voter id is registered more than once.
+    pub fn from_ids(ids: &[u64]) -> Result<Self> {
+        Self::with_hash_function(ids, HashFunction::Sha256)
+    }
+
+    /// Build the registry from the ids of the registered voters with the chosen hash function.
+    /// Proofs can only be generated from a [`HashFunction::Sha256`] registry, so a faster hash
+    /// function should only be chosen if the registry is used to check who is registered.
+    ///
+    /// # Arguments
+    ///
+    /// - `ids` - The ids of the registered voters.
+    /// - `hash_function` - The hash function to build the Merkle Tree with.
+    ///
+    /// # Errors
+    ///
+    /// If there are no voters or if any voter id is registered more than once.
+    pub fn with_hash_function(ids: &[u64], hash_function: HashFunction) -> Result<Self> {
+        let mut leaf_indices = HashMap::with_capacity(ids.len());
+        for (leaf_index, id) in ids.iter().enumerate() {
+            if leaf_indices.insert(*id, leaf_index).is_some() {
+                return Err(Error::DuplicateVoter(*id));
+            }
+        }
+        let tree = MerkleTree::new(
+            ids,
+            Box::new(move |left, right| hash_function.hash_node(left, right)),
+            Box::new(move |id| hash_function.hash_leaf(*id)),
+        )?;
+
+        Ok(Self {
+            tree,
+            leaf_indices,
+            hash_function,
+        })
+    }
+
+    /// Get the hash function the registry is built with.
+    #[must_use]
+    pub fn hash_function(&self) -> HashFunction {
+        self.hash_function
+    }
+
+    /// Get the number of the registered voters.
+    #[must_use]
+    pub fn voter_count(&self) -> u64 {
+        self.leaf_indices.len() as u64
+    }
+
+    /// Check whether the voter is registered, without generating a proof.
+    #[must_use]
+    pub fn contains(&self, id: u64) -> bool {
+        self.leaf_indices.contains_key(&id)
+    }
+
+    /// Get the root of the registry, which is published by the election authority.
+    /// Only the root of a [`HashFunction::Sha256`] registry matches the published one.
+    ///
+    /// # Errors
+    ///
+    /// If the Merkle Tree of the registry is empty, which can't happen.
+    pub fn root(&self) -> Result<Hash> {
+        Ok(self.tree.get_root()?)
+    }
+
+    /// Get the proof that the voter is registered, which is handed to the voter.
+    ///
+    /// # Errors
+    ///
+    /// If the voter is not registered or if the registry isn't built with
+    /// [`HashFunction::Sha256`], since the proof must verify against the published root.
+    pub fn proof_for(&self, id: u64) -> Result<MerkleProof<Hash>> {
+        if self.hash_function != HashFunction::Sha256 {
+            return Err(Error::UnprovableHash(self.hash_function));
+        }
+        let leaf_index = self.leaf_indices.get(&id).ok_or(Error::UnknownVoter(id))?;
+
+        Ok(self.tree.get_proof(*leaf_index)?)
+    }
+
+    /// Verify that the proof shows that the voter is registered in the registry with the root.
+    ///
+    /// # Arguments
+    ///
+    /// - `root` - The published root of the registry.
+    /// - `id` - The id of the voter.
+    /// - `proof` - The proof handed to the voter.
+    #[must_use]
+    pub fn verify(root: &Hash, id: u64, proof: &MerkleProof<Hash>) -> bool {
+        proof.root == *root
+            && proof.verify(HashFunction::Sha256.hash_leaf(id), |left, right| {
+                HashFunction::Sha256.hash_node(left, right)
+            })
+    }
+}
+
+/// The signed publication of a voter registry, which the election authority publishes, so that
+/// the voters and observers can check that the proofs are verified against the official root.
+#[serde_as]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryManifest {
+    /// The root of the registry.
+    #[serde_as(as = "Base64")]
+    pub root: Hash,
+    /// The hash function the registry is built with.
+    pub hash_function: HashFunction,
+    /// The number of the registered voters.
+    pub voter_count: u64,
+    /// The signature of the election authority over all previous fields.
+    pub signature: digital_sign::Signature,
+}
+
+impl RegistryManifest {
+    /// Build the manifest of the registry and sign it with the key of the election authority.
+    ///
+    /// # Arguments
+    ///
+    /// - `registry` - The registry to publish.
+    /// - `authority_signer` - The digital signer of the election authority.
+    ///
+    /// # Errors
+    ///
+    /// If the root of the registry could not be obtained.
+    pub fn new(registry: &VoterRegistry, authority_signer: &digital_sign::Signer) -> Result<Self> {
+        let root = registry.root()?;
+        let hash_function = registry.hash_function();
+        let voter_count = registry.voter_count();
+        let signature =
+            authority_signer.sign(&Self::signed_bytes(&root, hash_function, voter_count));
+
+        Ok(Self {
+            root,
+            hash_function,
+            voter_count,
+            signature,
+        })
+    }
+
+    /// The bytes signed by the election authority, which are the fields in the declared order.
+    fn signed_bytes(root: &Hash, hash_function: HashFunction, voter_count: u64) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(root.len() + 1 + 8);
+        bytes.extend_from_slice(root);
+        bytes.push(hash_function.id());
+        bytes.extend_from_slice(&voter_count.to_le_bytes());
+        bytes
+    }
+
+    /// Verify that the manifest was signed by the election authority.
+    ///
+    /// # Arguments
+    ///
+    /// - `authority_pk` - The digital signature public key of the election authority.
+    ///
+    /// # Errors
+    ///
+    /// If the signature doesn't match the manifest or the key.
+    pub fn verify(&self, authority_pk: &digital_sign::PublicKey) -> Result<()> {
+        let signed_bytes = Self::signed_bytes(&self.root, self.hash_function, self.voter_count);
+        digital_sign::verify(&signed_bytes, &self.signature, authority_pk)?;
+
+        Ok(())
+    }
+
+    /// Verify that the proof shows that the voter is registered in the published registry.
+    /// The signature of the manifest must be verified separately with `verify`.
+    ///
+    /// # Arguments
+    ///
+    /// - `id` - The id of the voter.
+    /// - `proof` - The proof handed to the voter.
+    #[must_use]
+    pub fn verify_proof(&self, id: u64, proof: &MerkleProof<Hash>) -> bool {
+        self.hash_function == HashFunction::Sha256 && VoterRegistry::verify(&self.root, id, proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_voter_registry() {
+        let ids = [11, 22, 33, 44, 55];
+        let registry = VoterRegistry::from_ids(&ids).unwrap();
+        let root = registry.root().unwrap();
+
+        for id in ids {
+            let proof = registry.proof_for(id).unwrap();
+            assert!(VoterRegistry::verify(&root, id, &proof));
+            assert!(!VoterRegistry::verify(&root, id + 1, &proof));
+        }
+        assert!(matches!(
+            registry.proof_for(66),
+            Err(Error::UnknownVoter(66))
+        ));
+
+        let other_registry = VoterRegistry::from_ids(&[11, 22]).unwrap();
+        let proof = other_registry.proof_for(11).unwrap();
+        assert!(!VoterRegistry::verify(&root, 11, &proof));
+
+        assert!(matches!(
+            VoterRegistry::from_ids(&[11, 11]),
+            Err(Error::DuplicateVoter(11))
+        ));
+        assert!(matches!(
+            VoterRegistry::from_ids(&[]),
+            Err(Error::Merkle(merkle::Error::EmptyTree))
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_hash_functions() {
+        let ids = [11, 22, 33, 44, 55];
+        let sha256_registry = VoterRegistry::from_ids(&ids).unwrap();
+        let blake3_registry =
+            VoterRegistry::with_hash_function(&ids, HashFunction::Blake3).unwrap();
+        assert_eq!(sha256_registry.hash_function(), HashFunction::Sha256);
+        assert_eq!(blake3_registry.hash_function(), HashFunction::Blake3);
+
+        // Both registries agree on who is registered.
+        for id in 0..100 {
+            assert_eq!(sha256_registry.contains(id), blake3_registry.contains(id));
+            assert_eq!(sha256_registry.contains(id), ids.contains(&id));
+        }
+
+        // But only the SHA-256 registry matches the published root and can generate proofs.
+        assert_ne!(
+            sha256_registry.root().unwrap(),
+            blake3_registry.root().unwrap()
+        );
+        assert!(sha256_registry.proof_for(11).is_ok());
+        assert!(matches!(
+            blake3_registry.proof_for(11),
+            Err(Error::UnprovableHash(HashFunction::Blake3))
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    #[test]
+    fn test_registry_manifest() {
+        let ids = [11, 22, 33];
+        let registry = VoterRegistry::from_ids(&ids).unwrap();
+        let authority_signer = digital_sign::Signer::new().unwrap();
+        let authority_pk = authority_signer.get_public_key();
+        let manifest = RegistryManifest::new(&registry, &authority_signer).unwrap();
+        assert_eq!(manifest.root, registry.root().unwrap());
+        assert_eq!(manifest.hash_function, HashFunction::Sha256);
+        assert_eq!(manifest.voter_count, 3);
+        manifest.verify(&authority_pk).unwrap();
+
+        let json = serde_json::to_string(&manifest).unwrap();
+        let parsed: RegistryManifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, manifest);
+        parsed.verify(&authority_pk).unwrap();
+        assert!(parsed.verify_proof(22, &registry.proof_for(22).unwrap()));
+
+        // A manifest published with another root can't pass as the official one.
+        let mut tampered = manifest.clone();
+        tampered.root = VoterRegistry::from_ids(&[11, 22]).unwrap().root().unwrap();
+        assert!(matches!(
+            tampered.verify(&authority_pk),
+            Err(Error::InvalidSignature(_))
+        ));
+        let mut tampered = manifest.clone();
+        tampered.voter_count += 1;
+        assert!(tampered.verify(&authority_pk).is_err());
+        let other_pk = digital_sign::Signer::new().unwrap().get_public_key();
+        assert!(manifest.verify(&other_pk).is_err());
+    }
+}